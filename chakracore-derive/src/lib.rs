@@ -0,0 +1,399 @@
+//! Procedural macros for deriving JavaScript bindings with `chakracore`.
+//!
+//! - `#[js_function]` exposes a free function. It generates a unit struct,
+//!   prefixed with `js_`, implementing `NativeFunction`.
+//! - `#[js_class]` on a struct names a native class (`ClassName`), and on an
+//!   `impl` block it defines the class' members (`NativeClass`).
+//!
+//! Arguments are converted using `FromArgument`, and return values using
+//! `IntoReturn`, which are implemented for all `FromJs` and `IntoJs` types.
+//! Functions returning a `Result` throw the error's description as a
+//! JavaScript `Error`.
+//!
+//! Members of a class are configured using the `#[js(...)]` attribute:
+//!
+//! - `constructor`: the function used by `new` (must return `Self`, or a
+//!   `Result` of it).
+//! - `getter`/`setter`: an accessor property (a setter's `set_` prefix is
+//!   stripped from the property name).
+//! - `name = "..."`: the member's JavaScript name.
+//! - `skip`: excludes the function from the class.
+//!
+//! Methods taking `&self` or `&mut self` are installed on the prototype, and
+//! associated functions on the constructor.
+//!
+//! ```rust,ignore
+//! #[js_class]
+//! struct Point(f64, f64);
+//!
+//! #[js_class]
+//! impl Point {
+//!   #[js(constructor)]
+//!   fn new(x: f64, y: f64) -> Self {
+//!     Point(x, y)
+//!   }
+//!
+//!   fn len(&self) -> f64 {
+//!     (self.0 * self.0 + self.1 * self.1).sqrt()
+//!   }
+//! }
+//!
+//! #[js_function]
+//! fn add(a: i32, b: i32) -> i32 {
+//!   a + b
+//! }
+//!
+//! binding::register_class::<Point>(&guard);
+//! binding::register_function::<js_add>(&guard);
+//! ```
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+  parse_macro_input, AttributeArgs, FnArg, ImplItem, ImplItemMethod, Item, ItemFn, ItemImpl,
+  ItemStruct, Lit, Meta, NestedMeta, Result, Signature, Type,
+};
+
+/// Exposes a free function to JavaScript.
+#[proc_macro_attribute]
+pub fn js_function(attr: TokenStream, item: TokenStream) -> TokenStream {
+  let args = parse_macro_input!(attr as AttributeArgs);
+  let function = parse_macro_input!(item as ItemFn);
+  expand_function(args, function)
+    .unwrap_or_else(|error| error.to_compile_error())
+    .into()
+}
+
+/// Exposes a struct, and the members of its `impl` block, to JavaScript.
+#[proc_macro_attribute]
+pub fn js_class(attr: TokenStream, item: TokenStream) -> TokenStream {
+  let args = parse_macro_input!(attr as AttributeArgs);
+  let result = match parse_macro_input!(item as Item) {
+    Item::Struct(item) => expand_struct(args, item),
+    Item::Impl(item) => expand_impl(args, item),
+    item => Err(syn::Error::new(
+      item.span(),
+      "#[js_class] can only be used on structs and impl blocks",
+    )),
+  };
+
+  result
+    .unwrap_or_else(|error| error.to_compile_error())
+    .into()
+}
+
+/// The options of a `#[js(...)]` or macro attribute.
+#[derive(Default)]
+struct Options {
+  name: Option<String>,
+  constructor: bool,
+  getter: bool,
+  setter: bool,
+  skip: bool,
+}
+
+impl Options {
+  /// Parses the options from a list of attribute arguments.
+  fn parse(args: &[NestedMeta]) -> Result<Self> {
+    let mut options = Options::default();
+    for arg in args {
+      match arg {
+        NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("name") => match &pair.lit {
+          Lit::Str(name) => options.name = Some(name.value()),
+          lit => return Err(syn::Error::new(lit.span(), "expected a string literal")),
+        },
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("constructor") => {
+          options.constructor = true
+        },
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("getter") => options.getter = true,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("setter") => options.setter = true,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => options.skip = true,
+        arg => return Err(syn::Error::new(arg.span(), "unknown option")),
+      }
+    }
+    Ok(options)
+  }
+
+  /// Extracts, and removes, the options of a method's `#[js(...)]` attributes.
+  fn extract(method: &mut ImplItemMethod) -> Result<Self> {
+    let mut args = Vec::new();
+    for attr in method.attrs.iter().filter(|attr| attr.path.is_ident("js")) {
+      match attr.parse_meta()? {
+        Meta::List(list) => args.extend(list.nested),
+        meta => return Err(syn::Error::new(meta.span(), "expected #[js(...)]")),
+      }
+    }
+
+    method.attrs.retain(|attr| !attr.path.is_ident("js"));
+    Self::parse(&args)
+  }
+}
+
+/// The kind of receiver a method takes.
+#[derive(PartialEq)]
+enum Receiver {
+  None,
+  Shared,
+  Mutable,
+}
+
+/// Returns a function's receiver and argument types.
+fn signature(signature: &Signature) -> Result<(Receiver, Vec<&Type>)> {
+  if !signature.generics.params.is_empty() {
+    return Err(syn::Error::new(
+      signature.generics.span(),
+      "generic functions cannot be exposed to JavaScript",
+    ));
+  }
+
+  if let Some(asyncness) = signature.asyncness {
+    return Err(syn::Error::new(
+      asyncness.span(),
+      "async functions cannot be exposed to JavaScript",
+    ));
+  }
+
+  let mut receiver = Receiver::None;
+  let mut types = Vec::new();
+
+  for input in &signature.inputs {
+    match input {
+      FnArg::Receiver(this) => {
+        receiver = match (&this.reference, &this.mutability) {
+          (Some(_), None) => Receiver::Shared,
+          (Some(_), Some(_)) => Receiver::Mutable,
+          (None, _) => {
+            return Err(syn::Error::new(
+              this.span(),
+              "methods taking `self` by value cannot be exposed to JavaScript",
+            ))
+          },
+        }
+      },
+      FnArg::Typed(pattern) => types.push(&*pattern.ty),
+    }
+  }
+
+  Ok((receiver, types))
+}
+
+/// Returns the identifiers and conversion statements for function arguments.
+fn arguments(types: &[&Type]) -> (Vec<syn::Ident>, TokenStream2) {
+  let idents = (0..types.len())
+    .map(|index| format_ident!("__arg{}", index))
+    .collect::<Vec<_>>();
+  let indices = 0..types.len();
+
+  let conversions = quote! {
+    #(
+      let #idents: #types = ::chakracore::binding::FromArgument::from_argument(
+        __guard,
+        __info.arguments.get(#indices),
+      )?;
+    )*
+  };
+
+  (idents, conversions)
+}
+
+/// Expands `#[js_function]`.
+fn expand_function(args: AttributeArgs, function: ItemFn) -> Result<TokenStream2> {
+  let options = Options::parse(&args)?;
+  let (receiver, types) = signature(&function.sig)?;
+  if receiver != Receiver::None {
+    return Err(syn::Error::new(
+      function.sig.span(),
+      "#[js_function] cannot be used on methods",
+    ));
+  }
+
+  let ident = &function.sig.ident;
+  let vis = &function.vis;
+  let binding = format_ident!("js_{}", ident);
+  let name = options.name.unwrap_or_else(|| ident.to_string());
+  let doc = format!("The JavaScript binding of `{}`.", ident);
+  let (idents, conversions) = arguments(&types);
+
+  Ok(quote! {
+    #function
+
+    #[doc = #doc]
+    #[allow(non_camel_case_types)]
+    #vis struct #binding;
+
+    impl ::chakracore::binding::NativeFunction for #binding {
+      const NAME: &'static str = #name;
+
+      fn create<'ctx>(
+        guard: &::chakracore::ContextGuard<'ctx>,
+      ) -> ::chakracore::value::Function<'ctx> {
+        ::chakracore::value::Function::with_name(
+          guard,
+          Self::NAME,
+          Box::new(|__guard, __info| {
+            #conversions
+            ::chakracore::binding::IntoReturn::into_return(#ident(#(#idents),*), __guard)
+          }),
+        )
+      }
+    }
+  })
+}
+
+/// Expands `#[js_class]` on a struct.
+fn expand_struct(args: AttributeArgs, item: ItemStruct) -> Result<TokenStream2> {
+  if !item.generics.params.is_empty() {
+    return Err(syn::Error::new(
+      item.generics.span(),
+      "generic structs cannot be exposed to JavaScript",
+    ));
+  }
+
+  let options = Options::parse(&args)?;
+  let ident = &item.ident;
+  let name = options.name.unwrap_or_else(|| ident.to_string());
+
+  Ok(quote! {
+    #item
+
+    impl ::chakracore::binding::ClassName for #ident {
+      const NAME: &'static str = #name;
+    }
+  })
+}
+
+/// Expands `#[js_class]` on an impl block.
+fn expand_impl(args: AttributeArgs, mut item: ItemImpl) -> Result<TokenStream2> {
+  if let Some(arg) = args.first() {
+    return Err(syn::Error::new(
+      arg.span(),
+      "options are specified on the struct's #[js_class] attribute",
+    ));
+  }
+
+  if item.trait_.is_some() || !item.generics.params.is_empty() {
+    return Err(syn::Error::new(
+      item.span(),
+      "#[js_class] can only be used on inherent, non-generic, impl blocks",
+    ));
+  }
+
+  let self_ty = item.self_ty.clone();
+  let mut members = Vec::new();
+
+  for member in item.items.iter_mut() {
+    let method = match member {
+      ImplItem::Method(method) => method,
+      _ => continue,
+    };
+
+    let options = Options::extract(method)?;
+    if options.skip {
+      continue;
+    }
+
+    let (receiver, types) = signature(&method.sig)?;
+    let ident = &method.sig.ident;
+    let (idents, conversions) = arguments(&types);
+
+    let name = options.name.clone().unwrap_or_else(|| {
+      let name = ident.to_string();
+      match name.strip_prefix("set_") {
+        Some(property) if options.setter => property.to_string(),
+        _ => name,
+      }
+    });
+
+    members.push(if options.constructor {
+      if receiver != Receiver::None {
+        return Err(syn::Error::new(
+          method.sig.span(),
+          "a constructor cannot take `self`",
+        ));
+      }
+
+      quote! {
+        .constructor(Box::new(|__guard, __info| {
+          #conversions
+          ::chakracore::binding::IntoInstance::into_instance(
+            <#self_ty>::#ident(#(#idents),*),
+            __guard,
+          )
+        }))
+      }
+    } else if options.getter {
+      if receiver != Receiver::Shared || !types.is_empty() {
+        return Err(syn::Error::new(
+          method.sig.span(),
+          "a getter must only take `&self`",
+        ));
+      }
+
+      quote! {
+        .getter(#name, Box::new(|__guard, __this| {
+          ::chakracore::binding::IntoReturn::into_return(<#self_ty>::#ident(__this), __guard)
+        }))
+      }
+    } else if options.setter {
+      if receiver != Receiver::Mutable || types.len() != 1 {
+        return Err(syn::Error::new(
+          method.sig.span(),
+          "a setter must take `&mut self` and a single value",
+        ));
+      }
+
+      let ty = types[0];
+      quote! {
+        .setter(#name, Box::new(|__guard, __this, __value| {
+          let __arg0: #ty =
+            ::chakracore::binding::FromArgument::from_argument(__guard, Some(&__value))?;
+          ::chakracore::binding::IntoReturn::into_return(
+            <#self_ty>::#ident(__this, __arg0),
+            __guard,
+          )
+          .map(|_| ())
+        }))
+      }
+    } else {
+      let call = quote! {
+        #conversions
+        ::chakracore::binding::IntoReturn::into_return(
+          <#self_ty>::#ident(__this, #(#idents),*),
+          __guard,
+        )
+      };
+
+      match receiver {
+        Receiver::Shared => quote! {
+          .method(#name, Box::new(|__guard, __this, __info| { #call }))
+        },
+        Receiver::Mutable => quote! {
+          .method_mut(#name, Box::new(|__guard, __this, __info| { #call }))
+        },
+        Receiver::None => quote! {
+          .static_method(#name, Box::new(|__guard, __info| {
+            #conversions
+            ::chakracore::binding::IntoReturn::into_return(
+              <#self_ty>::#ident(#(#idents),*),
+              __guard,
+            )
+          }))
+        },
+      }
+    });
+  }
+
+  Ok(quote! {
+    #item
+
+    impl ::chakracore::binding::NativeClass for #self_ty {
+      fn class() -> ::chakracore::Class<Self> {
+        ::chakracore::Class::<Self>::new(<Self as ::chakracore::binding::ClassName>::NAME)
+          #(#members)*
+      }
+    }
+  })
+}
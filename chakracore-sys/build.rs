@@ -69,7 +69,9 @@ fn setup_custom() -> (PathBuf, Vec<PathBuf>) {
 
 fn setup_default() -> (PathBuf, Vec<PathBuf>) {
   let out_dir = PathBuf::from(&get!("OUT_DIR"));
-  let lib_dir = out_dir.join(format!("lib-{}", VERSION));
+  // Builds with time-travel debugging are kept apart from regular ones
+  let suffix = if cfg!(feature = "ttd") { "-ttd" } else { "" };
+  let lib_dir = out_dir.join(format!("lib-{}{}", VERSION, suffix));
 
   // Directory where all sources are stored
   let src_dir =
@@ -155,6 +157,8 @@ mod build {
       let mut arguments = vec![
         #[cfg(feature = "static")]
         "--static",
+        #[cfg(feature = "ttd")]
+        "--extra-defines=ENABLE_TTD=1",
         if is_debug { "--debug" } else { "--test-build" },
         &arg_jobs,
         &arg_icu,
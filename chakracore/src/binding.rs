@@ -0,0 +1,308 @@
+//! Support for bindings generated by `chakracore-derive`.
+//!
+//! The `#[js_function]` and `#[js_class]` attributes generate glue code that
+//! converts arguments and return values using the traits in this module, and
+//! implement `NativeFunction` and `NativeClass` respectively. These can then
+//! be installed on the context's global object using `register_function` and
+//! `register_class`.
+//!
+//! Arguments and return values are converted using `FromJs` and `IntoJs`, so
+//! any type implementing these, for any lifetime, can be used in a binding's
+//! signature. Values bound to a guard (e.g `Object<'ctx>`) must therefore be
+//! received and returned as `Persistent` handles.
+//!
+//! The same conversions are used by `Function::wrap`, which creates a function
+//! from a closure with typed arguments. Besides `FromJs` types, a closure may
+//! accept the `This` extractor, and the active context's guard as its first
+//! argument.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! use js::binding::This;
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//!
+//! let repeat = js::value::Function::wrap(guard, |This(this): This<String>, count: Option<usize>| {
+//!   this.repeat(count.unwrap_or(2))
+//! });
+//!
+//! let value = js::value::String::new(guard, "foo");
+//! let result = repeat.call_with_this(guard, value, &[]).unwrap();
+//! assert_eq!(result.to_string(guard), "foofoo");
+//! # }).unwrap();
+//! ```
+use crate::convert::{ConversionError, FromJs, IntoJs};
+use crate::value::{self, function::CallbackInfo, function::CallbackResult, Function, Value};
+use crate::{Class, ContextGuard, Property};
+use std::fmt;
+
+/// A type that can be extracted from a function argument.
+pub trait FromArgument<'ctx>: Sized {
+  /// Converts an argument, which is `None` if it was not supplied. The error
+  /// is thrown as an exception.
+  fn from_argument(
+    guard: &ContextGuard<'ctx>,
+    argument: Option<&Value<'ctx>>,
+  ) -> Result<Self, Value<'ctx>>;
+}
+
+/// A type that can be returned from a function callback.
+pub trait IntoReturn<'ctx> {
+  /// Converts the value to a callback result.
+  fn into_return(self, guard: &ContextGuard<'ctx>) -> CallbackResult<'ctx>;
+}
+
+/// A type that can be returned from a class constructor.
+pub trait IntoInstance<'ctx, T> {
+  /// Converts the value to a native instance, or an exception.
+  fn into_instance(self, guard: &ContextGuard<'ctx>) -> Result<T, Value<'ctx>>;
+}
+
+/// The name of a native class.
+pub trait ClassName {
+  /// The name of the class' constructor.
+  const NAME: &'static str;
+}
+
+/// A native class, implemented by `#[js_class]`.
+pub trait NativeClass: ClassName + Sized + 'static {
+  /// Returns the class' builder, with all its members defined.
+  fn class() -> Class<Self>;
+}
+
+/// A native function, implemented by `#[js_function]`.
+pub trait NativeFunction {
+  /// The name of the function.
+  const NAME: &'static str;
+
+  /// Creates the function in the active context.
+  fn create<'ctx>(guard: &ContextGuard<'ctx>) -> Function<'ctx>;
+}
+
+/// A type that can be extracted from a function call.
+///
+/// This is implemented for all `FromJs` types, which consume one argument
+/// each, in addition to the `This` extractor.
+pub trait FromCallback<'ctx>: Sized {
+  /// Extracts the value from a call. The `index` is the position of the next
+  /// unconsumed argument. The error is thrown as an exception.
+  fn from_callback(
+    guard: &ContextGuard<'ctx>,
+    info: &CallbackInfo<'ctx>,
+    index: &mut usize,
+  ) -> Result<Self, Value<'ctx>>;
+}
+
+/// A closure that can be wrapped as a JavaScript function.
+///
+/// This is implemented for closures with up to eight `FromCallback` arguments,
+/// returning an `IntoReturn` type. The closure may additionally take the
+/// active context's guard as its first argument (see `WithGuard`).
+pub trait TypedCallback<Args>: Send + 'static {
+  /// Invokes the closure with arguments extracted from the call.
+  fn invoke<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    info: &CallbackInfo<'ctx>,
+  ) -> CallbackResult<'ctx>;
+}
+
+/// An extractor for a function call's `this` value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct This<T>(pub T);
+
+impl<'ctx, T: FromJs<'ctx>> FromCallback<'ctx> for This<T> {
+  fn from_callback(
+    guard: &ContextGuard<'ctx>,
+    info: &CallbackInfo<'ctx>,
+    _: &mut usize,
+  ) -> Result<Self, Value<'ctx>> {
+    T::from_js(guard, &info.this)
+      .map(This)
+      .map_err(|error| exception(guard, &error, "Invalid 'this'"))
+  }
+}
+
+/// Marks a closure that receives the active context's guard as its first
+/// argument, i.e `|guard: &ContextGuard, ...|`.
+///
+/// The guard is bound to the call, so neither it nor the values it creates
+/// can escape the callback.
+#[derive(Debug)]
+pub enum WithGuard {}
+
+impl<'ctx, T: FromJs<'ctx>> FromCallback<'ctx> for T {
+  /// A missing argument is converted as `undefined`, so it can be optional.
+  fn from_callback(
+    guard: &ContextGuard<'ctx>,
+    info: &CallbackInfo<'ctx>,
+    index: &mut usize,
+  ) -> Result<Self, Value<'ctx>> {
+    let position = *index;
+    *index += 1;
+
+    match info.arguments.get(position) {
+      Some(argument) => T::from_js(guard, argument).map_err(|error| {
+        let context = format!("Argument {}", position + 1);
+        exception(guard, &error, &context)
+      }),
+      None => T::from_js(guard, &value::undefined(guard)).map_err(|_| {
+        let expected = position + 1;
+        let message = format!(
+          "Expected at least {} argument{}, found {}",
+          expected,
+          if expected == 1 { "" } else { "s" },
+          info.arguments.len()
+        );
+        value::Error::type_error(guard, &message).into()
+      }),
+    }
+  }
+}
+
+macro_rules! typed_callback {
+  ($($arg:ident),*) => {
+    impl<F, R, $($arg),*> TypedCallback<($($arg,)*)> for F
+    where
+      F: Fn($($arg),*) -> R + Send + 'static,
+      R: for<'ctx> IntoReturn<'ctx>,
+      $($arg: for<'ctx> FromCallback<'ctx>),*
+    {
+      #[allow(non_snake_case, unused_mut, unused_variables)]
+      fn invoke<'ctx>(
+        &self,
+        guard: &ContextGuard<'ctx>,
+        info: &CallbackInfo<'ctx>,
+      ) -> CallbackResult<'ctx> {
+        let mut index = 0;
+        $(let $arg = $arg::from_callback(guard, info, &mut index)?;)*
+        (self)($($arg),*).into_return(guard)
+      }
+    }
+
+    impl<F, R, $($arg),*> TypedCallback<(WithGuard, $($arg,)*)> for F
+    where
+      F: for<'a, 'ctx> Fn(&'a ContextGuard<'ctx>, $($arg),*) -> R + Send + 'static,
+      R: for<'ctx> IntoReturn<'ctx>,
+      $($arg: for<'ctx> FromCallback<'ctx>),*
+    {
+      #[allow(non_snake_case, unused_mut, unused_variables)]
+      fn invoke<'ctx>(
+        &self,
+        guard: &ContextGuard<'ctx>,
+        info: &CallbackInfo<'ctx>,
+      ) -> CallbackResult<'ctx> {
+        let mut index = 0;
+        $(let $arg = $arg::from_callback(guard, info, &mut index)?;)*
+        (self)(guard, $($arg),*).into_return(guard)
+      }
+    }
+  };
+}
+
+typed_callback!();
+typed_callback!(T1);
+typed_callback!(T1, T2);
+typed_callback!(T1, T2, T3);
+typed_callback!(T1, T2, T3, T4);
+typed_callback!(T1, T2, T3, T4, T5);
+typed_callback!(T1, T2, T3, T4, T5, T6);
+typed_callback!(T1, T2, T3, T4, T5, T6, T7);
+typed_callback!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+/// Creates a native class' constructor and sets it on the global object.
+pub fn register_class<'ctx, T: NativeClass>(guard: &ContextGuard<'ctx>) -> Function<'ctx> {
+  let constructor = T::class().build(guard);
+  let property = Property::new(guard, T::NAME);
+  guard.global().set(guard, property, &constructor);
+  constructor
+}
+
+/// Creates a native function and sets it on the global object.
+pub fn register_function<'ctx, T: NativeFunction>(guard: &ContextGuard<'ctx>) -> Function<'ctx> {
+  let function = T::create(guard);
+  let property = Property::new(guard, T::NAME);
+  guard.global().set(guard, property, &function);
+  function
+}
+
+impl<'ctx, T: FromJs<'ctx>> FromArgument<'ctx> for T {
+  /// A missing argument is converted as `undefined`.
+  fn from_argument(
+    guard: &ContextGuard<'ctx>,
+    argument: Option<&Value<'ctx>>,
+  ) -> Result<Self, Value<'ctx>> {
+    let result = match argument {
+      Some(argument) => T::from_js(guard, argument),
+      None => T::from_js(guard, &value::undefined(guard)),
+    };
+    result.map_err(|error| error.to_exception(guard))
+  }
+}
+
+impl<'ctx, T: IntoJs<'ctx>> IntoReturn<'ctx> for T {
+  fn into_return(self, guard: &ContextGuard<'ctx>) -> CallbackResult<'ctx> {
+    Ok(self.into_js(guard))
+  }
+}
+
+impl<'ctx, T: IntoJs<'ctx>, E: fmt::Display> IntoReturn<'ctx> for Result<T, E> {
+  fn into_return(self, guard: &ContextGuard<'ctx>) -> CallbackResult<'ctx> {
+    self
+      .map(|value| value.into_js(guard))
+      .map_err(|error| value::Error::new(guard, &error.to_string()).into())
+  }
+}
+
+/// Returns a conversion error, with context, as an exception.
+fn exception<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  error: &ConversionError,
+  context: &str,
+) -> Value<'ctx> {
+  let message = format!("{}: {}", context, error);
+  match error {
+    ConversionError::Range { .. } => value::Error::range_error(guard, &message).into(),
+    _ => value::Error::type_error(guard, &message).into(),
+  }
+}
+
+impl<'ctx, T> IntoInstance<'ctx, T> for T {
+  fn into_instance(self, _guard: &ContextGuard<'ctx>) -> Result<T, Value<'ctx>> {
+    Ok(self)
+  }
+}
+
+impl<'ctx, T, E: fmt::Display> IntoInstance<'ctx, T> for Result<T, E> {
+  fn into_instance(self, guard: &ContextGuard<'ctx>) -> Result<T, Value<'ctx>> {
+    self.map_err(|error| value::Error::new(guard, &error.to_string()).into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{FromArgument, IntoReturn};
+  use crate::{test, value};
+
+  #[test]
+  fn argument_conversion() {
+    test::run_with_context(|guard| {
+      let number: value::Value = value::Number::new(guard, 10).into();
+      assert_eq!(i32::from_argument(guard, Some(&number)).unwrap(), 10);
+      assert!(String::from_argument(guard, Some(&number)).is_err());
+      assert!(i32::from_argument(guard, None).is_err());
+      assert_eq!(Option::<i32>::from_argument(guard, None).unwrap(), None);
+
+      let fraction: value::Value = value::Number::from_double(guard, 1.5).into();
+      let error = u8::from_argument(guard, Some(&fraction)).unwrap_err();
+      assert_eq!(
+        error.to_string(guard),
+        "RangeError: Number 1.5 cannot be represented as u8"
+      );
+
+      let error = Err::<(), _>("failure").into_return(guard).unwrap_err();
+      assert_eq!(error.to_string(guard), "Error: failure");
+    });
+  }
+}
@@ -0,0 +1,468 @@
+//! Native classes backed by Rust types.
+//!
+//! A `Class` defines a JavaScript constructor, whose instances are `External`
+//! objects wrapping a Rust value in a `RefCell`. Methods and accessors are
+//! installed on the constructor's prototype and dispatched to Rust closures.
+//!
+//! The instance is borrowed for the duration of a call, so a script that
+//! re-enters an instance while it's being mutated (e.g from a callback passed
+//! to a `method_mut`) receives a `TypeError`.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! struct Point(f64, f64);
+//!
+//! let point = js::Class::<Point>::new("Point")
+//!   .constructor(Box::new(|guard, info| {
+//!     let x = info.arguments.get(0).map_or(0.0, |x| x.to_double(guard));
+//!     let y = info.arguments.get(1).map_or(0.0, |y| y.to_double(guard));
+//!     Ok(Point(x, y))
+//!   }))
+//!   .method("len", Box::new(|guard, point, _| {
+//!     let length = (point.0 * point.0 + point.1 * point.1).sqrt();
+//!     Ok(js::value::Number::from_double(guard, length).into())
+//!   }))
+//!   .build(guard);
+//!
+//! let property = js::Property::new(guard, "Point");
+//! guard.global().set(guard, property, point);
+//!
+//! let result = js::script::eval(guard, "new Point(3, 4).len()").unwrap();
+//! assert_eq!(result.to_integer(guard), 5);
+//! # }).unwrap();
+//! ```
+use crate::value::function::{CallbackInfo, CallbackResult, FunctionCallback};
+use crate::value::{self, External, Function, Object, Value};
+use crate::{ContextGuard, Property, PropertyDescriptor};
+use std::cell::{Ref, RefCell, RefMut};
+use std::marker::PhantomData;
+
+/// Callback type for class constructors.
+pub type ConstructorCallback<T> = dyn for<'ctx> Fn(
+    &ContextGuard<'ctx>,
+    CallbackInfo<'ctx>,
+  ) -> ::std::result::Result<T, Value<'ctx>>
+  + Send;
+
+/// Callback type for class methods.
+pub type MethodCallback<T> =
+  dyn for<'ctx> Fn(&ContextGuard<'ctx>, &T, CallbackInfo<'ctx>) -> CallbackResult<'ctx> + Send;
+
+/// Callback type for class methods that mutate their instance.
+pub type MethodMutCallback<T> =
+  dyn for<'ctx> Fn(&ContextGuard<'ctx>, &mut T, CallbackInfo<'ctx>) -> CallbackResult<'ctx> + Send;
+
+/// Callback type for property getters.
+pub type GetterCallback<T> =
+  dyn for<'ctx> Fn(&ContextGuard<'ctx>, &T) -> CallbackResult<'ctx> + Send;
+
+/// Callback type for property setters.
+pub type SetterCallback<T> = dyn for<'ctx> Fn(
+    &ContextGuard<'ctx>,
+    &mut T,
+    Value<'ctx>,
+  ) -> ::std::result::Result<(), Value<'ctx>>
+  + Send;
+
+/// An accessor property, installed on the prototype.
+struct Accessor {
+  name: String,
+  getter: Option<Box<FunctionCallback>>,
+  setter: Option<Box<FunctionCallback>>,
+}
+
+/// A builder for a JavaScript class backed by the Rust type `T`.
+pub struct Class<T> {
+  name: String,
+  constructor: Option<Box<ConstructorCallback<T>>>,
+  methods: Vec<(String, Box<FunctionCallback>)>,
+  accessors: Vec<Accessor>,
+  statics: Vec<(String, Box<FunctionCallback>)>,
+  phantom: PhantomData<fn(T)>,
+}
+
+impl<T: 'static> Class<T> {
+  /// Creates a new class builder with a name.
+  pub fn new(name: &str) -> Self {
+    Class {
+      name: name.to_string(),
+      constructor: None,
+      methods: Vec::new(),
+      accessors: Vec::new(),
+      statics: Vec::new(),
+      phantom: PhantomData,
+    }
+  }
+
+  /// Set the callback used for constructing the native instance.
+  ///
+  /// Without a constructor, the class cannot be instantiated from script.
+  pub fn constructor(mut self, callback: Box<ConstructorCallback<T>>) -> Self {
+    self.constructor = Some(callback);
+    self
+  }
+
+  /// Adds a method to the class' prototype.
+  pub fn method(mut self, name: &str, callback: Box<MethodCallback<T>>) -> Self {
+    let method: Box<FunctionCallback> = Box::new(move |guard, info| {
+      let this = Self::unwrap_this(guard, &info.this)?;
+      let data = Self::borrow(guard, &this)?;
+      callback(guard, &data, info)
+    });
+
+    self.methods.push((name.to_string(), method));
+    self
+  }
+
+  /// Adds a method, that may mutate its instance, to the class' prototype.
+  pub fn method_mut(mut self, name: &str, callback: Box<MethodMutCallback<T>>) -> Self {
+    let method: Box<FunctionCallback> = Box::new(move |guard, info| {
+      let this = Self::unwrap_this(guard, &info.this)?;
+      let mut data = Self::borrow_mut(guard, &this)?;
+      callback(guard, &mut data, info)
+    });
+
+    self.methods.push((name.to_string(), method));
+    self
+  }
+
+  /// Adds a property getter to the class' prototype.
+  pub fn getter(mut self, name: &str, callback: Box<GetterCallback<T>>) -> Self {
+    let getter: Box<FunctionCallback> = Box::new(move |guard, info| {
+      let this = Self::unwrap_this(guard, &info.this)?;
+      let data = Self::borrow(guard, &this)?;
+      callback(guard, &data)
+    });
+
+    self.accessor(name).getter = Some(getter);
+    self
+  }
+
+  /// Adds a property setter to the class' prototype.
+  pub fn setter(mut self, name: &str, callback: Box<SetterCallback<T>>) -> Self {
+    let setter: Box<FunctionCallback> = Box::new(move |guard, info| {
+      let this = Self::unwrap_this(guard, &info.this)?;
+      let mut data = Self::borrow_mut(guard, &this)?;
+      let value = info
+        .arguments
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| value::undefined(guard));
+
+      callback(guard, &mut data, value).map(|_| value::undefined(guard))
+    });
+
+    self.accessor(name).setter = Some(setter);
+    self
+  }
+
+  /// Adds a static method to the class' constructor.
+  pub fn static_method(mut self, name: &str, callback: Box<FunctionCallback>) -> Self {
+    self.statics.push((name.to_string(), callback));
+    self
+  }
+
+  /// Creates the class' constructor, with its associated prototype.
+  pub fn build<'ctx>(self, guard: &ContextGuard<'ctx>) -> Function<'ctx> {
+    let name = self.name.clone();
+    let create = self.constructor;
+
+    let constructor = Function::with_name(
+      guard,
+      &self.name,
+      Box::new(move |guard, info| {
+        if !info.is_construct_call {
+          let message = format!("Class constructor {} cannot be invoked without 'new'", name);
+          return Err(value::Error::type_error(guard, &message).into());
+        }
+
+        let create = create
+          .as_ref()
+          .ok_or_else(|| value::Error::type_error(guard, "Illegal constructor"))?;
+
+        // Use the prototype of `new.target`, so derived classes are preserved
+        // (and in case the constructor's prototype has been replaced).
+        let prototype = info.new_target.clone().into_object().and_then(|target| {
+          target
+            .get(guard, Property::new(guard, "prototype"))
+            .into_object()
+        });
+
+        let data = Box::new(RefCell::new(create(guard, info)?));
+        let instance = match prototype {
+          Some(prototype) => External::with_prototype(guard, data, prototype),
+          None => External::new(guard, data),
+        };
+        Ok(instance.into())
+      }),
+    );
+
+    let prototype = Object::new(guard);
+    for (name, method) in self.methods {
+      let function = Function::with_name(guard, &name, method);
+      define_value(guard, &prototype, &name, &function);
+    }
+
+    for accessor in self.accessors {
+      let mut descriptor = PropertyDescriptor::new().configurable(true);
+      descriptor.get = accessor
+        .getter
+        .map(|getter| Function::new(guard, getter).into());
+      descriptor.set = accessor
+        .setter
+        .map(|setter| Function::new(guard, setter).into());
+
+      let property = Property::new(guard, &accessor.name);
+      assert!(prototype
+        .define_own_property(guard, property, &descriptor)
+        .unwrap_or(false));
+    }
+
+    for (name, method) in self.statics {
+      let function = Function::with_name(guard, &name, method);
+      define_value(guard, &constructor, &name, &function);
+    }
+
+    define_value(guard, &prototype, "constructor", &constructor);
+    constructor.set(guard, Property::new(guard, "prototype"), &prototype);
+    constructor
+  }
+
+  /// Returns the accessor with a specified name, creating it if required.
+  fn accessor(&mut self, name: &str) -> &mut Accessor {
+    let index = match self
+      .accessors
+      .iter()
+      .position(|accessor| accessor.name == name)
+    {
+      Some(index) => index,
+      None => {
+        self.accessors.push(Accessor {
+          name: name.to_string(),
+          getter: None,
+          setter: None,
+        });
+        self.accessors.len() - 1
+      },
+    };
+    &mut self.accessors[index]
+  }
+
+  /// Borrows the instance data, throwing if it's being mutated.
+  fn borrow<'a, 'ctx>(
+    guard: &ContextGuard<'ctx>,
+    this: &'a External<'ctx>,
+  ) -> ::std::result::Result<Ref<'a, T>, Value<'ctx>> {
+    Self::data(this)
+      .try_borrow()
+      .map_err(|_| value::Error::type_error(guard, "Instance is already in use").into())
+  }
+
+  /// Mutably borrows the instance data, throwing if it's already borrowed.
+  fn borrow_mut<'a, 'ctx>(
+    guard: &ContextGuard<'ctx>,
+    this: &'a External<'ctx>,
+  ) -> ::std::result::Result<RefMut<'a, T>, Value<'ctx>> {
+    Self::data(this)
+      .try_borrow_mut()
+      .map_err(|_| value::Error::type_error(guard, "Instance is already in use").into())
+  }
+
+  /// Returns the instance data of an external, verified by `unwrap_this`.
+  fn data<'a>(this: &'a External) -> &'a RefCell<T> {
+    this
+      .downcast_ref::<RefCell<T>>()
+      .expect("retrieving instance data")
+  }
+
+  /// Returns the external instance of a method call's `this` value.
+  fn unwrap_this<'ctx>(
+    guard: &ContextGuard<'ctx>,
+    this: &Value<'ctx>,
+  ) -> ::std::result::Result<External<'ctx>, Value<'ctx>> {
+    this
+      .clone()
+      .into_external()
+      .and_then(|external| {
+        if external.is::<RefCell<T>>() {
+          Some(external)
+        } else {
+          None
+        }
+      })
+      .ok_or_else(|| value::Error::type_error(guard, "Illegal invocation").into())
+  }
+}
+
+/// Defines a writable, non-enumerable, value on an object (i.e a method).
+fn define_value<'ctx, O, V>(guard: &ContextGuard<'ctx>, object: O, name: &str, value: V)
+where
+  O: AsRef<Object<'ctx>>,
+  V: AsRef<Value<'ctx>>,
+{
+  let descriptor = PropertyDescriptor::new()
+    .value(value.as_ref().clone())
+    .writable(true)
+    .configurable(true);
+
+  let property = Property::new(guard, name);
+  assert!(object
+    .as_ref()
+    .define_own_property(guard, property, &descriptor)
+    .unwrap_or(false));
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{script, test, value, Class, Property};
+  use std::cell::RefCell;
+
+  struct Counter {
+    count: i32,
+  }
+
+  fn counter<'ctx>(guard: &crate::ContextGuard<'ctx>) -> value::Function<'ctx> {
+    Class::<Counter>::new("Counter")
+      .constructor(Box::new(|guard, info| {
+        let count = info
+          .arguments
+          .get(0)
+          .map_or(0, |count| count.to_integer(guard));
+        Ok(Counter { count })
+      }))
+      .method_mut(
+        "increment",
+        Box::new(|guard, counter, _| {
+          counter.count += 1;
+          Ok(value::Number::new(guard, counter.count).into())
+        }),
+      )
+      .method_mut(
+        "visit",
+        Box::new(|guard, _, info| {
+          let visitor = info.arguments[0].clone().into_function().unwrap();
+          visitor
+            .call(guard, &[])
+            .map_err(|error| value::Error::new(guard, &error.to_string()).into())
+        }),
+      )
+      .method(
+        "inspect",
+        Box::new(|guard, _, info| {
+          let visitor = info.arguments[0].clone().into_function().unwrap();
+          visitor
+            .call(guard, &[])
+            .map_err(|error| value::Error::new(guard, &error.to_string()).into())
+        }),
+      )
+      .getter(
+        "count",
+        Box::new(|guard, counter| Ok(value::Number::new(guard, counter.count).into())),
+      )
+      .setter(
+        "count",
+        Box::new(|guard, counter, value| {
+          counter.count = value.to_integer(guard);
+          Ok(())
+        }),
+      )
+      .static_method(
+        "zero",
+        Box::new(|guard, _| Ok(value::Number::new(guard, 0).into())),
+      )
+      .build(guard)
+  }
+
+  #[test]
+  fn methods_and_accessors() {
+    test::run_with_context(|guard| {
+      let property = Property::new(guard, "Counter");
+      guard.global().set(guard, property, counter(guard));
+
+      let result = script::eval(
+        guard,
+        "
+        var counter = new Counter(5);
+        counter.increment();
+        counter.count = counter.count * 2;
+        counter.increment() + Counter.zero();",
+      )
+      .unwrap();
+      assert_eq!(result.to_integer(guard), 13);
+
+      let instance = script::eval(guard, "counter")
+        .unwrap()
+        .into_external()
+        .unwrap();
+      let data = instance.downcast_ref::<RefCell<Counter>>().unwrap();
+      assert_eq!(data.borrow().count, 13);
+
+      let result = script::eval(guard, "counter instanceof Counter").unwrap();
+      assert!(result.to_bool(guard));
+    });
+  }
+
+  #[test]
+  fn reentrancy() {
+    test::run_with_context(|guard| {
+      let property = Property::new(guard, "Counter");
+      guard.global().set(guard, property, counter(guard));
+
+      let result = script::eval(
+        guard,
+        "
+        var counter = new Counter(1);
+        try {
+          counter.visit(() => counter.count);
+        } catch (error) {
+          error.message;
+        }",
+      )
+      .unwrap();
+      assert!(result.to_string(guard).contains("already in use"));
+
+      let result = script::eval(guard, "counter.visit(() => 1) + counter.count").unwrap();
+      assert_eq!(result.to_integer(guard), 2);
+
+      let result = script::eval(guard, "counter.inspect(() => counter.count)").unwrap();
+      assert_eq!(result.to_integer(guard), 1);
+    });
+  }
+
+  #[test]
+  fn subclass() {
+    test::run_with_context(|guard| {
+      let property = Property::new(guard, "Counter");
+      guard.global().set(guard, property, counter(guard));
+
+      let result = script::eval(
+        guard,
+        "
+        class Doubler extends Counter {
+          double() { return this.increment() * 2; }
+        }
+        var doubler = new Doubler(2);
+        doubler instanceof Doubler && doubler instanceof Counter && doubler.double();",
+      )
+      .unwrap();
+      assert_eq!(result.to_integer(guard), 6);
+    });
+  }
+
+  #[test]
+  fn invalid_invocation() {
+    test::run_with_context(|guard| {
+      let property = Property::new(guard, "Counter");
+      guard.global().set(guard, property, counter(guard));
+
+      let result = script::eval(guard, "Counter(1)");
+      assert!(result.is_err());
+
+      let result = script::eval(guard, "Counter.prototype.increment.call({})");
+      assert!(result.is_err());
+    });
+  }
+}
@@ -1,14 +1,76 @@
-//! Execution contexts and sandboxing.
-use crate::{util::jstry, value, Result, Runtime};
+//! Execution contexts, sandboxing and builder.
+use crate::deterministic::{self, Clock};
+use crate::intrinsics::{self, Intrinsic};
+use crate::runtime::{Instance, Runtime};
+use crate::{scope, script, util::jstry, value, Error, Property, Result};
 use anymap::AnyMap;
 use boolinator::Boolinator;
 use chakracore_sys::*;
-use std::{marker::PhantomData, ptr};
+use std::{
+  marker::PhantomData,
+  ptr,
+  sync::{Arc, Weak},
+};
+
+/// Evaluates to the built-in objects that are not reachable from the global
+/// object: %IteratorPrototype%, the iterator prototypes of arrays, strings,
+/// maps and sets, %GeneratorFunction%.prototype and %AsyncFunction%.prototype.
+const HIDDEN_INTRINSICS: &str = "[
+  Object.getPrototypeOf(Object.getPrototypeOf([][Symbol.iterator]())),
+  Object.getPrototypeOf([][Symbol.iterator]()),
+  Object.getPrototypeOf(''[Symbol.iterator]()),
+  Object.getPrototypeOf(new Map()[Symbol.iterator]()),
+  Object.getPrototypeOf(new Set()[Symbol.iterator]()),
+  Object.getPrototypeOf(function* () {}),
+  Object.getPrototypeOf(async function () {}),
+]";
+
+/// A callback creating a value for a new context.
+pub type Initializer = dyn for<'ctx> FnOnce(&ContextGuard<'ctx>) -> value::Value<'ctx>;
+
+/// A builder for the context type.
+///
+/// The options are applied in a fixed order: the virtual clock is installed,
+/// globals are installed and intrinsics are replaced or removed, then the
+/// bootstrap scripts are run, and finally the built-in prototypes are frozen.
+///
+/// ```rust
+/// # use chakracore as js;
+/// # let runtime = js::Runtime::new().unwrap();
+/// let context = js::Context::builder(&runtime)
+///   .global("tenant", |guard| js::value::String::new(guard, "acme").into())
+///   .remove("eval")
+///   .bootstrap("var greeting = 'Hello ' + tenant;")
+///   .freeze_prototypes()
+///   .build()
+///   .unwrap();
+///
+/// context
+///   .exec_with(|guard| {
+///     let result = js::script::eval(guard, "typeof eval + ' ' + greeting").unwrap();
+///     assert_eq!(result.to_string(guard), "undefined Hello acme");
+///   })
+///   .unwrap();
+/// ```
+pub struct Builder<'a> {
+  runtime: &'a Runtime,
+  globals: Vec<(String, Box<Initializer>)>,
+  intrinsics: Vec<(String, Option<Box<Initializer>>)>,
+  scripts: Vec<String>,
+  clock: Option<Clock>,
+  freeze_prototypes: bool,
+}
 
 /// Used for holding context instance data.
 struct ContextData {
-  promise_queue: Vec<value::Function>,
+  // Tasks are only ever executed using a guard of the same context
+  promise_queue: Vec<value::Function<'static>>,
   user_data: AnyMap,
+  // Indexed by `Intrinsic`. These pin the context's built-ins, so a context
+  // is only reclaimed once its runtime is disposed.
+  intrinsics: Vec<value::Value<'static>>,
+  // The runtime disposes the context, so it must not be kept alive by it
+  runtime: Weak<Instance>,
 }
 
 /// A sandboxed execution context with its own set of built-in objects and
@@ -30,38 +92,62 @@ impl Context {
     let mut reference = JsContextRef::new();
     unsafe {
       jstry(JsCreateContext(runtime.as_raw(), &mut reference))?;
-      jstry(JsSetObjectBeforeCollectCallback(
-        reference,
-        ptr::null_mut(),
-        Some(Self::collect),
-      ))?;
-
-      let context = Self::from_raw(reference);
-      context.set_data(Box::new(ContextData {
-        promise_queue: Vec::new(),
-        user_data: AnyMap::new(),
-      }))?;
-
-      // Promise continuation callback requires an active context
-      context
-        .exec_with(|_| {
-          let data = context.get_data() as *mut _ as *mut _;
-          jstry(JsSetPromiseContinuationCallback(
-            Some(Self::promise_handler),
-            data,
-          ))
-        })
-        .expect("activating promise continuation callback")
-        .map(|_| context)
+      Self::attach(reference, runtime)
+    }
+  }
+
+  /// Sets up the internal data of a context created by the runtime.
+  pub(crate) unsafe fn attach(reference: JsContextRef, runtime: &Runtime) -> Result<Context> {
+    jstry(JsSetObjectBeforeCollectCallback(
+      reference,
+      ptr::null_mut(),
+      Some(Self::collect),
+    ))?;
+
+    let context = Self::from_raw(reference);
+
+    // Both the intrinsics and the promise continuation callback require an
+    // active context.
+    context
+      .exec_with(|guard| {
+        context.set_data(Box::new(ContextData {
+          promise_queue: Vec::new(),
+          user_data: AnyMap::new(),
+          intrinsics: intrinsics::capture(guard),
+          runtime: Arc::downgrade(runtime.instance()),
+        }))?;
+
+        let data = context.get_data() as *mut _ as *mut _;
+        jstry(JsSetPromiseContinuationCallback(
+          Some(Self::promise_handler),
+          data,
+        ))
+      })
+      .expect("activating promise continuation callback")
+      .map(|_| context)
+  }
+
+  /// Returns a context builder.
+  pub fn builder(runtime: &Runtime) -> Builder {
+    Builder {
+      runtime,
+      globals: Vec::new(),
+      intrinsics: Vec::new(),
+      scripts: Vec::new(),
+      clock: None,
+      freeze_prototypes: false,
     }
   }
 
   /// Binds the context to the current scope.
-  pub fn make_current<'a>(&'a self) -> Result<ContextGuard<'a>> {
+  ///
+  /// The guard's lifetime is chosen by the caller, so it must only be handed
+  /// out through a closure, which makes the lifetime unique to each call.
+  fn make_current<'ctx>(&self) -> Result<ContextGuard<'ctx>> {
     // Preserve the previous context so it can be restored later
     let current = unsafe { Self::get_current().map(|guard| guard.current.clone()) };
 
-    self.enter().map(|_| ContextGuard::<'a> {
+    self.enter().map(|_| ContextGuard {
       previous: current,
       current: self.clone(),
       phantom: PhantomData,
@@ -79,8 +165,9 @@ impl Context {
   ///
   /// This `ContextGuard` does not reset the current context upon destruction,
   /// in contrast to a normally allocated `ContextGuard`. This is merely a
-  /// hollow reference.
-  pub unsafe fn get_current<'a>() -> Option<ContextGuard<'a>> {
+  /// hollow reference. The caller must also ensure that values branded with
+  /// its lifetime are not used with a guard of another context.
+  pub unsafe fn get_current<'ctx>() -> Option<ContextGuard<'ctx>> {
     let mut reference = JsContextRef::new();
     jsassert!(JsGetCurrentContext(&mut reference));
 
@@ -95,10 +182,16 @@ impl Context {
 
   /// Binds the context to the closure's scope.
   ///
+  /// The guard's lifetime is unique to the closure, so values created within
+  /// it cannot escape it, nor be used with the guard of another context.
+  ///
   /// ```c
   /// let result = context.exec_with(|guard| script::eval(guard, "1 + 1")).unwrap();
   /// ```
-  pub fn exec_with<Ret, T: FnOnce(&ContextGuard) -> Ret>(&self, callback: T) -> Result<Ret> {
+  pub fn exec_with<Ret, T>(&self, callback: T) -> Result<Ret>
+  where
+    T: for<'ctx> FnOnce(&ContextGuard<'ctx>) -> Ret,
+  {
     self.make_current().map(|guard| callback(&guard))
   }
 
@@ -107,7 +200,10 @@ impl Context {
   /// This is a safe alternative to `get_current`. It will either return the
   /// closures result wrapped in `Some`, or `None`, if no context is currently
   /// active.
-  pub fn exec_with_current<Ret, T: FnOnce(&ContextGuard) -> Ret>(callback: T) -> Option<Ret> {
+  pub fn exec_with_current<Ret, T>(callback: T) -> Option<Ret>
+  where
+    T: for<'ctx> FnOnce(&ContextGuard<'ctx>) -> Ret,
+  {
     unsafe { Self::get_current().as_ref().map(callback) }
   }
 
@@ -118,20 +214,48 @@ impl Context {
   /// - Due to the fact that this relies on `from_value`, it suffers from the
   ///   same limitations and should be avoided.
   /// - If the value has no associated context, `None` will be returned.
-  pub(crate) fn exec_with_value<T, Ret>(value: &value::Value, callback: T) -> Result<Option<Ret>>
+  /// - The guard is branded with the value's lifetime, since it belongs to
+  ///   the value's context.
+  pub(crate) fn exec_with_value<'ctx, T, Ret>(
+    value: &value::Value<'ctx>,
+    callback: T,
+  ) -> Result<Option<Ret>>
   where
-    T: FnOnce(&ContextGuard) -> Ret,
+    T: FnOnce(&ContextGuard<'ctx>) -> Ret,
   {
-    Context::from_value(value).map_or(Ok(None), |context| unsafe {
-      // In case there is no active context, or if it differs from the
-      // value's context, temporarily change the context.
-      let guard = Context::get_current()
-        .and_then(|guard| (guard.context() == context).as_some(guard))
-        .map_or_else(|| context.make_current(), Ok);
-      guard.map(|guard| Some(callback(&guard)))
+    Context::from_value(value).map_or(Ok(None), |context| {
+      unsafe { Self::exec_with_branded(&context, callback) }.map(Some)
     })
   }
 
+  /// Executes a closure with a specific context.
+  ///
+  /// In contrast to `exec_with`, the active context is only changed if it
+  /// differs from the specified one.
+  pub(crate) fn exec_with_context<T, Ret>(context: &Context, callback: T) -> Result<Ret>
+  where
+    T: for<'ctx> FnOnce(&ContextGuard<'ctx>) -> Ret,
+  {
+    unsafe { Self::exec_with_branded(context, callback) }
+  }
+
+  /// Executes a closure with a specific context, using a guard branded with a
+  /// lifetime chosen by the caller.
+  ///
+  /// This is unsafe because the caller must ensure that any values branded
+  /// with the lifetime belong to the context.
+  unsafe fn exec_with_branded<'ctx, T, Ret>(context: &Context, callback: T) -> Result<Ret>
+  where
+    T: FnOnce(&ContextGuard<'ctx>) -> Ret,
+  {
+    // In case there is no active context, or if it differs from the
+    // specified context, temporarily change the context.
+    let guard = Context::get_current()
+      .and_then(|guard| (guard.context() == *context).as_some(guard))
+      .map_or_else(|| context.make_current(), Ok);
+    guard.map(|guard| callback(&guard))
+  }
+
   /// Set user data associated with the context.
   ///
   /// - Only one value per type.
@@ -169,12 +293,17 @@ impl Context {
     unsafe { self.get_data().user_data.get_mut::<T>() }
   }
 
+  /// Returns whether the context belongs to the same runtime as another.
+  pub fn is_same_runtime(&self, other: &Context) -> bool {
+    self.runtime() == other.runtime()
+  }
+
   /// Returns a recyclable value's associated context.
   ///
   /// This is unreliable, because types that have an associated context is
   /// implementation defined (by the underlying runtime), based on whether they
   /// are recyclable or not, therefore it should be avoided.
-  fn from_value(value: &value::Value) -> Option<Context> {
+  pub(crate) fn from_value(value: &value::Value) -> Option<Context> {
     let mut reference = JsContextRef::new();
     unsafe {
       jstry(JsGetContextOfObject(value.as_raw(), &mut reference))
@@ -183,6 +312,19 @@ impl Context {
     }
   }
 
+  /// Returns the shared instance of the context's runtime, which keeps it
+  /// from being disposed.
+  pub(crate) fn runtime_instance(&self) -> Option<Arc<Instance>> {
+    unsafe { self.get_data().runtime.upgrade() }
+  }
+
+  /// Returns the handle of the context's runtime.
+  pub(crate) fn runtime(&self) -> JsRuntimeHandle {
+    let mut runtime = JsRuntimeHandle::new();
+    jsassert!(unsafe { JsGetRuntime(self.as_raw(), &mut runtime) });
+    runtime
+  }
+
   /// Sets the internal data of the context.
   unsafe fn set_data(&self, data: Box<ContextData>) -> Result<()> {
     jstry(JsSetContextData(
@@ -220,7 +362,9 @@ impl Context {
     let data = (data as *mut ContextData)
       .as_mut()
       .expect("retrieving promise handler stack");
-    data.promise_queue.push(value::Function::from_raw(task));
+    // The queue is stored on the heap, so the task must be reference counted
+    let task = scope::unscoped(|| value::Function::from_raw(task));
+    data.promise_queue.push(task);
   }
 
   /// A collect callback, triggered before the context is destroyed.
@@ -232,24 +376,191 @@ impl Context {
 
 reference!(Context);
 
+impl<'a> Builder<'a> {
+  /// Installs a property on the global object.
+  pub fn global<F>(mut self, name: &str, value: F) -> Self
+  where
+    F: for<'ctx> FnOnce(&ContextGuard<'ctx>) -> value::Value<'ctx> + 'static,
+  {
+    self.globals.push((name.to_string(), Box::new(value)));
+    self
+  }
+
+  /// Replaces a built-in object, specified by its path relative to the global
+  /// object (e.g `Date.now`).
+  ///
+  /// Any built-in reachable by another path is unaffected; the `Function`
+  /// constructor for instance, is also available as
+  /// `Function.prototype.constructor`.
+  pub fn replace<F>(mut self, path: &str, value: F) -> Self
+  where
+    F: for<'ctx> FnOnce(&ContextGuard<'ctx>) -> value::Value<'ctx> + 'static,
+  {
+    self
+      .intrinsics
+      .push((path.to_string(), Some(Box::new(value))));
+    self
+  }
+
+  /// Removes a built-in object, specified by its path relative to the global
+  /// object (e.g `eval`). Building fails if the property is not configurable.
+  pub fn remove(mut self, path: &str) -> Self {
+    self.intrinsics.push((path.to_string(), None));
+    self
+  }
+
+  /// Replaces `Math.random` with a generator seeded by `seed`.
+  pub fn random_seed(self, seed: u64) -> Self {
+    self.replace("Math.random", move |guard| {
+      deterministic::random(guard, seed)
+    })
+  }
+
+  /// Replaces `Date` with a constructor reading the current time from a
+  /// virtual clock. This affects `Date.now()`, `Date()` and `new Date()`.
+  pub fn clock(mut self, clock: Clock) -> Self {
+    self.clock = Some(clock);
+    self
+  }
+
+  /// Adds a script to be evaluated once the context has been set up.
+  pub fn bootstrap(mut self, code: &str) -> Self {
+    self.scripts.push(code.to_string());
+    self
+  }
+
+  /// Freezes the prototypes of all built-in constructors, including the
+  /// objects in their prototype chains, and the built-in iterator, generator
+  /// and async function prototypes.
+  pub fn freeze_prototypes(mut self) -> Self {
+    self.freeze_prototypes = true;
+    self
+  }
+
+  /// Creates the context object with associated settings.
+  pub fn build(self) -> Result<Context> {
+    let context = Context::new(self.runtime)?;
+    context.exec_with(|guard| {
+      let global = guard.global();
+      let builtins = global.get_own_property_names(guard).to_vec(guard);
+
+      // Captured before any script runs, since these are only reachable
+      // through instances.
+      let hidden = if self.freeze_prototypes {
+        script::eval(guard, HIDDEN_INTRINSICS)?
+          .into_array()
+          .map(|array| array.to_vec(guard))
+      } else {
+        None
+      };
+
+      if let Some(clock) = self.clock {
+        let date = deterministic::date(guard, clock)?;
+        global.set(guard, Property::new(guard, "Date"), date);
+      }
+
+      for (name, value) in self.globals {
+        global.set(guard, Property::new(guard, &name), value(guard));
+      }
+
+      for (path, value) in self.intrinsics {
+        let (object, property) = Self::resolve(guard, &path)?;
+        match value {
+          Some(value) => object.set(guard, property, value(guard)),
+          None => {
+            if !object.delete(guard, property) {
+              return Err(Error::NotConfigurable(path));
+            }
+          },
+        }
+      }
+
+      for (index, code) in self.scripts.iter().enumerate() {
+        script::eval_with_name(guard, &format!("bootstrap{}.js", index), code)?;
+      }
+
+      if self.freeze_prototypes {
+        let prototype = Property::new(guard, "prototype");
+        let constructors = builtins
+          .into_iter()
+          .map(|name| global.get(guard, Property::new(guard, &name.to_string(guard))))
+          .filter_map(|value| value.into_function());
+
+        // Function.prototype is only reachable through the constructors' own
+        // prototype chains.
+        let mut chains = Vec::new();
+        for constructor in constructors {
+          chains.push(constructor.get(guard, &prototype));
+          chains.push(constructor.get_prototype(guard));
+        }
+
+        // The hidden intrinsics are frozen themselves, along with the
+        // prototypes of the generators they create.
+        for intrinsic in hidden.into_iter().flatten() {
+          if let Some(object) = intrinsic.clone().into_object() {
+            chains.push(object.get(guard, &prototype));
+          }
+          chains.push(intrinsic);
+        }
+
+        for chain in chains {
+          let mut object = chain.into_object();
+          while let Some(prototype) = object {
+            prototype.freeze(guard)?;
+            object = prototype.get_prototype(guard).into_object();
+          }
+        }
+      }
+      Ok(())
+    })??;
+    Ok(context)
+  }
+
+  /// Resolves a path to the object and property it refers to.
+  fn resolve<'ctx>(
+    guard: &ContextGuard<'ctx>,
+    path: &str,
+  ) -> Result<(value::Object<'ctx>, Property)> {
+    let mut names = path.rsplitn(2, '.');
+    let name = names.next().unwrap_or(path);
+    let object = names.next().map_or(Some(guard.global()), |parent| {
+      parent.split('.').try_fold(guard.global(), |object, name| {
+        object.get(guard, Property::new(guard, name)).into_object()
+      })
+    });
+
+    object
+      .map(|object| (object, Property::new(guard, name)))
+      .ok_or_else(|| Error::PathNotFound(path.to_string()))
+  }
+}
+
 /// A guard that keeps a context active while it is in scope.
+///
+/// Guards are only handed out by `Context::exec_with` (and its variants), and
+/// the lifetime `'ctx` is unique to each call. Since it is also invariant,
+/// values branded with it cannot be used with a guard of another context.
+///
+/// The brand does not prevent a captured guard from being used while another
+/// context is active, e.g within a nested `exec_with`; values created this
+/// way belong to the active context.
 #[must_use]
 #[derive(Debug)]
-pub struct ContextGuard<'a> {
+pub struct ContextGuard<'ctx> {
   previous: Option<Context>,
   current: Context,
-  phantom: PhantomData<&'a Context>,
+  phantom: PhantomData<fn(&'ctx ()) -> &'ctx ()>,
   drop: bool,
 }
 
-impl<'a> ContextGuard<'a> {
+impl<'ctx> ContextGuard<'ctx> {
   /// Returns the guard's associated context.
   pub fn context(&self) -> Context {
     self.current.clone()
   }
 
   /// Returns the active context's global object.
-  pub fn global(&self) -> value::Object {
+  pub fn global(&self) -> value::Object<'ctx> {
     let mut value = JsValueRef::new();
     unsafe {
       jsassert!(JsGetGlobalObject(&mut value));
@@ -257,16 +568,38 @@ impl<'a> ContextGuard<'a> {
     }
   }
 
+  /// Returns a built-in object, as it was when the context was created.
+  pub(crate) fn intrinsic(&self, intrinsic: Intrinsic) -> value::Value<'ctx> {
+    let data = unsafe { self.current.get_data() };
+    intrinsics::get(self, &data.intrinsics, intrinsic)
+  }
+
+  /// Returns a guard of the same context, which does not reset the current
+  /// context upon destruction, branded with another lifetime.
+  ///
+  /// This is unsafe because the caller must ensure that values branded with
+  /// the lifetime are not used with a guard of another context.
+  pub(crate) unsafe fn rebrand<'a>(&self) -> ContextGuard<'a> {
+    ContextGuard {
+      previous: None,
+      current: self.current.clone(),
+      phantom: PhantomData,
+      drop: false,
+    }
+  }
+
   /// Executes all the context's queued promise tasks.
   pub fn execute_tasks(&self) {
     let data = unsafe { self.current.get_data() };
     while let Some(task) = data.promise_queue.pop() {
+      // The queue only holds tasks of this context
+      let task: value::Function<'ctx> = unsafe { value::Function::from_raw(task.as_raw()) };
       task.call(self, &[]).expect("executing promise task");
     }
   }
 }
 
-impl<'a> Drop for ContextGuard<'a> {
+impl<'ctx> Drop for ContextGuard<'ctx> {
   /// Resets the currently active context.
   fn drop(&mut self) {
     if self.drop {
@@ -277,7 +610,8 @@ impl<'a> Drop for ContextGuard<'a> {
 
 #[cfg(test)]
 mod tests {
-  use crate::{script, test, value, Context, Property};
+  use crate::{script, test, value, Context, Error, Property};
+  use matches::assert_matches;
 
   #[test]
   fn global() {
@@ -299,18 +633,18 @@ mod tests {
   #[test]
   fn stack() {
     let (runtime, context) = test::setup_env();
-    {
-      let get_current = || unsafe { Context::get_current().unwrap().context() };
-      let _guard = context.make_current().unwrap();
+    let get_current = || unsafe { Context::get_current().unwrap().context() };
+    context
+      .exec_with(|_| {
+        assert_eq!(get_current(), context);
 
-      assert_eq!(get_current(), context);
-      {
         let inner_context = Context::new(&runtime).unwrap();
-        let _guard = inner_context.make_current().unwrap();
-        assert_eq!(get_current(), inner_context);
-      }
-      assert_eq!(get_current(), context);
-    }
+        inner_context
+          .exec_with(|_| assert_eq!(get_current(), inner_context))
+          .unwrap();
+        assert_eq!(get_current(), context);
+      })
+      .unwrap();
     assert!(unsafe { Context::get_current() }.is_none());
   }
 
@@ -362,10 +696,91 @@ mod tests {
     let (runtime, context) = test::setup_env();
     let context2 = Context::new(&runtime).unwrap();
 
-    let guard1 = context.make_current().unwrap();
-    let object = script::eval(&guard1, "({ foo: 1337 })").unwrap();
+    let object = context
+      .exec_with(|guard1| {
+        let object = script::eval(guard1, "({ foo: 1337 })").unwrap();
+        object.marshal_to(&context2).unwrap()
+      })
+      .unwrap();
+
+    context2
+      .exec_with(|guard2| {
+        let object = object.get(guard2).unwrap();
+        assert_eq!(object.to_json(guard2).unwrap(), r#"{"foo":1337}"#);
+      })
+      .unwrap();
+  }
+
+  #[test]
+  fn builder() {
+    let (runtime, _) = test::setup_env();
+    let context = Context::builder(&runtime)
+      .global("answer", |guard| value::Number::new(guard, 42).into())
+      .replace("Math.random", |guard| {
+        value::Function::new(
+          guard,
+          Box::new(|guard, _| Ok(value::Number::new(guard, 4).into())),
+        )
+        .into()
+      })
+      .replace("Function.prototype.constructor", |guard| {
+        let function = value::Function::new(
+          guard,
+          Box::new(|guard, _| Err(value::Error::new(guard, "disabled").into())),
+        );
+        function.into()
+      })
+      .remove("Function")
+      .remove("eval")
+      .bootstrap("var doubled = answer * 2;")
+      .freeze_prototypes()
+      .build()
+      .unwrap();
+
+    context
+      .exec_with(|guard| {
+        let result = script::eval(guard, "doubled + Math.random()").unwrap();
+        assert_eq!(result.to_integer(guard), 88);
+
+        let result = script::eval(guard, "typeof eval + typeof Function").unwrap();
+        assert_eq!(result.to_string(guard), "undefinedundefined");
+        assert!(script::eval(guard, "(function() {}).constructor('return 1')").is_err());
+
+        let result = script::eval(
+          guard,
+          "[Object.prototype, Array.prototype, Function.prototype, Promise.prototype,
+            Object.getPrototypeOf(Object.getPrototypeOf([][Symbol.iterator]())),
+            Object.getPrototypeOf([][Symbol.iterator]()),
+            Object.getPrototypeOf(function* () {}),
+            Object.getPrototypeOf(function* () {}).prototype,
+            Object.getPrototypeOf(async function () {})]
+            .every(Object.isFrozen)",
+        )
+        .unwrap();
+        assert!(result.to_bool(guard));
+
+        // Values created by the library are unaffected
+        let object = value::Object::new(guard);
+        object.set(
+          guard,
+          Property::new(guard, "foo"),
+          value::Number::new(guard, 1),
+        );
+        assert_eq!(object.to_json(guard).unwrap(), r#"{"foo":1}"#);
+      })
+      .unwrap();
+  }
+
+  #[test]
+  fn builder_errors() {
+    let (runtime, _) = test::setup_env();
+    let result = Context::builder(&runtime).remove("Foo.bar").build();
+    assert_matches!(result, Err(Error::PathNotFound(ref path)) if path == "Foo.bar");
+
+    let result = Context::builder(&runtime).bootstrap("throw 1").build();
+    assert_matches!(result, Err(Error::ScriptException(_)));
 
-    let guard2 = context2.make_current().unwrap();
-    assert_eq!(object.to_json(&guard2).unwrap(), r#"{"foo":1337}"#);
+    let result = Context::builder(&runtime).remove("Infinity").build();
+    assert_matches!(result, Err(Error::NotConfigurable(ref path)) if path == "Infinity");
   }
 }
@@ -0,0 +1,546 @@
+//! Conversion between JavaScript values and native types.
+//!
+//! The `FromJs` trait performs strict conversions; a value is never coerced
+//! using JavaScript semantics (in contrast to the `Value::to_*` methods). A
+//! number is only converted to an integer if it is integral and within the
+//! bounds of the target type.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # use std::collections::HashMap;
+//! use js::convert::{FromJs, IntoJs};
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//!
+//! let value = js::script::eval(guard, "({ foo: [1, 2], bar: [3] })").unwrap();
+//! let map = HashMap::<String, Vec<u8>>::from_js(guard, &value).unwrap();
+//! assert_eq!(map["foo"], [1, 2]);
+//!
+//! let value = (10, "foo").into_js(guard);
+//! assert_eq!(value.to_json(guard).unwrap(), r#"[10,"foo"]"#);
+//! # }).unwrap();
+//! ```
+use crate::scope;
+use crate::value::{self, Handle, Rebind, Value};
+use crate::{ContextGuard, Property};
+use chakracore_sys::JsValueType;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error as StdError;
+use std::hash::BuildHasher;
+use std::{fmt, iter};
+
+/// The result of a conversion.
+pub type Result<T> = ::std::result::Result<T, ConversionError>;
+
+/// An error caused by an incompatible value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConversionError {
+  /// The value is not of the expected type.
+  Type {
+    /// The type required by the conversion.
+    expected: JsValueType,
+    /// The type of the value.
+    actual: JsValueType,
+  },
+  /// The number is not integral, or outside the bounds of the native type.
+  Range {
+    /// The name of the native type.
+    target: &'static str,
+    /// The number that could not be represented.
+    value: f64,
+  },
+  /// The array does not contain the expected number of elements.
+  Length {
+    /// The number of elements required by the conversion.
+    expected: usize,
+    /// The number of elements in the array.
+    actual: usize,
+  },
+}
+
+impl ConversionError {
+  /// Creates a type error from an expected type and a value.
+  pub fn new(expected: JsValueType, value: &Value) -> Self {
+    ConversionError::Type {
+      expected,
+      actual: value.get_type(),
+    }
+  }
+
+  /// Returns the error as a JavaScript exception.
+  ///
+  /// Range errors are represented as a `RangeError`, and all others as a
+  /// `TypeError`.
+  pub fn to_exception<'ctx>(&self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    let message = self.to_string();
+    match self {
+      ConversionError::Range { .. } => value::Error::range_error(guard, &message).into(),
+      _ => value::Error::type_error(guard, &message).into(),
+    }
+  }
+}
+
+impl StdError for ConversionError {}
+
+impl fmt::Display for ConversionError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ConversionError::Type { expected, actual } => {
+        write!(f, "Expected {:?}, found {:?}", expected, actual)
+      },
+      ConversionError::Range { target, value } => {
+        write!(f, "Number {} cannot be represented as {}", value, target)
+      },
+      ConversionError::Length { expected, actual } => write!(
+        f,
+        "Expected an array of length {}, found length {}",
+        expected, actual
+      ),
+    }
+  }
+}
+
+/// A type that can be converted from a JavaScript value.
+///
+/// The lifetime is the one of the converted value, which allows value types
+/// (e.g `Object<'ctx>`) to be converted as well.
+pub trait FromJs<'ctx>: Sized {
+  /// Converts a value to the native type.
+  fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self>;
+}
+
+/// A type that can be converted to a JavaScript value.
+pub trait IntoJs<'ctx> {
+  /// Converts the native type to a value.
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx>;
+}
+
+impl<'ctx> FromJs<'ctx> for Value<'ctx> {
+  fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    Ok(value.clone())
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for Value<'ctx> {
+  fn into_js(self, _guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    self
+  }
+}
+
+impl<'ctx> FromJs<'ctx> for () {
+  fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    if value.is_undefined() {
+      Ok(())
+    } else {
+      Err(ConversionError::new(JsValueType::Undefined, value))
+    }
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for () {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    value::undefined(guard)
+  }
+}
+
+impl<'ctx> FromJs<'ctx> for bool {
+  fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    value
+      .clone()
+      .into_boolean()
+      .map(|boolean| boolean.value())
+      .ok_or_else(|| ConversionError::new(JsValueType::Boolean, value))
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for bool {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    value::Boolean::new(guard, self).into()
+  }
+}
+
+/// Returns a number's floating point representation.
+fn number(value: &Value) -> Result<f64> {
+  value
+    .clone()
+    .into_number()
+    .map(|number| number.value_double())
+    .ok_or_else(|| ConversionError::new(JsValueType::Number, value))
+}
+
+macro_rules! integer {
+  ($typ:ident, $create:ident, $repr:ty) => {
+    impl<'ctx> FromJs<'ctx> for $typ {
+      fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+        let number = number(value)?;
+
+        // The upper bound is exclusive, since `MAX` may be rounded up
+        let in_range = number >= $typ::MIN as f64 && number < $typ::MAX as f64 + 1.0;
+        if number.fract() == 0.0 && in_range {
+          Ok(number as $typ)
+        } else {
+          Err(ConversionError::Range {
+            target: stringify!($typ),
+            value: number,
+          })
+        }
+      }
+    }
+
+    impl<'ctx> IntoJs<'ctx> for $typ {
+      fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+        value::Number::$create(guard, self as $repr).into()
+      }
+    }
+  };
+}
+
+integer!(i8, new, i32);
+integer!(i16, new, i32);
+integer!(i32, new, i32);
+integer!(u8, new, i32);
+integer!(u16, new, i32);
+
+// These may not be exactly representable as a JavaScript number
+integer!(u32, from_double, f64);
+integer!(i64, from_double, f64);
+integer!(u64, from_double, f64);
+integer!(isize, from_double, f64);
+integer!(usize, from_double, f64);
+
+impl<'ctx> FromJs<'ctx> for f64 {
+  fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    number(value)
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for f64 {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    value::Number::from_double(guard, self).into()
+  }
+}
+
+impl<'ctx> FromJs<'ctx> for f32 {
+  fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    number(value).map(|number| number as f32)
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for f32 {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    value::Number::from_double(guard, self as f64).into()
+  }
+}
+
+impl<'ctx> FromJs<'ctx> for String {
+  fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    value
+      .clone()
+      .into_string()
+      .map(|string| string.value())
+      .ok_or_else(|| ConversionError::new(JsValueType::String, value))
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for String {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    self.as_str().into_js(guard)
+  }
+}
+
+impl<'ctx> IntoJs<'ctx> for &str {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    value::String::new(guard, self).into()
+  }
+}
+
+impl<'ctx, T: FromJs<'ctx>> FromJs<'ctx> for Option<T> {
+  /// Both `null` and `undefined` are represented as `None`.
+  fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    if value.is_null() || value.is_undefined() {
+      Ok(None)
+    } else {
+      T::from_js(guard, value).map(Some)
+    }
+  }
+}
+
+impl<'ctx, T: IntoJs<'ctx>> IntoJs<'ctx> for Option<T> {
+  /// `None` is represented as `null`.
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    match self {
+      Some(value) => value.into_js(guard),
+      None => value::null(guard),
+    }
+  }
+}
+
+impl<'ctx, T: FromJs<'ctx>> FromJs<'ctx> for Vec<T> {
+  fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    let array = value
+      .clone()
+      .into_array()
+      .ok_or_else(|| ConversionError::new(JsValueType::Array, value))?;
+    // The elements may be handles, which are stored on the heap
+    scope::unscoped(|| {
+      array
+        .iter(guard)
+        .map(|element| T::from_js(guard, &element))
+        .collect()
+    })
+  }
+}
+
+impl<'ctx, T: IntoJs<'ctx>> IntoJs<'ctx> for Vec<T> {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    array(guard, self.into_iter().map(|value| value.into_js(guard)))
+  }
+}
+
+/// Returns an object's own enumerable properties, converted to native types.
+fn entries<'ctx, T, C>(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<C>
+where
+  T: FromJs<'ctx>,
+  C: iter::FromIterator<(String, T)>,
+{
+  let object = value
+    .clone()
+    .into_object()
+    .ok_or_else(|| ConversionError::new(JsValueType::Object, value))?;
+  // The values may be handles, which are stored on the heap
+  scope::unscoped(|| {
+    object
+      .entries(guard)
+      .map(|(key, value)| T::from_js(guard, &value).map(|value| (key.to_string(guard), value)))
+      .collect()
+  })
+}
+
+/// Creates an object from native key-value pairs.
+fn object<'ctx, T, I>(guard: &ContextGuard<'ctx>, entries: I) -> Value<'ctx>
+where
+  T: IntoJs<'ctx>,
+  I: Iterator<Item = (String, T)>,
+{
+  let object = value::Object::new(guard);
+  for (key, value) in entries {
+    object.set(guard, Property::new(guard, &key), value.into_js(guard));
+  }
+  object.into()
+}
+
+/// Creates an array from values.
+fn array<'ctx, I>(guard: &ContextGuard<'ctx>, values: I) -> Value<'ctx>
+where
+  I: ExactSizeIterator<Item = Value<'ctx>>,
+{
+  let array = value::Array::new(guard, values.len() as u32);
+  for (index, value) in values.enumerate() {
+    array.set_index(guard, index as u32, value);
+  }
+  array.into()
+}
+
+impl<'ctx, T, S> FromJs<'ctx> for HashMap<String, T, S>
+where
+  T: FromJs<'ctx>,
+  S: BuildHasher + Default,
+{
+  fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    entries(guard, value)
+  }
+}
+
+impl<'ctx, T, S> IntoJs<'ctx> for HashMap<String, T, S>
+where
+  T: IntoJs<'ctx>,
+  S: BuildHasher,
+{
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    object(guard, self.into_iter())
+  }
+}
+
+impl<'ctx, T: FromJs<'ctx>> FromJs<'ctx> for BTreeMap<String, T> {
+  fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    entries(guard, value)
+  }
+}
+
+impl<'ctx, T: IntoJs<'ctx>> IntoJs<'ctx> for BTreeMap<String, T> {
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    object(guard, self.into_iter())
+  }
+}
+
+macro_rules! tuple {
+  ($length:expr => $($name:ident: $index:expr),+) => {
+    impl<'ctx, $($name: FromJs<'ctx>),+> FromJs<'ctx> for ($($name,)+) {
+      fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+        let array = value
+          .clone()
+          .into_array()
+          .ok_or_else(|| ConversionError::new(JsValueType::Array, value))?;
+
+        let length = array.len(guard);
+        if length != $length {
+          return Err(ConversionError::Length { expected: $length, actual: length });
+        }
+
+        Ok(($($name::from_js(guard, &array.get_index(guard, $index))?,)+))
+      }
+    }
+
+    impl<'ctx, $($name: IntoJs<'ctx>),+> IntoJs<'ctx> for ($($name,)+) {
+      #[allow(non_snake_case)]
+      fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+        let ($($name,)+) = self;
+        array(guard, vec![$($name.into_js(guard)),+].into_iter())
+      }
+    }
+  };
+}
+
+tuple!(1 => A: 0);
+tuple!(2 => A: 0, B: 1);
+tuple!(3 => A: 0, B: 1, C: 2);
+tuple!(4 => A: 0, B: 1, C: 2, D: 3);
+tuple!(5 => A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple!(6 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+tuple!(7 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+tuple!(8 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+macro_rules! subtype {
+  ($typ:ident, $into:ident, $expected:ident) => {
+    impl<'ctx> FromJs<'ctx> for value::$typ<'ctx> {
+      fn from_js(_guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+        value
+          .clone()
+          .$into()
+          .ok_or_else(|| ConversionError::new(JsValueType::$expected, value))
+      }
+    }
+
+    impl<'ctx> IntoJs<'ctx> for value::$typ<'ctx> {
+      fn into_js(self, _guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+        self.into()
+      }
+    }
+  };
+}
+
+subtype!(Array, into_array, Array);
+subtype!(ArrayBuffer, into_array_buffer, ArrayBuffer);
+subtype!(Boolean, into_boolean, Boolean);
+subtype!(Date, into_date, Object);
+subtype!(External, into_external, Object);
+subtype!(Function, into_function, Function);
+subtype!(Map, into_map, Object);
+subtype!(Number, into_number, Number);
+subtype!(Object, into_object, Object);
+subtype!(Promise, into_promise, Object);
+subtype!(Proxy, into_proxy, Object);
+subtype!(RegExp, into_regexp, Object);
+subtype!(Set, into_set, Object);
+subtype!(String, into_string, String);
+
+impl<'ctx, T> FromJs<'ctx> for value::Persistent<T>
+where
+  T: Rebind<'ctx> + 'static,
+  T::Bound: FromJs<'ctx> + Handle<Static = T>,
+{
+  fn from_js(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Self> {
+    T::Bound::from_js(guard, value).map(|value| value::Persistent::new(guard, &value))
+  }
+}
+
+impl<'ctx, T> IntoJs<'ctx> for value::Persistent<T>
+where
+  T: Rebind<'ctx> + 'static,
+  T::Bound: Into<Value<'ctx>>,
+{
+  /// Panics if the handle belongs to another context.
+  fn into_js(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    self
+      .get(guard)
+      .expect("persistent value of the same context")
+      .into()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ConversionError, FromJs, IntoJs};
+  use crate::{script, test, value};
+  use chakracore_sys::JsValueType;
+  use matches::assert_matches;
+  use std::collections::HashMap;
+
+  #[test]
+  fn integer_bounds() {
+    test::run_with_context(|guard| {
+      let value = value::Number::from_double(guard, 300.0).into();
+      assert_eq!(u16::from_js(guard, &value), Ok(300));
+      assert_matches!(
+        u8::from_js(guard, &value),
+        Err(ConversionError::Range { .. })
+      );
+
+      let value = value::Number::from_double(guard, 1.5).into();
+      assert!(i32::from_js(guard, &value).is_err());
+      assert_eq!(f64::from_js(guard, &value), Ok(1.5));
+
+      let value = value::Number::from_double(guard, 9_223_372_036_854_775_808.0).into();
+      assert!(i64::from_js(guard, &value).is_err());
+      assert!(u64::from_js(guard, &value).is_ok());
+    });
+  }
+
+  #[test]
+  fn type_mismatch() {
+    test::run_with_context(|guard| {
+      let value = value::String::new(guard, "10").into();
+      assert_eq!(
+        i32::from_js(guard, &value),
+        Err(ConversionError::Type {
+          expected: JsValueType::Number,
+          actual: JsValueType::String,
+        })
+      );
+    });
+  }
+
+  #[test]
+  fn collections() {
+    test::run_with_context(|guard| {
+      let value = script::eval(guard, "({ foo: [1, null], bar: [] })").unwrap();
+      let map = HashMap::<String, Vec<Option<i32>>>::from_js(guard, &value).unwrap();
+      assert_eq!(map["foo"], [Some(1), None]);
+      assert!(map["bar"].is_empty());
+
+      let value = script::eval(
+        guard,
+        "Object.defineProperty({ foo: 1 }, 'bar', { value: 2, enumerable: false })",
+      )
+      .unwrap();
+      let map = HashMap::<String, i32>::from_js(guard, &value).unwrap();
+      assert_eq!(map.len(), 1);
+      assert_eq!(map["foo"], 1);
+
+      let value = ("foo", 10, (true,)).into_js(guard);
+      assert_eq!(value.to_json(guard).unwrap(), r#"["foo",10,[true]]"#);
+      assert_eq!(
+        <(String, u8, (bool,))>::from_js(guard, &value),
+        Ok(("foo".to_string(), 10, (true,)))
+      );
+      assert_matches!(
+        <(String, u8)>::from_js(guard, &value),
+        Err(ConversionError::Length {
+          expected: 2,
+          actual: 3
+        })
+      );
+    });
+  }
+}
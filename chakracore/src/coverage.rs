@@ -0,0 +1,758 @@
+//! Code coverage of scripts, collected by the debugger.
+//!
+//! Coverage is recorded for scripts compiled with a name (e.g using
+//! `script::eval_with_name`) while the collector is active. A breakpoint is
+//! set on the first statement of every line, and each hit is counted. Scripts
+//! evaluated several times with the same name are merged in reports.
+//!
+//! Functions are found by scanning the source of each script once it has been
+//! compiled, so functions that are never called are reported with no hits.
+//! Calls are counted as executions of a function's first statement.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! let coverage = js::coverage::Coverage::start(guard).unwrap();
+//! js::script::eval_with_name(guard, "plugin.js", "var x = 1;\nx += 1;").unwrap();
+//!
+//! let report = coverage.to_lcov();
+//! assert!(report.contains("SF:plugin.js"));
+//! # }).unwrap();
+//! ```
+use crate::debug::{self, Debugger, Event, Location, Script, Step};
+use crate::{ContextGuard, Result};
+use serde_json::{json, Map, Value as Json};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+/// The name the debugger uses for the top level code of a script.
+const GLOBAL_CODE: &str = "Global code";
+
+/// The name of functions that are not named, nor assigned to a name.
+const ANONYMOUS: &str = "(anonymous)";
+
+/// Keywords that may be followed by a parenthesized expression and a block,
+/// like a method.
+const CONTROL_KEYWORDS: &[&str] = &["catch", "for", "function", "if", "switch", "while", "with"];
+
+/// Keywords after which a `/` starts a regular expression, not a division.
+const EXPRESSION_KEYWORDS: &[&str] = &[
+  "await",
+  "case",
+  "delete",
+  "do",
+  "else",
+  "in",
+  "instanceof",
+  "new",
+  "of",
+  "return",
+  "throw",
+  "typeof",
+  "void",
+  "yield",
+];
+
+/// A collector of code coverage, for scripts of a runtime.
+///
+/// Coverage is no longer collected once the collector is dropped.
+pub struct Coverage {
+  state: Arc<Mutex<State>>,
+  _debugger: Debugger,
+}
+
+impl Coverage {
+  /// Starts collecting coverage of scripts in the runtime of the active
+  /// context. Only scripts compiled after this call are covered.
+  pub fn start(guard: &ContextGuard) -> Result<Coverage> {
+    let state = Arc::new(Mutex::new(State::default()));
+    let debugger = {
+      let state = state.clone();
+      Debugger::start(
+        guard,
+        Box::new(move |guard, event| {
+          let mut state = state.lock().expect("recording coverage");
+          match event {
+            Event::SourceCompile(script) => state.instrument(guard, &script),
+            Event::Breakpoint { id, location } => state.hit(guard, id, &location),
+            _ => (),
+          }
+          Step::Continue
+        }),
+      )?
+    };
+
+    Ok(Coverage {
+      state,
+      _debugger: debugger,
+    })
+  }
+
+  /// Returns the coverage of each script, ordered by name.
+  pub fn scripts(&self) -> Vec<ScriptCoverage> {
+    let state = self.state.lock().expect("reading coverage");
+    let mut scripts: BTreeMap<&str, ScriptCoverage> = BTreeMap::new();
+
+    for script in state.scripts.values() {
+      match scripts.get_mut(script.file_name.as_str()) {
+        Some(merged) => merged.merge(script),
+        None => {
+          scripts.insert(&script.file_name, script.clone());
+        },
+      }
+    }
+
+    scripts.into_values().collect()
+  }
+
+  /// Returns a report in the LCOV trace file format.
+  pub fn to_lcov(&self) -> String {
+    let mut report = String::new();
+    for script in self.scripts() {
+      let _ = writeln!(report, "TN:");
+      let _ = writeln!(report, "SF:{}", script.file_name);
+
+      for function in &script.functions {
+        let _ = writeln!(report, "FN:{},{}", function.line + 1, function.name);
+      }
+      for function in &script.functions {
+        let _ = writeln!(report, "FNDA:{},{}", function.hits, function.name);
+      }
+      let called = script.functions.iter().filter(|function| function.hits > 0);
+      let _ = writeln!(report, "FNF:{}", script.functions.len());
+      let _ = writeln!(report, "FNH:{}", called.count());
+
+      let lines = script.lines();
+      for (line, hits) in &lines {
+        let _ = writeln!(report, "DA:{},{}", line + 1, hits);
+      }
+      let _ = writeln!(report, "LF:{}", lines.len());
+      let _ = writeln!(
+        report,
+        "LH:{}",
+        lines.values().filter(|&&hits| hits > 0).count()
+      );
+      let _ = writeln!(report, "end_of_record");
+    }
+    report
+  }
+
+  /// Returns a report in the Istanbul JSON format (i.e `coverage-final.json`).
+  ///
+  /// Lines are one-based and columns are zero-based, as used by Istanbul.
+  pub fn to_istanbul(&self) -> String {
+    let files = self
+      .scripts()
+      .into_iter()
+      .map(|script| {
+        let statement_map = indexed(&script.statements, |statement| {
+          json!({
+            "start": position(statement.line, statement.column),
+            "end": position(statement.end_line, statement.end_column),
+          })
+        });
+        let function_map = indexed(&script.functions, |function| {
+          let start = position(function.line, function.column);
+          let range = json!({ "start": start, "end": start });
+          json!({ "name": function.name, "decl": range, "loc": range, "line": function.line + 1 })
+        });
+
+        let file = json!({
+          "path": script.file_name,
+          "statementMap": statement_map,
+          "fnMap": function_map,
+          "branchMap": {},
+          "s": indexed(&script.statements, |statement| json!(statement.hits)),
+          "f": indexed(&script.functions, |function| json!(function.hits)),
+          "b": {},
+        });
+        (script.file_name, file)
+      })
+      .collect::<Map<_, _>>();
+
+    Json::Object(files).to_string()
+  }
+}
+
+/// The coverage of a script.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScriptCoverage {
+  /// The script's name.
+  pub file_name: String,
+  /// The script's statements, ordered by their location.
+  pub statements: Vec<StatementCoverage>,
+  /// The script's functions, ordered by their location.
+  pub functions: Vec<FunctionCoverage>,
+}
+
+impl ScriptCoverage {
+  /// Returns the number of executions of each line with a statement.
+  pub fn lines(&self) -> BTreeMap<u32, u32> {
+    let mut lines = BTreeMap::new();
+    for statement in &self.statements {
+      let hits = lines.entry(statement.line).or_insert(0);
+      *hits = statement.hits.max(*hits);
+    }
+    lines
+  }
+
+  /// Adds the coverage of another compilation of the same script.
+  fn merge(&mut self, other: &ScriptCoverage) {
+    for statement in &other.statements {
+      let key = (statement.line, statement.column);
+      match self
+        .statements
+        .iter_mut()
+        .find(|s| (s.line, s.column) == key)
+      {
+        Some(existing) => existing.hits += statement.hits,
+        None => self.statements.push(statement.clone()),
+      }
+    }
+
+    for function in &other.functions {
+      let key = (function.line, function.column);
+      match self
+        .functions
+        .iter_mut()
+        .find(|f| (f.line, f.column) == key)
+      {
+        Some(existing) => existing.hits += function.hits,
+        None => self.functions.push(function.clone()),
+      }
+    }
+
+    self.statements.sort_by_key(|s| (s.line, s.column));
+    self.functions.sort_by_key(|f| (f.line, f.column));
+  }
+}
+
+/// The coverage of a statement. Lines and columns are zero-based.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatementCoverage {
+  /// The line the statement starts at.
+  pub line: u32,
+  /// The column the statement starts at.
+  pub column: u32,
+  /// The line the statement ends at.
+  pub end_line: u32,
+  /// The column the statement ends at.
+  pub end_column: u32,
+  /// The number of times the statement was executed.
+  pub hits: u32,
+}
+
+impl StatementCoverage {
+  fn from_location(location: &Location) -> Self {
+    let mut end = (location.line, location.column);
+    for (index, text) in location.source_text.split('\n').enumerate() {
+      let length = text.chars().count() as u32;
+      end = if index == 0 {
+        (end.0, end.1 + length)
+      } else {
+        (end.0 + 1, length)
+      };
+    }
+
+    StatementCoverage {
+      line: location.line,
+      column: location.column,
+      end_line: end.0,
+      end_column: end.1,
+      hits: 0,
+    }
+  }
+}
+
+/// The coverage of a function. Lines and columns are zero-based.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionCoverage {
+  /// The function's name.
+  pub name: String,
+  /// The line of the function's declaration.
+  pub line: u32,
+  /// The column of the function's declaration.
+  pub column: u32,
+  /// The number of times the function was called.
+  pub hits: u32,
+  /// The location of the function's first executed statement.
+  entry: Option<(u32, u32)>,
+  /// The location of the function, as reported by the debugger.
+  declaration: Option<(u32, u32)>,
+}
+
+/// The coverage recorded by the debugger callback.
+#[derive(Default)]
+struct State {
+  scripts: BTreeMap<u32, ScriptCoverage>,
+  breakpoints: HashMap<u32, usize>,
+}
+
+impl State {
+  /// Sets a breakpoint on each line of a newly compiled script.
+  fn instrument(&mut self, guard: &ContextGuard, script: &Script) {
+    let file_name = match script.file_name {
+      Some(ref name) if !name.is_empty() => name.clone(),
+      _ => return,
+    };
+
+    let mut coverage = ScriptCoverage {
+      file_name,
+      ..ScriptCoverage::default()
+    };
+
+    if let Ok(source) = debug::source(guard, script.id) {
+      coverage.functions = functions(&source)
+        .into_iter()
+        .map(|(name, line, column)| FunctionCoverage {
+          name,
+          line,
+          column,
+          ..FunctionCoverage::default()
+        })
+        .collect();
+    }
+
+    for line in 0..script.line_count {
+      // Lines without any statements can not be instrumented
+      let breakpoint = match debug::set_breakpoint(guard, script.id, line, 0) {
+        Ok(breakpoint) => breakpoint,
+        Err(_) => continue,
+      };
+
+      // A breakpoint may be adjusted to a statement that is already covered
+      if self.breakpoints.contains_key(&breakpoint.id) {
+        continue;
+      }
+
+      let location = &breakpoint.location;
+      let key = (location.line, location.column);
+      if coverage
+        .statements
+        .iter()
+        .any(|s| (s.line, s.column) == key)
+      {
+        let _ = debug::remove_breakpoint(guard, breakpoint.id);
+        continue;
+      }
+
+      self
+        .breakpoints
+        .insert(breakpoint.id, coverage.statements.len());
+      coverage
+        .statements
+        .push(StatementCoverage::from_location(location));
+    }
+
+    self.scripts.insert(script.id, coverage);
+  }
+
+  /// Records the execution of a statement, and the call of its function.
+  fn hit(&mut self, guard: &ContextGuard, id: u32, location: &Location) {
+    let script = match self.scripts.get_mut(&location.script_id) {
+      Some(script) => script,
+      None => return,
+    };
+
+    if let Some(&index) = self.breakpoints.get(&id) {
+      script.statements[index].hits += 1;
+    }
+
+    let function = debug::stack_trace(guard)
+      .ok()
+      .and_then(|frames| frames.into_iter().next())
+      .and_then(|frame| debug::function(guard, frame.function_handle).ok());
+    let function = match function {
+      Some(ref function) if function.name != GLOBAL_CODE => function,
+      _ => return,
+    };
+
+    let entry = (location.line, location.column);
+    let key = (function.location.line, function.location.column);
+    match find_function(&script.functions, key) {
+      Some(index) => {
+        let existing = &mut script.functions[index];
+        existing.declaration = Some(key);
+        if *existing.entry.get_or_insert(entry) == entry {
+          existing.hits += 1;
+        }
+      },
+      // The function was not found when the script was scanned
+      None => {
+        script.functions.push(FunctionCoverage {
+          name: function.name.clone(),
+          line: key.0,
+          column: key.1,
+          hits: 1,
+          entry: Some(entry),
+          declaration: Some(key),
+        });
+        script.functions.sort_by_key(|f| (f.line, f.column));
+      },
+    }
+  }
+}
+
+/// Returns the index of a function, by the location the debugger reports
+/// for it.
+///
+/// The debugger and the scan may disagree on where a function starts within
+/// its line, so an unmatched function on the same line is used otherwise,
+/// preferring the last one starting before the location.
+fn find_function(functions: &[FunctionCoverage], (line, column): (u32, u32)) -> Option<usize> {
+  let reported = functions
+    .iter()
+    .position(|f| f.declaration == Some((line, column)));
+  reported.or_else(|| {
+    let candidates = functions
+      .iter()
+      .enumerate()
+      .filter(|(_, f)| f.line == line && f.declaration.is_none());
+    let before = candidates
+      .clone()
+      .filter(|(_, f)| f.column <= column)
+      .last();
+    before
+      .or_else(|| candidates.min_by_key(|(_, f)| f.column))
+      .map(|(index, _)| index)
+  })
+}
+
+/// A token of a script, as far as it matters for finding functions.
+#[derive(Debug, PartialEq)]
+enum Token {
+  /// An identifier or keyword.
+  Word(String),
+  /// A punctuator; only `=>` has more than one character.
+  Punct(&'static str),
+  /// A single character punctuator.
+  Char(char),
+  /// A string, template, number or regular expression literal.
+  Literal,
+}
+
+/// Returns the name, line and column of every function in a script.
+///
+/// This recognizes `function` declarations and expressions, arrow functions
+/// and methods, without parsing the script. Functions within template literal
+/// substitutions are not found.
+fn functions(source: &str) -> Vec<(String, u32, u32)> {
+  let tokens = tokenize(source);
+  let word = |index: usize| match tokens.get(index) {
+    Some((Token::Word(word), ..)) => Some(word.as_str()),
+    _ => None,
+  };
+  let is = |index: usize, expected: char| {
+    tokens
+      .get(index)
+      .map_or(false, |(token, ..)| *token == Token::Char(expected))
+  };
+
+  // Unnamed functions take the name they are assigned to (e.g `var f = ...`)
+  let assigned = |start: usize| {
+    let name = start.checked_sub(2).and_then(word);
+    let assignment = start
+      .checked_sub(1)
+      .map_or(false, |index| is(index, '=') || is(index, ':'));
+    name.filter(|_| assignment).unwrap_or(ANONYMOUS).to_string()
+  };
+
+  // Returns the index of the bracket matching the one at an index
+  let matching = |index: usize, open: char, close: char, forward: bool| {
+    let mut depth = 0;
+    let mut index = index as isize;
+    while let Some((token, ..)) = tokens.get(index as usize).filter(|_| index >= 0) {
+      match token {
+        Token::Char(c) if *c == open => depth += 1,
+        Token::Char(c) if *c == close => depth -= 1,
+        _ => (),
+      }
+      if depth == 0 {
+        return Some(index as usize);
+      }
+      index += if forward { 1 } else { -1 };
+    }
+    None
+  };
+
+  // Async functions start at their `async` keyword
+  let with_async = |start: usize| match start.checked_sub(1).and_then(word) {
+    Some("async") => start - 1,
+    _ => start,
+  };
+
+  let mut functions = Vec::new();
+  for (index, (token, ..)) in tokens.iter().enumerate() {
+    match token {
+      Token::Word(keyword) if keyword == "function" => {
+        let start = with_async(index);
+        let next = if is(index + 1, '*') {
+          index + 2
+        } else {
+          index + 1
+        };
+        let name = word(next)
+          .filter(|_| is(next + 1, '('))
+          .map_or_else(|| assigned(start), str::to_string);
+        let (_, line, column) = tokens[start];
+        functions.push((name, line, column));
+      },
+      Token::Punct("=>") => {
+        let start = match index.checked_sub(1).map(|previous| &tokens[previous].0) {
+          Some(Token::Word(_)) => index - 1,
+          Some(Token::Char(')')) => match matching(index - 1, ')', '(', false) {
+            Some(start) => start,
+            None => continue,
+          },
+          _ => continue,
+        };
+        let start = with_async(start);
+        let (_, line, column) = tokens[start];
+        functions.push((assigned(start), line, column));
+      },
+      Token::Word(name) if is(index + 1, '(') && !CONTROL_KEYWORDS.contains(&name.as_str()) => {
+        let is_method = index.checked_sub(1).map_or(true, |previous| {
+          word(previous) != Some("function") && !is(previous, '.') && !is(previous, '*')
+        });
+        let body = matching(index + 1, '(', ')', true).map(|end| end + 1);
+        if is_method && body.map_or(false, |body| is(body, '{')) {
+          let (_, line, column) = tokens[index];
+          functions.push((name.clone(), line, column));
+        }
+      },
+      _ => (),
+    }
+  }
+  functions
+}
+
+/// Splits a script into tokens, each with its zero-based line and column.
+fn tokenize(source: &str) -> Vec<(Token, u32, u32)> {
+  let chars = source.chars().collect::<Vec<_>>();
+  let mut tokens: Vec<(Token, u32, u32)> = Vec::new();
+  let (mut index, mut line, mut column) = (0, 0, 0);
+
+  // Advances past a number of characters, keeping track of the position
+  let mut advance = |index: &mut usize, count: usize| {
+    for &c in &chars[*index..(*index + count).min(chars.len())] {
+      if c == '\n' {
+        line += 1;
+        column = 0;
+      } else {
+        column += 1;
+      }
+    }
+    *index += count;
+    (line, column)
+  };
+
+  // Returns the length of a quoted literal, starting at its opening quote
+  let quoted = |start: usize, quote: char| {
+    let mut end = start + 1;
+    let mut braces = 0;
+    while let Some(&c) = chars.get(end) {
+      end += 1;
+      match c {
+        '\\' => end += 1,
+        '{' if quote == '`' && chars.get(end - 2) == Some(&'$') => braces += 1,
+        '}' if braces > 0 => braces -= 1,
+        c if c == quote && braces == 0 => break,
+        '\n' if quote != '`' => break,
+        _ => (),
+      }
+    }
+    end.min(chars.len()) - start
+  };
+
+  let mut position = (0, 0);
+  while let Some(&c) = chars.get(index) {
+    let next = chars.get(index + 1).cloned();
+    let start = index;
+    let token = match c {
+      c if c.is_whitespace() => None,
+      '/' if next == Some('/') => {
+        let end = chars[index..].iter().position(|&c| c == '\n');
+        index += end.unwrap_or(chars.len() - index) - 1;
+        None
+      },
+      '/' if next == Some('*') => {
+        let end =
+          (index + 2..chars.len()).find(|&i| chars[i] == '*' && chars.get(i + 1) == Some(&'/'));
+        index = end.map_or(chars.len(), |end| end + 1) - 1;
+        None
+      },
+      '/' if allows_regexp(tokens.last().map(|(token, ..)| token)) => {
+        let mut end = index + 1;
+        let mut class = false;
+        while let Some(&c) = chars.get(end) {
+          end += 1;
+          match c {
+            '\\' => end += 1,
+            '[' => class = true,
+            ']' => class = false,
+            '/' if !class => break,
+            '\n' => break,
+            _ => (),
+          }
+        }
+        while chars.get(end).map_or(false, |c| c.is_alphanumeric()) {
+          end += 1;
+        }
+        index = end.min(chars.len()) - 1;
+        Some(Token::Literal)
+      },
+      '\'' | '"' | '`' => {
+        index += quoted(index, c) - 1;
+        Some(Token::Literal)
+      },
+      c if c.is_alphanumeric() || c == '_' || c == '$' => {
+        let length = chars[index..]
+          .iter()
+          .take_while(|&&c| c.is_alphanumeric() || c == '_' || c == '$')
+          .count();
+        index += length - 1;
+        Some(if c.is_ascii_digit() {
+          Token::Literal
+        } else {
+          Token::Word(chars[start..start + length].iter().collect())
+        })
+      },
+      '=' if next == Some('>') => {
+        index += 1;
+        Some(Token::Punct("=>"))
+      },
+      c => Some(Token::Char(c)),
+    };
+
+    if let Some(token) = token {
+      tokens.push((token, position.0, position.1));
+    }
+    index += 1;
+    position = advance(&mut start.clone(), index - start);
+  }
+  tokens
+}
+
+/// Returns whether a `/` after a token starts a regular expression.
+fn allows_regexp(previous: Option<&Token>) -> bool {
+  match previous {
+    None | Some(Token::Punct(_)) => true,
+    Some(Token::Word(word)) => EXPRESSION_KEYWORDS.contains(&word.as_str()),
+    Some(Token::Char(c)) => !matches!(c, ')' | ']' | '}'),
+    Some(Token::Literal) => false,
+  }
+}
+
+/// Returns a map of items, keyed by their index, as used by Istanbul.
+fn indexed<T, F: Fn(&T) -> Json>(items: &[T], value: F) -> Json {
+  let map = items
+    .iter()
+    .enumerate()
+    .map(|(index, item)| (index.to_string(), value(item)));
+  Json::Object(map.collect())
+}
+
+/// Returns an Istanbul position, from a zero-based line and column.
+fn position(line: u32, column: u32) -> Json {
+  json!({ "line": line + 1, "column": column })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Coverage;
+  use crate::{json, script, test, Property};
+
+  const PLUGIN: &str = "function double(x) {
+  return x * 2;
+}
+function unused() {
+  return 0;
+}
+var total = double(1) + double(2);
+if (total > 100) {
+  total = 0;
+}";
+
+  #[test]
+  fn lines_and_functions() {
+    test::run_with_context(|guard| {
+      let coverage = Coverage::start(guard).unwrap();
+      script::eval(guard, "var anonymous = 1;").unwrap();
+      script::eval_with_name(guard, "plugin.js", PLUGIN).unwrap();
+      script::eval_with_name(guard, "plugin.js", PLUGIN).unwrap();
+
+      let scripts = coverage.scripts();
+      assert_eq!(scripts.len(), 1);
+      assert_eq!(scripts[0].file_name, "plugin.js");
+
+      let lines = scripts[0].lines();
+      assert_eq!(lines[&1], 4);
+      assert_eq!(lines[&4], 0);
+      assert_eq!(lines[&6], 2);
+      assert_eq!(lines[&8], 0);
+
+      let functions = &scripts[0].functions;
+      let double = functions.iter().find(|f| f.name == "double").unwrap();
+      assert_eq!((double.line, double.hits), (0, 4));
+      let unused = functions.iter().find(|f| f.name == "unused").unwrap();
+      assert_eq!((unused.line, unused.hits), (3, 0));
+    });
+  }
+
+  #[test]
+  fn function_scan() {
+    let source = "function a() {}
+var b = function* () { return '/* function c() {} */'; };
+var d = (x, y) => x / y, e = async x => /=>/.test(x);
+class F { constructor() {} g(h) { if (h) { return [].map(i => i); } } }";
+    let names = super::functions(source)
+      .into_iter()
+      .map(|(name, line, column)| format!("{}@{}:{}", name, line, column))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      names,
+      [
+        "a@0:0",
+        "b@1:8",
+        "d@2:8",
+        "e@2:29",
+        "constructor@3:10",
+        "g@3:27",
+        "(anonymous)@3:57"
+      ]
+    );
+  }
+
+  #[test]
+  fn reports() {
+    test::run_with_context(|guard| {
+      let coverage = Coverage::start(guard).unwrap();
+      script::eval_with_name(guard, "plugin \"a\".js", PLUGIN).unwrap();
+
+      let lcov = coverage.to_lcov();
+      assert!(lcov.starts_with("TN:\nSF:plugin \"a\".js\n"));
+      assert!(lcov.contains("\nFNDA:2,double\n"));
+      assert!(lcov.contains("\nFNDA:0,unused\n"));
+      assert!(lcov.contains("\nDA:2,2\n"));
+      assert!(lcov.contains("\nDA:5,0\n"));
+      assert!(lcov.ends_with("end_of_record\n"));
+
+      let istanbul = coverage.to_istanbul();
+      let report = json::parse(guard, &istanbul)
+        .unwrap()
+        .into_object()
+        .unwrap();
+      let file = report
+        .get(guard, Property::new(guard, "plugin \"a\".js"))
+        .into_object()
+        .unwrap();
+      let path = file.get(guard, Property::new(guard, "path"));
+      assert_eq!(path.to_string(guard), "plugin \"a\".js");
+      assert!(istanbul.contains("\"name\":\"double\""));
+      assert!(istanbul.contains("\"name\":\"unused\""));
+    });
+  }
+}
@@ -0,0 +1,731 @@
+//! Script debugging, using the runtime's diagnostic API.
+//!
+//! A `Debugger` puts a runtime in debug mode, and reports debug events to a
+//! callback. Events that pause the script (e.g a breakpoint) are reported
+//! while the script is suspended, so its state can be inspected using the
+//! functions in this module. Returning from the callback resumes the script,
+//! as specified by the returned `Step`.
+//!
+//! Exceptions are only reported if the runtime was created with
+//! `dispatch_exceptions`, and once `set_break_on_exception` has been called.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # use js::debug::{Debugger, Event, Step};
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! let _debugger = Debugger::start(guard, Box::new(|guard, event| {
+//!   if let Event::DebuggerStatement(_) = event {
+//!     let result = js::debug::evaluate(guard, "answer * 2", 0).unwrap();
+//!     assert_eq!(result.display.unwrap(), "84");
+//!   }
+//!   Step::Continue
+//! })).unwrap();
+//!
+//! js::script::eval(guard, "var answer = 42; debugger;").unwrap();
+//! # }).unwrap();
+//! ```
+use crate::runtime::Instance;
+use crate::{util::jstry, value, Context, ContextGuard, Error, Property, Result};
+use chakracore_sys::*;
+use libc::c_void;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::{mem, ptr, thread};
+
+/// A callback for debug events, returning how the script should be resumed.
+///
+/// The returned step is ignored for events that do not pause the script.
+pub type EventCallback = dyn for<'ctx> FnMut(&ContextGuard<'ctx>, Event) -> Step + Send;
+
+/// A debug event reported by the runtime.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+  /// A script has been compiled.
+  SourceCompile(Script),
+  /// A script failed to compile.
+  CompileError { script: Script, message: String },
+  /// The script is paused at a breakpoint.
+  Breakpoint { id: u32, location: Location },
+  /// The script is paused after a step.
+  StepComplete(Location),
+  /// The script is paused at a `debugger` statement.
+  DebuggerStatement(Location),
+  /// The script is paused after an async break was requested.
+  AsyncBreak(Location),
+  /// The script is paused at an exception.
+  Exception {
+    location: Location,
+    uncaught: bool,
+    exception: Variable,
+  },
+}
+
+impl Event {
+  /// Returns whether the script is paused during the event.
+  pub fn is_break(&self) -> bool {
+    !matches!(self, Event::SourceCompile(_) | Event::CompileError { .. })
+  }
+
+  /// Creates an event from its raw representation.
+  fn from_raw<'ctx>(
+    guard: &ContextGuard<'ctx>,
+    event: JsDiagDebugEvent,
+    data: &value::Object<'ctx>,
+  ) -> Self {
+    match event {
+      JsDiagDebugEvent::SourceCompile => Event::SourceCompile(Script::from_object(guard, data)),
+      JsDiagDebugEvent::CompileError => Event::CompileError {
+        script: Script::from_object(guard, data),
+        message: text(guard, data, "error").unwrap_or_default(),
+      },
+      JsDiagDebugEvent::Breakpoint => Event::Breakpoint {
+        id: number(guard, data, "breakpointId"),
+        location: Location::from_object(guard, data),
+      },
+      JsDiagDebugEvent::StepComplete => Event::StepComplete(Location::from_object(guard, data)),
+      JsDiagDebugEvent::DebuggerStatement => {
+        Event::DebuggerStatement(Location::from_object(guard, data))
+      },
+      JsDiagDebugEvent::AsyncBreak => Event::AsyncBreak(Location::from_object(guard, data)),
+      JsDiagDebugEvent::RuntimeException => Event::Exception {
+        location: Location::from_object(guard, data),
+        uncaught: field(guard, data, "uncaught").to_bool(guard),
+        exception: object(guard, data, "exception")
+          .map(|exception| Variable::from_object(guard, &exception))
+          .unwrap_or_default(),
+      },
+    }
+  }
+}
+
+/// How a paused script should be resumed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Step {
+  /// Continue until the next break.
+  Continue,
+  /// Step into the next function call.
+  StepIn,
+  /// Step out of the current function.
+  StepOut,
+  /// Step over the next statement.
+  StepOver,
+}
+
+impl Step {
+  /// Returns the step's raw representation.
+  fn as_raw(self) -> JsDiagStepType {
+    match self {
+      Step::Continue => JsDiagStepType::Continue,
+      Step::StepIn => JsDiagStepType::StepIn,
+      Step::StepOut => JsDiagStepType::StepOut,
+      Step::StepOver => JsDiagStepType::StepOver,
+    }
+  }
+}
+
+/// Which exceptions pause the script.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BreakOnException {
+  /// Exceptions never pause the script.
+  Never,
+  /// Only exceptions that are not caught pause the script.
+  Uncaught,
+  /// All exceptions pause the script, as soon as they are thrown.
+  FirstChance,
+}
+
+/// A script known by the debugger.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Script {
+  /// The script's identifier.
+  pub id: u32,
+  /// The script's name, if any.
+  pub file_name: Option<String>,
+  /// The number of lines in the script.
+  pub line_count: u32,
+  /// The length of the script's source code.
+  pub source_length: u32,
+}
+
+impl Script {
+  fn from_object<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>) -> Self {
+    Script {
+      id: number(guard, object, "scriptId"),
+      file_name: text(guard, object, "fileName"),
+      line_count: number(guard, object, "lineCount"),
+      source_length: number(guard, object, "sourceLength"),
+    }
+  }
+}
+
+/// A location in a script. Lines and columns are zero-based.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Location {
+  /// The script's identifier.
+  pub script_id: u32,
+  /// The line within the script.
+  pub line: u32,
+  /// The column within the line.
+  pub column: u32,
+  /// The source code of the statement at the location.
+  pub source_text: String,
+}
+
+impl Location {
+  fn from_object<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>) -> Self {
+    Location {
+      script_id: number(guard, object, "scriptId"),
+      line: number(guard, object, "line"),
+      column: number(guard, object, "column"),
+      source_text: text(guard, object, "sourceText").unwrap_or_default(),
+    }
+  }
+}
+
+/// A breakpoint set in a script.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Breakpoint {
+  /// The breakpoint's identifier.
+  pub id: u32,
+  /// The location of the breakpoint.
+  pub location: Location,
+}
+
+impl Breakpoint {
+  fn from_object<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>) -> Self {
+    Breakpoint {
+      id: number(guard, object, "breakpointId"),
+      location: Location::from_object(guard, object),
+    }
+  }
+}
+
+/// A frame of a paused script's call stack.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StackFrame {
+  /// The frame's index, zero being the top of the stack.
+  pub index: u32,
+  /// The location of the frame.
+  pub location: Location,
+  /// The handle of the frame's function.
+  pub function_handle: u32,
+}
+
+impl StackFrame {
+  fn from_object<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>) -> Self {
+    StackFrame {
+      index: number(guard, object, "index"),
+      location: Location::from_object(guard, object),
+      function_handle: number(guard, object, "functionHandle"),
+    }
+  }
+}
+
+/// A function's declaration, as presented by the debugger.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Function {
+  /// The function's name.
+  pub name: String,
+  /// The location of the function's declaration.
+  pub location: Location,
+}
+
+impl Function {
+  fn from_object<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>) -> Self {
+    Function {
+      name: text(guard, object, "name").unwrap_or_default(),
+      location: Location::from_object(guard, object),
+    }
+  }
+}
+
+/// A snapshot of a value, as presented by the debugger.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Variable {
+  /// The name of the variable or property.
+  pub name: String,
+  /// The type of the value (e.g `number`).
+  pub kind: String,
+  /// The class of the value, if it's an object.
+  pub class_name: Option<String>,
+  /// The display representation of the value.
+  pub display: Option<String>,
+  /// The handle of the value, used to retrieve its properties.
+  pub handle: u32,
+}
+
+impl Variable {
+  fn from_object<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>) -> Self {
+    Variable {
+      name: text(guard, object, "name").unwrap_or_default(),
+      kind: text(guard, object, "type").unwrap_or_default(),
+      class_name: text(guard, object, "className"),
+      display: text(guard, object, "display"),
+      handle: number(guard, object, "handle"),
+    }
+  }
+}
+
+/// The variables of a stack frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameProperties {
+  /// The frame's `this` value.
+  pub this: Option<Variable>,
+  /// The exception being thrown, if any.
+  pub exception: Option<Variable>,
+  /// The frame's `arguments` object.
+  pub arguments: Option<Variable>,
+  /// The value returned by the frame's function, if it is returning.
+  pub return_value: Option<Variable>,
+  /// The frame's local variables.
+  pub locals: Vec<Variable>,
+  /// The handles of the frame's enclosing scopes, innermost first.
+  pub scopes: Vec<u32>,
+  /// The handle of the global object.
+  pub globals: Option<u32>,
+}
+
+/// A handle to a runtime in debug mode.
+///
+/// Debugging is stopped once the handle is dropped.
+///
+/// If the callback panics, the script is resumed and the callback is not
+/// invoked again. The panic is propagated once the debugger is dropped.
+pub struct Debugger {
+  state: Option<Box<State>>,
+  context: Context,
+}
+
+/// The state shared with the debug event callback.
+struct State {
+  callback: Box<EventCallback>,
+  panic: Option<Box<dyn Any + Send>>,
+}
+
+/// A handle for pausing a debugged runtime, which can be sent to and shared
+/// between threads.
+///
+/// The handle keeps the runtime alive, but does not keep it in debug mode.
+#[derive(Clone)]
+pub struct AsyncBreak(Arc<Instance>);
+
+impl AsyncBreak {
+  /// Requests the runtime to pause at the next statement.
+  ///
+  /// An error is returned if the runtime is no longer being debugged.
+  pub fn request(&self) -> Result<()> {
+    jstry(unsafe { JsDiagRequestAsyncBreak(self.0.as_raw()) })
+  }
+}
+
+impl Debugger {
+  /// Starts debugging the runtime of the active context.
+  ///
+  /// Scripts compiled before the debugger was started have no debug
+  /// information, and can not be paused.
+  pub fn start(guard: &ContextGuard, callback: Box<EventCallback>) -> Result<Debugger> {
+    let context = guard.context();
+    let mut state = Box::new(State {
+      callback,
+      panic: None,
+    });
+    jstry(unsafe {
+      JsDiagStartDebugging(
+        context.runtime(),
+        Some(Self::event_callback),
+        &mut *state as *mut _ as *mut _,
+      )
+    })?;
+    Ok(Debugger {
+      state: Some(state),
+      context,
+    })
+  }
+
+  /// Requests the runtime to pause at the next statement.
+  ///
+  /// Use `async_break` to request it from another thread.
+  pub fn request_async_break(&self) -> Result<()> {
+    jstry(unsafe { JsDiagRequestAsyncBreak(self.context.runtime()) })
+  }
+
+  /// Returns a handle for requesting async breaks from any thread, or `None`
+  /// if the runtime has been dropped.
+  pub fn async_break(&self) -> Option<AsyncBreak> {
+    self.context.runtime_instance().map(AsyncBreak)
+  }
+
+  /// Sets which exceptions pause the script.
+  pub fn set_break_on_exception(&self, mode: BreakOnException) -> Result<()> {
+    let attributes = match mode {
+      BreakOnException::Never => JsDiagBreakOnExceptionAttributeNone,
+      BreakOnException::Uncaught => JsDiagBreakOnExceptionAttributeUncaught,
+      BreakOnException::FirstChance => JsDiagBreakOnExceptionAttributeFirstChance,
+    };
+    jstry(unsafe { JsDiagSetBreakOnException(self.context.runtime(), attributes) })
+  }
+
+  /// A debug event callback, triggered by the runtime.
+  unsafe extern "system" fn event_callback(
+    event: JsDiagDebugEvent,
+    data: JsValueRef,
+    state: *mut c_void,
+  ) {
+    let state = &mut *(state as *mut State);
+    if state.panic.is_some() {
+      return;
+    }
+
+    // A panic must not unwind into the runtime
+    let callback = &mut state.callback;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+      // There is always an active context in debug events
+      Context::exec_with_current(|guard| {
+        let data = value::Object::from_raw(data);
+        let event = Event::from_raw(guard, event, &data);
+        let is_break = event.is_break();
+
+        let step = callback(guard, event);
+        if is_break {
+          jsassert!(JsDiagSetStepType(step.as_raw()));
+        }
+      })
+      .expect("executing debug event callback");
+    }));
+
+    if let Err(panic) = result {
+      state.panic = Some(panic);
+    }
+  }
+}
+
+impl Drop for Debugger {
+  /// Stops debugging the runtime.
+  fn drop(&mut self) {
+    let stopped = Context::exec_with_context(&self.context, |_| {
+      jstry(unsafe { JsDiagStopDebugging(self.context.runtime(), ptr::null_mut()) })
+    });
+
+    if let Ok(Ok(())) = stopped {
+      let panic = self.state.take().and_then(|state| state.panic);
+      if let Some(panic) = panic.filter(|_| !thread::panicking()) {
+        panic::resume_unwind(panic);
+      }
+      return;
+    }
+
+    // The runtime may still use the callback, so it must not be released
+    mem::forget(self.state.take());
+  }
+}
+
+/// Sets a breakpoint in a script. The location is adjusted to the nearest
+/// statement, which is returned as part of the breakpoint.
+pub fn set_breakpoint(
+  guard: &ContextGuard,
+  script_id: u32,
+  line: u32,
+  column: u32,
+) -> Result<Breakpoint> {
+  let breakpoint = diagnostic(guard, |result| unsafe {
+    JsDiagSetBreakpoint(script_id, line, column, result)
+  })?;
+  Ok(Breakpoint::from_object(guard, &breakpoint))
+}
+
+/// Removes a breakpoint.
+pub fn remove_breakpoint(_guard: &ContextGuard, id: u32) -> Result<()> {
+  jstry(unsafe { JsDiagRemoveBreakpoint(id) })
+}
+
+/// Returns all breakpoints.
+pub fn breakpoints(guard: &ContextGuard) -> Result<Vec<Breakpoint>> {
+  let breakpoints = diagnostic(guard, |result| unsafe { JsDiagGetBreakpoints(result) })?;
+  Ok(list(guard, &breakpoints, Breakpoint::from_object))
+}
+
+/// Returns all scripts compiled while debugging.
+pub fn scripts(guard: &ContextGuard) -> Result<Vec<Script>> {
+  let scripts = diagnostic(guard, |result| unsafe { JsDiagGetScripts(result) })?;
+  Ok(list(guard, &scripts, Script::from_object))
+}
+
+/// Returns the source code of a script.
+pub fn source(guard: &ContextGuard, script_id: u32) -> Result<String> {
+  let source = diagnostic(guard, |result| unsafe {
+    JsDiagGetSource(script_id, result)
+  })?;
+  Ok(text(guard, &source, "source").unwrap_or_default())
+}
+
+/// Returns the call stack of the paused script.
+pub fn stack_trace(guard: &ContextGuard) -> Result<Vec<StackFrame>> {
+  let frames = diagnostic(guard, |result| unsafe { JsDiagGetStackTrace(result) })?;
+  Ok(list(guard, &frames, StackFrame::from_object))
+}
+
+/// Returns the declaration of a function, by its handle.
+pub fn function(guard: &ContextGuard, handle: u32) -> Result<Function> {
+  let function = diagnostic(guard, |result| unsafe {
+    JsDiagGetObjectFromHandle(handle, result)
+  })?;
+  Ok(Function::from_object(guard, &function))
+}
+
+/// Returns the variables of a stack frame of the paused script.
+pub fn stack_properties<'ctx>(guard: &ContextGuard<'ctx>, frame: u32) -> Result<FrameProperties> {
+  let properties = diagnostic(guard, |result| unsafe {
+    JsDiagGetStackProperties(frame, result)
+  })?;
+  let variable =
+    |name| object(guard, &properties, name).map(|object| Variable::from_object(guard, &object));
+  let handle = |object: &value::Object<'ctx>| number(guard, object, "handle");
+
+  Ok(FrameProperties {
+    this: variable("thisObject"),
+    exception: variable("exception"),
+    arguments: variable("arguments"),
+    return_value: variable("returnValue"),
+    locals: object(guard, &properties, "locals").map_or_else(Vec::new, |locals| {
+      list(guard, &locals, Variable::from_object)
+    }),
+    scopes: object(guard, &properties, "scopes").map_or_else(Vec::new, |scopes| {
+      list(guard, &scopes, |_, scope| handle(scope))
+    }),
+    globals: object(guard, &properties, "globals").map(|globals| handle(&globals)),
+  })
+}
+
+/// Returns the properties of a value, by its handle.
+pub fn properties(guard: &ContextGuard, handle: u32) -> Result<Vec<Variable>> {
+  let properties = diagnostic(guard, |result| unsafe {
+    JsDiagGetProperties(handle, 0, u32::MAX, result)
+  })?;
+  Ok(
+    object(guard, &properties, "properties").map_or_else(Vec::new, |properties| {
+      list(guard, &properties, Variable::from_object)
+    }),
+  )
+}
+
+/// Evaluates an expression within a stack frame of the paused script.
+///
+/// An exception thrown by the expression is returned as an error.
+pub fn evaluate(guard: &ContextGuard, expression: &str, frame: u32) -> Result<Variable> {
+  let expression = value::String::new(guard, expression);
+  let mut result = JsValueRef::new();
+  let code = unsafe {
+    JsDiagEvaluate(
+      expression.as_raw(),
+      frame,
+      JsParseScriptAttributeNone,
+      false,
+      &mut result,
+    )
+  };
+
+  // The result describes the exception, which is not set on the runtime
+  if code != JsErrorCode::ScriptException {
+    jstry(code)?;
+  }
+
+  let result = unsafe { value::Object::from_raw(result) };
+  let variable = Variable::from_object(guard, &result);
+  if code == JsErrorCode::ScriptException {
+    Err(Error::ScriptException(variable.display.unwrap_or_default()))
+  } else {
+    Ok(variable)
+  }
+}
+
+/// Calls a diagnostic function returning an object.
+fn diagnostic<'ctx, T>(_guard: &ContextGuard<'ctx>, call: T) -> Result<value::Object<'ctx>>
+where
+  T: FnOnce(&mut JsValueRef) -> JsErrorCode,
+{
+  let mut result = JsValueRef::new();
+  jstry(call(&mut result))?;
+  Ok(unsafe { value::Object::from_raw(result) })
+}
+
+/// Returns a property of a diagnostic object.
+fn field<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  object: &value::Object<'ctx>,
+  name: &str,
+) -> value::Value<'ctx> {
+  object.get(guard, Property::new(guard, name))
+}
+
+/// Returns a numeric property of a diagnostic object.
+fn number<'ctx>(guard: &ContextGuard<'ctx>, object: &value::Object<'ctx>, name: &str) -> u32 {
+  field(guard, object, name).to_double(guard) as u32
+}
+
+/// Returns a property of a diagnostic object as a string, if it's defined.
+fn text<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  object: &value::Object<'ctx>,
+  name: &str,
+) -> Option<String> {
+  let value = field(guard, object, name);
+  if value.is_undefined() || value.is_null() {
+    None
+  } else {
+    Some(value.to_string(guard))
+  }
+}
+
+/// Returns a property of a diagnostic object, if it's an object.
+fn object<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  object: &value::Object<'ctx>,
+  name: &str,
+) -> Option<value::Object<'ctx>> {
+  field(guard, object, name).into_object()
+}
+
+/// Converts a diagnostic array, skipping any elements that are not objects.
+fn list<'ctx, T, F>(guard: &ContextGuard<'ctx>, array: &value::Object<'ctx>, convert: F) -> Vec<T>
+where
+  F: Fn(&ContextGuard<'ctx>, &value::Object<'ctx>) -> T,
+{
+  let array: value::Value = array.clone().into();
+  array.into_array().map_or_else(Vec::new, |array| {
+    array
+      .iter(guard)
+      .filter_map(|element| element.into_object())
+      .map(|element| convert(guard, &element))
+      .collect()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Debugger, Event, Step};
+  use crate::{script, test};
+  use std::panic::{self, AssertUnwindSafe};
+  use std::sync::{Arc, Mutex};
+  use std::thread;
+
+  #[test]
+  fn debugger_statement() {
+    test::run_with_context(|guard| {
+      let locals = Arc::new(Mutex::new(Vec::new()));
+      let debugger = {
+        let locals = locals.clone();
+        Debugger::start(
+          guard,
+          Box::new(move |guard, event| {
+            if let Event::DebuggerStatement(location) = event {
+              assert_eq!(location.line, 2);
+              assert!(super::stack_trace(guard).unwrap().len() >= 2);
+
+              let properties = super::stack_properties(guard, 0).unwrap();
+              let mut locals = locals.lock().unwrap();
+              locals.extend(properties.locals.into_iter().map(|local| local.name));
+
+              let result = super::evaluate(guard, "a + b", 0).unwrap();
+              assert_eq!(result.display.unwrap(), "3");
+              assert!(super::evaluate(guard, "missing.property", 0).is_err());
+            }
+            Step::Continue
+          }),
+        )
+        .unwrap()
+      };
+
+      let code = "function sum(a, b) {\n  var c = a + b;\n  debugger;\n  return c;\n}\nsum(1, 2);";
+      assert_eq!(script::eval(guard, code).unwrap().to_integer(guard), 3);
+      drop(debugger);
+
+      assert!(locals.lock().unwrap().contains(&"c".to_string()));
+    });
+  }
+
+  #[test]
+  fn breakpoint() {
+    test::run_with_context(|guard| {
+      let values = Arc::new(Mutex::new(Vec::new()));
+      let _debugger = {
+        let values = values.clone();
+        Debugger::start(
+          guard,
+          Box::new(move |guard, event| {
+            match event {
+              Event::SourceCompile(script) => {
+                let breakpoint = super::set_breakpoint(guard, script.id, 1, 0).unwrap();
+                assert_eq!(breakpoint.location.line, 1);
+                assert_eq!(super::breakpoints(guard).unwrap(), vec![breakpoint]);
+              },
+              Event::Breakpoint { location, .. } => {
+                let value = super::evaluate(guard, "x", 0).unwrap();
+                values
+                  .lock()
+                  .unwrap()
+                  .push((location.line, value.display.unwrap()));
+              },
+              _ => (),
+            }
+            Step::Continue
+          }),
+        )
+        .unwrap()
+      };
+
+      script::eval(guard, "var x = 1;\nx += 1;\nx;").unwrap();
+      assert_eq!(*values.lock().unwrap(), vec![(1, "1".to_string())]);
+    });
+  }
+
+  #[test]
+  fn async_break() {
+    test::run_with_context(|guard| {
+      let breaks = Arc::new(Mutex::new(0));
+      let debugger = {
+        let breaks = breaks.clone();
+        Debugger::start(
+          guard,
+          Box::new(move |_, event| {
+            if let Event::AsyncBreak(_) = event {
+              *breaks.lock().unwrap() += 1;
+            }
+            Step::Continue
+          }),
+        )
+        .unwrap()
+      };
+
+      let handle = debugger.async_break().unwrap();
+      thread::spawn(move || handle.request().unwrap())
+        .join()
+        .unwrap();
+
+      script::eval(guard, "var x = 1;\nx += 1;").unwrap();
+      assert_eq!(*breaks.lock().unwrap(), 1);
+    });
+  }
+
+  #[test]
+  fn callback_panic() {
+    test::run_with_context(|guard| {
+      let debugger = Debugger::start(
+        guard,
+        Box::new(|_, event| match event {
+          Event::DebuggerStatement(_) => panic!("debugger statement"),
+          _ => Step::Continue,
+        }),
+      )
+      .unwrap();
+
+      let result = script::eval(guard, "debugger; 1 + 1").unwrap();
+      assert_eq!(result.to_integer(guard), 2);
+
+      let panic = panic::catch_unwind(AssertUnwindSafe(|| drop(debugger))).unwrap_err();
+      assert_eq!(panic.downcast_ref::<&str>(), Some(&"debugger statement"));
+    });
+  }
+}
@@ -0,0 +1,231 @@
+//! Reproducible replacements for time and randomness.
+//!
+//! Scripts that use `Math.random` or `Date` produce different results on
+//! every run. The replacements in this module are installed using a context
+//! builder, so script runs can be fully reproduced:
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # use std::time::Duration;
+//! # let runtime = js::Runtime::new().unwrap();
+//! let clock = js::deterministic::Clock::new(Duration::from_secs(60));
+//! let context = js::Context::builder(&runtime)
+//!   .random_seed(1337)
+//!   .clock(clock.clone())
+//!   .build()
+//!   .unwrap();
+//!
+//! clock.advance(Duration::from_millis(500));
+//! context
+//!   .exec_with(|guard| {
+//!     let result = js::script::eval(guard, "new Date().getTime()").unwrap();
+//!     assert_eq!(result.to_integer(guard), 60_500);
+//!   })
+//!   .unwrap();
+//! ```
+//!
+//! Only `Date` reads the virtual clock. The library has no timers of its own,
+//! so a host that provides `setTimeout` or similar must schedule them by the
+//! same `Clock` for runs to stay reproducible.
+use crate::convert::ConversionError;
+use crate::intrinsics::Intrinsic;
+use crate::{script, value, ContextGuard, Error, Result};
+use chakracore_sys::JsValueType;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A wrapper for the native `Date` constructor, reading the current time from
+/// a `now` function.
+const DATE_WRAPPER: &str = "(function (NativeDate, now) {
+  var construct = Reflect.construct;
+  var define = Object.defineProperty;
+
+  function Date(...args) {
+    if (new.target === undefined) {
+      return new NativeDate(now()).toString();
+    }
+    return construct(NativeDate, args.length === 0 ? [now()] : args, new.target);
+  }
+
+  define(Date, 'length', { value: 7, configurable: true });
+  define(Date, 'prototype', { value: NativeDate.prototype });
+  ['now', 'parse', 'UTC'].forEach(function (name) {
+    var value = name === 'now' ? now : NativeDate[name];
+    define(Date, name, { value: value, writable: true, configurable: true });
+  });
+  define(NativeDate.prototype, 'constructor', {
+    value: Date,
+    writable: true,
+    configurable: true,
+  });
+  return Date;
+})";
+
+/// A virtual clock, controlled by the host.
+///
+/// The time is measured relative to the Unix epoch, and only changes when it
+/// is explicitly set or advanced. Clones share the same time, so a host can
+/// keep a clone to drive the timers of its own event loop.
+#[derive(Clone, Debug, Default)]
+pub struct Clock(Arc<Mutex<Duration>>);
+
+impl Clock {
+  /// Creates a clock, starting at a time relative to the Unix epoch.
+  pub fn new(time: Duration) -> Self {
+    Clock(Arc::new(Mutex::new(time)))
+  }
+
+  /// Returns the current time of the clock.
+  pub fn now(&self) -> Duration {
+    *self.0.lock().expect("reading virtual clock")
+  }
+
+  /// Sets the current time of the clock.
+  pub fn set(&self, time: Duration) {
+    *self.0.lock().expect("setting virtual clock") = time;
+  }
+
+  /// Advances the clock by a duration.
+  pub fn advance(&self, duration: Duration) {
+    *self.0.lock().expect("advancing virtual clock") += duration;
+  }
+
+  /// Returns the current time in milliseconds, as used by `Date`.
+  fn millis(&self) -> f64 {
+    self.now().as_millis() as f64
+  }
+}
+
+/// A seeded pseudo-random number generator (xorshift128+).
+struct Random([u64; 2]);
+
+impl Random {
+  /// Creates a generator, with its state expanded from a seed (splitmix64).
+  fn new(seed: u64) -> Self {
+    let mut seed = seed;
+    let mut next = || {
+      seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+      let mut z = seed;
+      z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+      z ^ (z >> 31)
+    };
+    Random([next(), next()])
+  }
+
+  /// Returns the next number in the range [0, 1).
+  fn next(&mut self) -> f64 {
+    let [mut s1, s0] = self.0;
+    s1 ^= s1 << 23;
+    s1 ^= s1 >> 17;
+    s1 ^= s0 ^ (s0 >> 26);
+    self.0 = [s0, s1];
+    (s0.wrapping_add(s1) >> 11) as f64 / (1u64 << 53) as f64
+  }
+}
+
+/// Creates a `Math.random` replacement, generating numbers from a seed.
+pub(crate) fn random<'ctx>(guard: &ContextGuard<'ctx>, seed: u64) -> value::Value<'ctx> {
+  let random = Mutex::new(Random::new(seed));
+  let function = value::Function::with_name(
+    guard,
+    "random",
+    Box::new(move |guard, _| {
+      let number = random.lock().expect("generating random number").next();
+      Ok(value::Number::from_double(guard, number).into())
+    }),
+  );
+  function.into()
+}
+
+/// Creates a `Date` replacement, reading the current time from a clock.
+pub(crate) fn date<'ctx>(guard: &ContextGuard<'ctx>, clock: Clock) -> Result<value::Value<'ctx>> {
+  let now = value::Function::with_name(
+    guard,
+    "now",
+    Box::new(move |guard, _| Ok(value::Number::from_double(guard, clock.millis()).into())),
+  );
+
+  let wrapper = script::eval(guard, DATE_WRAPPER)?;
+  let function = wrapper
+    .clone()
+    .into_function()
+    .ok_or_else(|| Error::Conversion(ConversionError::new(JsValueType::Function, &wrapper)))?;
+
+  let native = guard.intrinsic(Intrinsic::Date);
+  function.call(guard, &[&native, &now])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Clock;
+  use crate::{script, test, Context};
+  use std::time::Duration;
+
+  #[test]
+  fn random_seed() {
+    let (runtime, _) = test::setup_env();
+    let sample = |seed| {
+      let context = Context::builder(&runtime)
+        .random_seed(seed)
+        .build()
+        .unwrap();
+      context
+        .exec_with(|guard| {
+          let result = script::eval(guard, "[1, 2, 3].map(() => Math.random())").unwrap();
+          result.to_json(guard).unwrap()
+        })
+        .unwrap()
+    };
+
+    assert_eq!(sample(10), sample(10));
+    assert_ne!(sample(10), sample(11));
+
+    let context = Context::builder(&runtime).random_seed(10).build().unwrap();
+    context
+      .exec_with(|guard| {
+        let code = "Array.from({ length: 1000 }, Math.random).every(n => n >= 0 && n < 1)";
+        assert!(script::eval(guard, code).unwrap().to_bool(guard));
+      })
+      .unwrap();
+  }
+
+  #[test]
+  fn clock() {
+    let (runtime, _) = test::setup_env();
+    let clock = Clock::new(Duration::from_secs(1000));
+    let context = Context::builder(&runtime)
+      .clock(clock.clone())
+      .build()
+      .unwrap();
+
+    context
+      .exec_with(|guard| {
+        let now = || script::eval(guard, "Date.now()").unwrap().to_double(guard);
+        assert_eq!(now(), 1_000_000.0);
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(now(), 1_000_250.0);
+
+        let result = script::eval(guard, "new Date().getTime()").unwrap();
+        assert_eq!(result.to_double(guard), 1_000_250.0);
+
+        let result = script::eval(guard, "Date() === new Date(Date.now()).toString()").unwrap();
+        assert!(result.to_bool(guard));
+
+        let result = script::eval(
+          guard,
+          "new Date(0).getTime() === 0
+            && new Date() instanceof Date
+            && new Date().constructor === Date
+            && Date.UTC(1970, 0, 2) === 86400000",
+        )
+        .unwrap();
+        assert!(result.to_bool(guard));
+
+        clock.set(Duration::from_secs(0));
+        assert_eq!(now(), 0.0);
+      })
+      .unwrap();
+  }
+}
@@ -1,3 +1,4 @@
+use crate::convert::ConversionError;
 use chakracore_sys::JsErrorCode;
 use std::error::Error as StdError;
 use std::fmt;
@@ -14,6 +15,20 @@ pub enum Error {
   ScriptCompilation(String),
   /// A JSRT call failed.
   JsrtCall(JsErrorCode),
+  /// An index was beyond the length of an array.
+  IndexOutOfBounds { index: usize, length: usize },
+  /// A value was used with a context of another runtime.
+  RuntimeMismatch,
+  /// A value could not be cloned (e.g a function).
+  DataClone(String),
+  /// A property path could not be resolved.
+  PathNotFound(String),
+  /// A property path could not be removed, since it's not configurable.
+  NotConfigurable(String),
+  /// A value returned by script code was not of the expected type.
+  Conversion(ConversionError),
+  /// An I/O operation failed.
+  Io(::std::io::Error),
 }
 
 impl StdError for Error {}
@@ -24,6 +39,15 @@ impl fmt::Display for Error {
       Error::ScriptException(message) => write!(f, "JavaScript exception: {}", message),
       Error::ScriptCompilation(message) => write!(f, "JavaScript compile error: {}", message),
       Error::JsrtCall(error) => write!(f, "JSRT call error: {:?}", error),
+      Error::IndexOutOfBounds { index, length } => {
+        write!(f, "Index {} is out of bounds for length {}", index, length)
+      },
+      Error::RuntimeMismatch => write!(f, "Value belongs to another runtime"),
+      Error::DataClone(message) => write!(f, "Data clone error: {}", message),
+      Error::PathNotFound(path) => write!(f, "Property path '{}' could not be resolved", path),
+      Error::NotConfigurable(path) => write!(f, "Property path '{}' is not configurable", path),
+      Error::Conversion(error) => write!(f, "Conversion error: {}", error),
+      Error::Io(error) => write!(f, "I/O error: {}", error),
     }
   }
 }
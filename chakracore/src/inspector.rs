@@ -0,0 +1,1142 @@
+//! A Chrome DevTools Protocol server, for attaching debuggers to a runtime.
+//!
+//! The inspector serves the protocol on a localhost WebSocket, which Chrome
+//! DevTools and VS Code can attach to. It supports the `Debugger` domain
+//! (breakpoints, stepping and evaluation while paused), `Runtime.evaluate`,
+//! `Runtime.getProperties`, and reports calls to `console` as events.
+//!
+//! Since any client can execute code, the WebSocket is served under a random
+//! path, and requests must name the host by address or as `localhost`. This
+//! prevents web pages from attaching through DNS rebinding.
+//!
+//! The script thread handles all requests. While a script is paused, this is
+//! done by the debugger. Otherwise, `poll` must be called at a regular
+//! interval, like `execute_tasks`.
+//!
+//! ```rust,no_run
+//! # use chakracore as js;
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! let inspector = js::inspector::Inspector::start(guard, 9229).unwrap();
+//! println!("Debugger listening on {}", inspector.url());
+//!
+//! loop {
+//!   inspector.poll(guard);
+//!   guard.execute_tasks();
+//! # break;
+//! }
+//! # }).unwrap();
+//! ```
+use crate::debug::{
+  self, AsyncBreak, BreakOnException, Debugger, Event, Location, Script, Step, Variable,
+};
+use crate::{script, value, ContextGuard, Error, Property, Result};
+use boolinator::Boolinator;
+use chakracore_sys::JsValueType;
+use serde_json::{json, Value as Json};
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+use uuid::Uuid;
+
+/// The identifier of the execution context reported to clients.
+const CONTEXT_ID: u32 = 1;
+
+/// The interval at which the server checks for messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The time a client has to send each part of its request, before it's
+/// disconnected, so an idle connection cannot block the server.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A Chrome DevTools Protocol server for the runtime of a context.
+///
+/// The server is stopped once the inspector is dropped.
+pub struct Inspector {
+  address: SocketAddr,
+  id: String,
+  session: Arc<Session>,
+  debugger: Debugger,
+}
+
+impl Inspector {
+  /// Starts serving the protocol on a localhost port, for the runtime of the
+  /// active context. Use port `0` for any available port.
+  ///
+  /// A `console` object reporting to the client is installed in the active
+  /// context.
+  pub fn start(guard: &ContextGuard, port: u16) -> Result<Inspector> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(Error::Io)?;
+    let address = listener.local_addr().map_err(Error::Io)?;
+    listener.set_nonblocking(true).map_err(Error::Io)?;
+
+    let (incoming_sender, incoming) = mpsc::channel();
+    let (outgoing, outgoing_receiver) = mpsc::channel();
+    let session = Arc::new(Session {
+      incoming: Mutex::new(incoming),
+      outgoing: Mutex::new(outgoing),
+      breakpoints: Mutex::new(Vec::new()),
+      break_on_exception: Mutex::new(None),
+      connected: Arc::new(AtomicBool::new(false)),
+      paused: Arc::new(AtomicBool::new(false)),
+      shutdown: Arc::new(AtomicBool::new(false)),
+    });
+
+    let debugger = {
+      let session = session.clone();
+      Debugger::start(
+        guard,
+        Box::new(move |guard, event| session.on_event(guard, event)),
+      )?
+    };
+
+    let id = Uuid::new_v4().to_string();
+    let server = Server {
+      listener,
+      address,
+      id: id.clone(),
+      async_break: debugger.async_break(),
+      incoming: incoming_sender,
+      outgoing: outgoing_receiver,
+      connected: session.connected.clone(),
+      paused: session.paused.clone(),
+      shutdown: session.shutdown.clone(),
+    };
+    thread::spawn(move || server.run());
+
+    define_console(guard, &session);
+    Ok(Inspector {
+      address,
+      id,
+      session,
+      debugger,
+    })
+  }
+
+  /// Returns the address the server is listening on.
+  pub fn address(&self) -> SocketAddr {
+    self.address
+  }
+
+  /// Returns the WebSocket URL clients connect to. Its path is random, and
+  /// is only disclosed to local clients.
+  pub fn url(&self) -> String {
+    format!("ws://{}/{}", self.address, self.id)
+  }
+
+  /// Returns whether a client is connected.
+  pub fn is_connected(&self) -> bool {
+    self.session.connected.load(Ordering::SeqCst)
+  }
+
+  /// Handles all pending requests of the client.
+  pub fn poll(&self, guard: &ContextGuard) {
+    if let Some(mode) = self.session.break_on_exception.lock().unwrap().take() {
+      let _ = self.debugger.set_break_on_exception(mode);
+    }
+
+    loop {
+      let message = self.session.incoming.lock().unwrap().try_recv();
+      let message = match message {
+        Ok(message) => message,
+        Err(_) => break,
+      };
+
+      match self.session.handle(guard, &message, false) {
+        Outcome::BreakOnException(mode) => {
+          let _ = self.debugger.set_break_on_exception(mode);
+        },
+        Outcome::Resume(_) | Outcome::None => (),
+      }
+    }
+  }
+}
+
+impl Drop for Inspector {
+  /// Stops the server.
+  fn drop(&mut self) {
+    self.session.shutdown.store(true, Ordering::SeqCst);
+  }
+}
+
+/// An action requested by the client, which the handler can not perform.
+enum Outcome {
+  None,
+  Resume(Step),
+  BreakOnException(BreakOnException),
+}
+
+/// A breakpoint set by URL, applied to every script with a matching name.
+struct UrlBreakpoint {
+  id: String,
+  url: String,
+  line: u32,
+  column: u32,
+  resolved: Vec<u32>,
+}
+
+/// The state shared between the inspector, its debugger and `console`.
+struct Session {
+  incoming: Mutex<Receiver<String>>,
+  outgoing: Mutex<Sender<String>>,
+  breakpoints: Mutex<Vec<UrlBreakpoint>>,
+  break_on_exception: Mutex<Option<BreakOnException>>,
+  connected: Arc<AtomicBool>,
+  paused: Arc<AtomicBool>,
+  shutdown: Arc<AtomicBool>,
+}
+
+impl Session {
+  /// Handles a debug event, pausing until the client resumes the script.
+  fn on_event(&self, guard: &ContextGuard, event: Event) -> Step {
+    match event {
+      Event::SourceCompile(script) => {
+        self.notify("Debugger.scriptParsed", script_json(&script));
+        self.resolve_breakpoints(guard, &script);
+        Step::Continue
+      },
+      Event::CompileError { script, .. } => {
+        self.notify("Debugger.scriptFailedToParse", script_json(&script));
+        Step::Continue
+      },
+      Event::Breakpoint { id, .. } => {
+        let id = self.breakpoint_id(id);
+        self.pause(guard, "other", None, vec![id])
+      },
+      Event::StepComplete(_) | Event::DebuggerStatement(_) | Event::AsyncBreak(_) => {
+        self.pause(guard, "other", None, Vec::new())
+      },
+      Event::Exception { exception, .. } => {
+        let data = variable_json(&exception);
+        self.pause(guard, "exception", Some(data), Vec::new())
+      },
+    }
+  }
+
+  /// Reports a paused script, and handles requests until it is resumed.
+  fn pause(
+    &self,
+    guard: &ContextGuard,
+    reason: &str,
+    data: Option<Json>,
+    hit: Vec<String>,
+  ) -> Step {
+    // Scripts are only paused while a client is attached
+    if !self.connected.load(Ordering::SeqCst) {
+      return Step::Continue;
+    }
+
+    let mut params = json!({
+      "callFrames": call_frames(guard),
+      "reason": reason,
+      "hitBreakpoints": hit,
+    });
+    if let Some(data) = data {
+      params["data"] = data;
+    }
+    self.notify("Debugger.paused", params);
+
+    self.paused.store(true, Ordering::SeqCst);
+    let step = loop {
+      let message = self.incoming.lock().unwrap().recv_timeout(POLL_INTERVAL);
+      match message {
+        Ok(message) => match self.handle(guard, &message, true) {
+          Outcome::Resume(step) => {
+            self.notify("Debugger.resumed", json!({}));
+            break step;
+          },
+          Outcome::BreakOnException(mode) => {
+            *self.break_on_exception.lock().unwrap() = Some(mode);
+          },
+          Outcome::None => (),
+        },
+        Err(RecvTimeoutError::Timeout) if self.connected.load(Ordering::SeqCst) => (),
+        Err(_) => break Step::Continue,
+      }
+    };
+    self.paused.store(false, Ordering::SeqCst);
+    step
+  }
+
+  /// Handles a request of the client, and sends the response.
+  fn handle(&self, guard: &ContextGuard, message: &str, paused: bool) -> Outcome {
+    let request: Json = match serde_json::from_str(message) {
+      Ok(request) => request,
+      Err(_) => return Outcome::None,
+    };
+
+    let params = &request["params"];
+    let mut outcome = Outcome::None;
+    let result = match request["method"].as_str().unwrap_or_default() {
+      "Runtime.enable" => {
+        let context = json!({ "id": CONTEXT_ID, "origin": "", "name": "chakracore" });
+        self.notify(
+          "Runtime.executionContextCreated",
+          json!({ "context": context }),
+        );
+        Ok(json!({}))
+      },
+      "Runtime.evaluate" => self.evaluate(guard, params, paused),
+      "Runtime.getProperties" => get_properties(guard, params),
+      "Debugger.enable" => {
+        for script in debug::scripts(guard).unwrap_or_default() {
+          self.notify("Debugger.scriptParsed", script_json(&script));
+        }
+        Ok(json!({ "debuggerId": "chakracore" }))
+      },
+      "Debugger.getScriptSource" => integer(&params["scriptId"]).and_then(|id| {
+        let source = debug::source(guard, id).map_err(|error| error.to_string())?;
+        Ok(json!({ "scriptSource": source }))
+      }),
+      "Debugger.setBreakpoint" => set_breakpoint(guard, &params["location"]),
+      "Debugger.setBreakpointByUrl" => self.set_breakpoint_by_url(guard, params),
+      "Debugger.removeBreakpoint" => self.remove_breakpoint(guard, params),
+      "Debugger.evaluateOnCallFrame" => integer(&params["callFrameId"]).and_then(|frame| {
+        evaluate_on_frame(
+          guard,
+          params["expression"].as_str().unwrap_or_default(),
+          frame,
+        )
+      }),
+      "Debugger.setPauseOnExceptions" => {
+        let mode = match params["state"].as_str() {
+          Some("uncaught") => BreakOnException::Uncaught,
+          Some("all") => BreakOnException::FirstChance,
+          _ => BreakOnException::Never,
+        };
+        outcome = Outcome::BreakOnException(mode);
+        Ok(json!({}))
+      },
+      // The break is requested by the server thread, since a running script
+      // does not poll for requests
+      "Debugger.pause" => Ok(json!({})),
+      method @ "Debugger.resume"
+      | method @ "Debugger.stepOver"
+      | method @ "Debugger.stepInto"
+      | method @ "Debugger.stepOut"
+        if paused =>
+      {
+        outcome = Outcome::Resume(match method {
+          "Debugger.stepOver" => Step::StepOver,
+          "Debugger.stepInto" => Step::StepIn,
+          "Debugger.stepOut" => Step::StepOut,
+          _ => Step::Continue,
+        });
+        Ok(json!({}))
+      },
+      // Unsupported domains are acknowledged, since clients enable many
+      _ => Ok(json!({})),
+    };
+
+    let response = match result {
+      Ok(result) => json!({ "id": request["id"], "result": result }),
+      Err(message) => json!({
+        "id": request["id"],
+        "error": { "code": -32000, "message": message },
+      }),
+    };
+    self.send(response);
+    outcome
+  }
+
+  /// Evaluates an expression globally, or in the top frame if paused.
+  fn evaluate(
+    &self,
+    guard: &ContextGuard,
+    params: &Json,
+    paused: bool,
+  ) -> ::std::result::Result<Json, String> {
+    let expression = params["expression"].as_str().unwrap_or_default();
+    if paused {
+      return evaluate_on_frame(guard, expression, 0);
+    }
+
+    Ok(match script::eval(guard, expression) {
+      Ok(result) => json!({ "result": value_json(guard, &result) }),
+      Err(Error::ScriptException(message)) | Err(Error::ScriptCompilation(message)) => {
+        exception_json(&message)
+      },
+      Err(error) => return Err(error.to_string()),
+    })
+  }
+
+  /// Sets a breakpoint in all current, and future, scripts with a URL.
+  fn set_breakpoint_by_url(
+    &self,
+    guard: &ContextGuard,
+    params: &Json,
+  ) -> ::std::result::Result<Json, String> {
+    let url = params["url"].as_str().ok_or("Missing 'url'")?.to_string();
+    let line = integer(&params["lineNumber"])?;
+    let column = integer(&params["columnNumber"]).unwrap_or(0);
+
+    let mut breakpoint = UrlBreakpoint {
+      id: format!("{}:{}:{}", line, column, url),
+      url,
+      line,
+      column,
+      resolved: Vec::new(),
+    };
+
+    let mut locations = Vec::new();
+    for script in debug::scripts(guard).unwrap_or_default() {
+      if matches_url(&script, &breakpoint.url) {
+        if let Ok(resolved) = debug::set_breakpoint(guard, script.id, line, column) {
+          breakpoint.resolved.push(resolved.id);
+          locations.push(location_json(&resolved.location));
+        }
+      }
+    }
+
+    let result = json!({ "breakpointId": breakpoint.id, "locations": locations });
+    self.breakpoints.lock().unwrap().push(breakpoint);
+    Ok(result)
+  }
+
+  /// Removes a breakpoint, set either by URL or location.
+  fn remove_breakpoint(
+    &self,
+    guard: &ContextGuard,
+    params: &Json,
+  ) -> ::std::result::Result<Json, String> {
+    let id = params["breakpointId"]
+      .as_str()
+      .ok_or("Missing 'breakpointId'")?;
+
+    let mut breakpoints = self.breakpoints.lock().unwrap();
+    let ids = match breakpoints
+      .iter()
+      .position(|breakpoint| breakpoint.id == id)
+    {
+      Some(index) => breakpoints.remove(index).resolved,
+      None => vec![integer(&params["breakpointId"])?],
+    };
+
+    for id in ids {
+      debug::remove_breakpoint(guard, id).map_err(|error| error.to_string())?;
+    }
+    Ok(json!({}))
+  }
+
+  /// Applies any breakpoints set by URL to a newly compiled script.
+  fn resolve_breakpoints(&self, guard: &ContextGuard, script: &Script) {
+    let mut breakpoints = self.breakpoints.lock().unwrap();
+    for breakpoint in breakpoints
+      .iter_mut()
+      .filter(|breakpoint| matches_url(script, &breakpoint.url))
+    {
+      if let Ok(resolved) =
+        debug::set_breakpoint(guard, script.id, breakpoint.line, breakpoint.column)
+      {
+        breakpoint.resolved.push(resolved.id);
+        self.notify(
+          "Debugger.breakpointResolved",
+          json!({
+            "breakpointId": breakpoint.id,
+            "location": location_json(&resolved.location),
+          }),
+        );
+      }
+    }
+  }
+
+  /// Returns the protocol identifier of a breakpoint.
+  fn breakpoint_id(&self, id: u32) -> String {
+    self
+      .breakpoints
+      .lock()
+      .unwrap()
+      .iter()
+      .find(|breakpoint| breakpoint.resolved.contains(&id))
+      .map_or_else(|| id.to_string(), |breakpoint| breakpoint.id.clone())
+  }
+
+  /// Sends an event to the client.
+  fn notify(&self, method: &str, params: Json) {
+    self.send(json!({ "method": method, "params": params }));
+  }
+
+  /// Sends a message to the client.
+  fn send(&self, message: Json) {
+    // Messages are discarded if the server has stopped
+    let _ = self.outgoing.lock().unwrap().send(message.to_string());
+  }
+}
+
+/// The server thread, relaying messages between a client and the session.
+struct Server {
+  listener: TcpListener,
+  address: SocketAddr,
+  id: String,
+  async_break: Option<AsyncBreak>,
+  incoming: Sender<String>,
+  outgoing: Receiver<String>,
+  connected: Arc<AtomicBool>,
+  paused: Arc<AtomicBool>,
+  shutdown: Arc<AtomicBool>,
+}
+
+impl Server {
+  /// Accepts clients, one at a time, until the inspector is dropped.
+  fn run(self) {
+    while !self.shutdown.load(Ordering::SeqCst) {
+      let stream = match self.listener.accept() {
+        Ok((stream, _)) => stream,
+        Err(_) => {
+          thread::sleep(POLL_INTERVAL);
+          continue;
+        },
+      };
+
+      let blocking = stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.set_read_timeout(Some(REQUEST_TIMEOUT)))
+        .and_then(|_| stream.set_write_timeout(Some(REQUEST_TIMEOUT)));
+      if blocking.is_err() || self.discover(&stream) {
+        continue;
+      }
+
+      let authorize = |request: &Request, response: Response| {
+        let header = |name| {
+          request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+        };
+        let path = request.uri().path().trim_start_matches('/');
+
+        if path == self.id && is_trusted(header("host"), header("origin")) {
+          Ok(response)
+        } else {
+          let mut error = ErrorResponse::new(None);
+          *error.status_mut() = StatusCode::FORBIDDEN;
+          Err(error)
+        }
+      };
+
+      if let Ok(socket) = tungstenite::accept_hdr(stream, authorize) {
+        // Any messages sent before the client connected are stale
+        while self.outgoing.try_recv().is_ok() {}
+
+        self.connected.store(true, Ordering::SeqCst);
+        self.relay(socket);
+        self.connected.store(false, Ordering::SeqCst);
+      }
+    }
+  }
+
+  /// Relays messages until the client disconnects.
+  fn relay(&self, mut socket: tungstenite::WebSocket<TcpStream>) {
+    if socket
+      .get_ref()
+      .set_read_timeout(Some(POLL_INTERVAL))
+      .is_err()
+    {
+      return;
+    }
+
+    while !self.shutdown.load(Ordering::SeqCst) {
+      while let Ok(message) = self.outgoing.try_recv() {
+        if socket.send(Message::Text(message)).is_err() {
+          return;
+        }
+      }
+
+      match socket.read() {
+        Ok(Message::Text(message)) => {
+          if is_pause(&message) && !self.paused.load(Ordering::SeqCst) {
+            if let Some(async_break) = &self.async_break {
+              let _ = async_break.request();
+            }
+          }
+
+          if self.incoming.send(message).is_err() {
+            return;
+          }
+        },
+        Ok(Message::Close(_)) => return,
+        Ok(_) => (),
+        Err(tungstenite::Error::Io(ref error))
+          if error.kind() == io::ErrorKind::WouldBlock
+            || error.kind() == io::ErrorKind::TimedOut => {},
+        Err(_) => return,
+      }
+    }
+  }
+
+  /// Responds to HTTP discovery requests, used by clients to find the
+  /// WebSocket URL. Returns whether the request was handled, which includes
+  /// a client that sends nothing before the timeout.
+  fn discover(&self, mut stream: &TcpStream) -> bool {
+    let mut buffer = [0; 1024];
+    let size = match stream.peek(&mut buffer) {
+      Ok(size) => size,
+      Err(_) => return true,
+    };
+
+    let request = String::from_utf8_lossy(&buffer[..size]);
+    if request.to_ascii_lowercase().contains("upgrade: websocket") {
+      return false;
+    }
+
+    let header = |name: &str| {
+      request.lines().skip(1).find_map(|line| {
+        let mut parts = line.splitn(2, ':');
+        let key = parts.next()?;
+        let value = parts.next()?;
+        key.trim().eq_ignore_ascii_case(name).as_some(value.trim())
+      })
+    };
+
+    if !is_trusted(header("host"), header("origin")) {
+      let _ = stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+      return true;
+    }
+
+    let address = format!("{}/{}", self.address, self.id);
+    let body = match request.split_whitespace().nth(1) {
+      Some("/json") | Some("/json/list") => json!([{
+        "description": "chakracore",
+        "id": self.id,
+        "title": "chakracore",
+        "type": "node",
+        "url": "file://",
+        "webSocketDebuggerUrl": format!("ws://{}", address),
+        "devtoolsFrontendUrl": format!(
+          "devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={}",
+          address
+        ),
+      }]),
+      Some("/json/version") => json!({ "Browser": "chakracore", "Protocol-Version": "1.3" }),
+      _ => return false,
+    };
+
+    let body = body.to_string();
+    let response = format!(
+      concat!(
+        "HTTP/1.1 200 OK\r\n",
+        "Content-Type: application/json; charset=UTF-8\r\n",
+        "Content-Length: {}\r\n\r\n{}",
+      ),
+      body.len(),
+      body
+    );
+    let _ = stream.write_all(response.as_bytes());
+    true
+  }
+}
+
+/// Returns whether a request may access the server, by its `Host` and
+/// `Origin` headers.
+///
+/// The host must be an address or `localhost`, since a DNS rebinding attack
+/// relies on a domain name resolving to the local machine. Browsers send an
+/// origin, which must be DevTools itself or a page served by the local
+/// machine, through a loopback address or `localhost`.
+fn is_trusted(host: Option<&str>, origin: Option<&str>) -> bool {
+  /// Returns the name of a host, without its port.
+  fn name(host: &str) -> &str {
+    if host.starts_with('[') {
+      host[1..].split(']').next().unwrap_or_default()
+    } else {
+      host.split(':').next().unwrap_or_default()
+    }
+  }
+
+  let is_localhost = |name: &str| name.eq_ignore_ascii_case("localhost");
+  let origin = origin.map_or(true, |origin| {
+    let mut parts = origin.splitn(2, "://");
+    match (parts.next(), parts.next()) {
+      (Some("devtools"), _) | (Some("chrome-devtools"), _) => true,
+      (Some("http"), Some(host)) | (Some("https"), Some(host)) => {
+        let name = name(host);
+        is_localhost(name) || name.parse().map_or(false, |ip: IpAddr| ip.is_loopback())
+      },
+      _ => false,
+    }
+  });
+
+  let host = host.map(name);
+  host.map_or(false, |name| {
+    is_localhost(name) || name.parse::<IpAddr>().is_ok()
+  }) && origin
+}
+
+/// Returns whether a message is a `Debugger.pause` request.
+fn is_pause(message: &str) -> bool {
+  serde_json::from_str::<Json>(message)
+    .map(|request| request["method"] == "Debugger.pause")
+    .unwrap_or(false)
+}
+
+/// Installs a `console` object, reporting its calls to the client.
+fn define_console(guard: &ContextGuard, session: &Arc<Session>) {
+  let console = value::Object::new(guard);
+  let methods = [
+    ("log", "log"),
+    ("info", "info"),
+    ("warn", "warning"),
+    ("error", "error"),
+    ("debug", "debug"),
+  ];
+
+  for &(name, kind) in methods.iter() {
+    let session = session.clone();
+    let function = value::Function::with_name(
+      guard,
+      name,
+      Box::new(move |guard, info| {
+        let args = info
+          .arguments
+          .iter()
+          .map(|argument| value_json(guard, argument))
+          .collect::<Vec<_>>();
+        let text = info
+          .arguments
+          .iter()
+          .map(|argument| argument.to_string(guard))
+          .collect::<Vec<_>>()
+          .join(" ");
+
+        session.notify(
+          "Runtime.consoleAPICalled",
+          json!({
+            "type": kind,
+            "args": args,
+            "executionContextId": CONTEXT_ID,
+            "timestamp": timestamp(),
+          }),
+        );
+        session.notify(
+          "Console.messageAdded",
+          json!({ "message": { "source": "console-api", "level": kind, "text": text } }),
+        );
+        Ok(value::undefined(guard))
+      }),
+    );
+    console.set(guard, Property::new(guard, name), function);
+  }
+
+  guard
+    .global()
+    .set(guard, Property::new(guard, "console"), console);
+}
+
+/// Evaluates an expression within a stack frame of the paused script.
+fn evaluate_on_frame(
+  guard: &ContextGuard,
+  expression: &str,
+  frame: u32,
+) -> ::std::result::Result<Json, String> {
+  Ok(match debug::evaluate(guard, expression, frame) {
+    Ok(result) => json!({ "result": variable_json(&result) }),
+    Err(Error::ScriptException(message)) => exception_json(&message),
+    Err(error) => return Err(error.to_string()),
+  })
+}
+
+/// Sets a breakpoint at a location.
+fn set_breakpoint(guard: &ContextGuard, location: &Json) -> ::std::result::Result<Json, String> {
+  let script_id = integer(&location["scriptId"])?;
+  let line = integer(&location["lineNumber"])?;
+  let column = integer(&location["columnNumber"]).unwrap_or(0);
+
+  let breakpoint =
+    debug::set_breakpoint(guard, script_id, line, column).map_err(|error| error.to_string())?;
+  Ok(json!({
+    "breakpointId": breakpoint.id.to_string(),
+    "actualLocation": location_json(&breakpoint.location),
+  }))
+}
+
+/// Returns the properties of an object, or the local variables of a frame.
+fn get_properties(guard: &ContextGuard, params: &Json) -> ::std::result::Result<Json, String> {
+  let id = params["objectId"].as_str().ok_or("Missing 'objectId'")?;
+  let properties = match id.trim_start_matches("locals:").parse::<u32>() {
+    Ok(frame) if id.starts_with("locals:") => {
+      debug::stack_properties(guard, frame).map(|properties| properties.locals)
+    },
+    Ok(handle) => debug::properties(guard, handle),
+    Err(_) => return Err(format!("Invalid object identifier '{}'", id)),
+  }
+  .map_err(|error| error.to_string())?;
+
+  let result = properties
+    .iter()
+    .map(|property| {
+      json!({
+        "name": property.name,
+        "value": variable_json(property),
+        "configurable": true,
+        "enumerable": true,
+        "isOwn": true,
+      })
+    })
+    .collect::<Vec<_>>();
+  Ok(json!({ "result": result }))
+}
+
+/// Returns the call frames of the paused script.
+fn call_frames(guard: &ContextGuard) -> Vec<Json> {
+  let scripts = debug::scripts(guard).unwrap_or_default();
+  let frames = debug::stack_trace(guard).unwrap_or_default();
+
+  frames
+    .iter()
+    .map(|frame| {
+      let properties = debug::stack_properties(guard, frame.index).unwrap_or_default();
+      let scope = |kind: &str, id: String| {
+        json!({ "type": kind, "object": { "type": "object", "objectId": id } })
+      };
+
+      let mut scope_chain = vec![scope("local", format!("locals:{}", frame.index))];
+      let closures = properties.scopes.iter();
+      scope_chain.extend(closures.map(|handle| scope("closure", handle.to_string())));
+      scope_chain.extend(properties.globals.map(|handle| scope("global", handle.to_string())));
+
+      let this = properties
+        .this
+        .as_ref()
+        .map_or_else(|| json!({ "type": "undefined" }), variable_json);
+
+      let url = scripts
+        .iter()
+        .find(|script| script.id == frame.location.script_id)
+        .and_then(|script| script.file_name.clone())
+        .unwrap_or_default();
+
+      json!({
+        "callFrameId": frame.index.to_string(),
+        "functionName": "",
+        "location": location_json(&frame.location),
+        "url": url,
+        "scopeChain": scope_chain,
+        "this": this,
+      })
+    })
+    .collect()
+}
+
+/// Returns whether a breakpoint URL refers to a script.
+fn matches_url(script: &Script, url: &str) -> bool {
+  match script.file_name {
+    Some(ref name) if !name.is_empty() => url == name || url.trim_start_matches("file://") == name,
+    _ => false,
+  }
+}
+
+/// Parses an integer, which the protocol may represent as a string.
+fn integer(value: &Json) -> ::std::result::Result<u32, String> {
+  value
+    .as_u64()
+    .or_else(|| value.as_str().and_then(|value| value.parse().ok()))
+    .map(|value| value as u32)
+    .ok_or_else(|| format!("Invalid integer '{}'", value))
+}
+
+/// Returns the milliseconds since the Unix epoch.
+fn timestamp() -> f64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|time| time.as_millis() as f64)
+    .unwrap_or(0.0)
+}
+
+/// Represents a script as a `Debugger.scriptParsed` event.
+fn script_json(script: &Script) -> Json {
+  json!({
+    "scriptId": script.id.to_string(),
+    "url": script.file_name.clone().unwrap_or_default(),
+    "startLine": 0,
+    "startColumn": 0,
+    "endLine": script.line_count,
+    "endColumn": 0,
+    "executionContextId": CONTEXT_ID,
+    "hash": "",
+  })
+}
+
+/// Represents a location as a protocol `Location`.
+fn location_json(location: &Location) -> Json {
+  json!({
+    "scriptId": location.script_id.to_string(),
+    "lineNumber": location.line,
+    "columnNumber": location.column,
+  })
+}
+
+/// Represents an exception as a protocol evaluation result.
+fn exception_json(message: &str) -> Json {
+  json!({
+    "result": { "type": "object", "subtype": "error", "description": message },
+    "exceptionDetails": {
+      "exceptionId": 1,
+      "text": message,
+      "lineNumber": 0,
+      "columnNumber": 0,
+    },
+  })
+}
+
+/// Represents a debugger variable as a protocol `RemoteObject`.
+fn variable_json(variable: &Variable) -> Json {
+  let display = variable.display.clone().unwrap_or_default();
+  match variable.kind.as_str() {
+    "undefined" => json!({ "type": "undefined" }),
+    "null" => json!({ "type": "object", "subtype": "null", "value": null }),
+    "number" => {
+      let number = display.parse::<f64>().ok();
+      json!({ "type": "number", "value": number, "description": display })
+    },
+    "boolean" => json!({ "type": "boolean", "value": display == "true" }),
+    "string" => json!({ "type": "string", "value": display }),
+    kind => json!({
+      "type": if kind == "function" { "function" } else { "object" },
+      "className": variable.class_name,
+      "description": display,
+      "objectId": variable.handle.to_string(),
+    }),
+  }
+}
+
+/// Represents a value as a protocol `RemoteObject`.
+fn value_json<'ctx>(guard: &ContextGuard<'ctx>, value: &value::Value<'ctx>) -> Json {
+  let description = value.to_string(guard);
+  match value.get_type() {
+    JsValueType::Undefined => json!({ "type": "undefined" }),
+    JsValueType::Null => json!({ "type": "object", "subtype": "null", "value": null }),
+    JsValueType::Number => {
+      json!({ "type": "number", "value": value.to_double(guard), "description": description })
+    },
+    JsValueType::String => json!({ "type": "string", "value": description }),
+    JsValueType::Boolean => json!({ "type": "boolean", "value": value.to_bool(guard) }),
+    JsValueType::Function => json!({ "type": "function", "description": description }),
+    _ => {
+      // Objects are described by their JSON representation, if available
+      let description = value
+        .to_json(guard)
+        .ok()
+        .filter(|json| json != "undefined")
+        .unwrap_or(description);
+      json!({ "type": "object", "description": description })
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Inspector;
+  use crate::{script, test};
+  use serde_json::{json, Value as Json};
+  use std::io::{Read, Write};
+  use std::net::TcpStream;
+  use std::sync::mpsc;
+  use std::thread;
+  use std::time::Duration;
+  use tungstenite::client::IntoClientRequest;
+  use tungstenite::http::HeaderValue;
+  use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+  /// A minimal protocol client, standing in for a debugger frontend.
+  struct Client(WebSocket<MaybeTlsStream<TcpStream>>);
+
+  impl Client {
+    fn connect(url: &str) -> Self {
+      Client(tungstenite::connect(url).unwrap().0)
+    }
+
+    fn send(&mut self, id: u32, method: &str, params: Json) {
+      let request = json!({ "id": id, "method": method, "params": params });
+      self.0.send(Message::Text(request.to_string())).unwrap();
+    }
+
+    /// Returns the next message matching a predicate, skipping any others.
+    fn receive<F: Fn(&Json) -> bool>(&mut self, predicate: F) -> Json {
+      loop {
+        if let Message::Text(text) = self.0.read().unwrap() {
+          let message = serde_json::from_str(&text).unwrap();
+          if predicate(&message) {
+            return message;
+          }
+        }
+      }
+    }
+
+    fn response(&mut self, id: u32) -> Json {
+      self.receive(|message| message["id"] == id)["result"].clone()
+    }
+
+    fn event(&mut self, method: &str) -> Json {
+      self.receive(|message| message["method"] == method)["params"].clone()
+    }
+  }
+
+  #[test]
+  fn evaluate_and_console() {
+    test::run_with_context(|guard| {
+      let inspector = Inspector::start(guard, 0).unwrap();
+      let (sender, receiver) = mpsc::channel();
+      let mut client = Client::connect(&inspector.url());
+
+      let thread = thread::spawn(move || {
+        let expression = "console.log('answer', 42); 6 * 7";
+        client.send(1, "Runtime.evaluate", json!({ "expression": expression }));
+        let event = client.event("Runtime.consoleAPICalled");
+        let result = client.response(1);
+
+        client.send(2, "Runtime.evaluate", json!({ "expression": "missing" }));
+        let error = client.response(2);
+        sender.send((event, result, error)).unwrap();
+      });
+
+      let (event, result, error) = loop {
+        inspector.poll(guard);
+        if let Ok(result) = receiver.recv_timeout(Duration::from_millis(10)) {
+          break result;
+        }
+      };
+      thread.join().unwrap();
+
+      assert_eq!(event["type"], "log");
+      assert_eq!(event["args"][0]["value"], "answer");
+      assert_eq!(event["args"][1]["value"].as_f64(), Some(42.0));
+      assert_eq!(result["result"]["value"].as_f64(), Some(42.0));
+      assert!(error["exceptionDetails"]["text"].is_string());
+    });
+  }
+
+  #[test]
+  fn pause_and_resume() {
+    test::run_with_context(|guard| {
+      let inspector = Inspector::start(guard, 0).unwrap();
+      let (sender, receiver) = mpsc::channel();
+      let mut client = Client::connect(&inspector.url());
+
+      let thread = thread::spawn(move || {
+        client.send(1, "Debugger.enable", json!({}));
+        client.response(1);
+        sender.send(()).unwrap();
+
+        let paused = client.event("Debugger.paused");
+        let frame = paused["callFrames"][0]["callFrameId"].clone();
+        let params = json!({ "callFrameId": frame, "expression": "value * 2" });
+        client.send(2, "Debugger.evaluateOnCallFrame", params);
+        let result = client.response(2);
+
+        client.send(3, "Debugger.resume", json!({}));
+        client.event("Debugger.resumed");
+        (paused, result)
+      });
+
+      // Wait until the client is attached and has enabled the debugger
+      while receiver.recv_timeout(Duration::from_millis(10)).is_err() {
+        inspector.poll(guard);
+      }
+
+      let result = script::eval(guard, "var value = 21;\ndebugger;\nvalue;").unwrap();
+      assert_eq!(result.to_integer(guard), 21);
+
+      let (paused, result) = thread.join().unwrap();
+      assert_eq!(paused["reason"], "other");
+      assert_eq!(paused["callFrames"][0]["location"]["lineNumber"], 1);
+      assert_eq!(result["result"]["value"].as_f64(), Some(42.0));
+    });
+  }
+
+  #[test]
+  fn pause_running_script() {
+    test::run_with_context(|guard| {
+      let inspector = Inspector::start(guard, 0).unwrap();
+      let (sender, receiver) = mpsc::channel();
+      let mut client = Client::connect(&inspector.url());
+
+      let thread = thread::spawn(move || {
+        client.send(1, "Debugger.enable", json!({}));
+        client.response(1);
+        sender.send(()).unwrap();
+
+        // The script thread is busy, so it never polls for this request
+        thread::sleep(Duration::from_millis(50));
+        client.send(2, "Debugger.pause", json!({}));
+        client.event("Debugger.paused");
+
+        let params = json!({ "callFrameId": "0", "expression": "done = true" });
+        client.send(3, "Debugger.evaluateOnCallFrame", params);
+        client.response(3);
+        client.send(4, "Debugger.resume", json!({}));
+        client.event("Debugger.resumed");
+      });
+
+      while receiver.recv_timeout(Duration::from_millis(10)).is_err() {
+        inspector.poll(guard);
+      }
+
+      let result = script::eval(guard, "var done = false;\nwhile (!done) {}\ndone;").unwrap();
+      assert!(result.to_bool(guard));
+      thread.join().unwrap();
+    });
+  }
+
+  #[test]
+  fn untrusted_clients() {
+    test::run_with_context(|guard| {
+      let inspector = Inspector::start(guard, 0).unwrap();
+      let address = inspector.address();
+
+      // The WebSocket is only served under the inspector's random path
+      assert!(tungstenite::connect(format!("ws://{}", address)).is_err());
+      assert!(tungstenite::connect(format!("ws://{}/chakracore", address)).is_err());
+
+      // A domain name may have been rebound to the local machine
+      let mut request = inspector.url().into_client_request().unwrap();
+      let host = HeaderValue::from_static("attacker.example");
+      request.headers_mut().insert("host", host);
+      assert!(tungstenite::connect(request).is_err());
+
+      let mut request = inspector.url().into_client_request().unwrap();
+      let origin = HeaderValue::from_static("http://attacker.example");
+      request.headers_mut().insert("origin", origin);
+      assert!(tungstenite::connect(request).is_err());
+
+      let discover = |host: &str| {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let request = format!("GET /json HTTP/1.1\r\nHost: {}\r\n\r\n", host);
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+      };
+
+      assert!(discover("attacker.example").starts_with("HTTP/1.1 403"));
+      let response = discover(&address.to_string());
+      assert!(response.contains(&inspector.url()));
+
+      // An idle connection is dropped, instead of blocking other clients
+      let _idle = TcpStream::connect(address).unwrap();
+      assert!(tungstenite::connect(inspector.url()).is_ok());
+    });
+  }
+
+  #[test]
+  fn trusted_origins() {
+    let host = Some("127.0.0.1:9229");
+    assert!(super::is_trusted(host, None));
+    assert!(super::is_trusted(host, Some("devtools://devtools")));
+    assert!(super::is_trusted(host, Some("http://localhost:8080")));
+    assert!(super::is_trusted(host, Some("http://[::1]:8080")));
+    assert!(super::is_trusted(Some("192.168.1.10:9229"), None));
+
+    assert!(!super::is_trusted(None, None));
+    assert!(!super::is_trusted(host, Some("http://192.168.1.10")));
+    assert!(!super::is_trusted(host, Some("https://203.0.113.1:8080")));
+    assert!(!super::is_trusted(host, Some("file://")));
+  }
+}
@@ -0,0 +1,228 @@
+//! Built-in objects captured at context creation.
+//!
+//! Scripts may overwrite, or remove, any global object (e.g `JSON`). To
+//! ensure the library always uses the genuine implementations, they are
+//! captured before any user code is run and stored, as reference counted
+//! handles, in the context's internal data, out of reach of scripts.
+use crate::value::{self, Value};
+use crate::{scope, util::jstry, Context, ContextGuard, Property, Result};
+use chakracore_sys::*;
+
+macro_rules! intrinsics {
+  ($($name:ident => $path:expr),* $(,)*) => {
+    /// A built-in object captured at context creation.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub(crate) enum Intrinsic {
+      $($name),*
+    }
+
+    impl Intrinsic {
+      /// All captured intrinsics.
+      const ALL: &'static [Intrinsic] = &[$(Intrinsic::$name),*];
+
+      /// Returns the intrinsic's path relative to the global object.
+      ///
+      /// Accessors are prefixed with `get `, in which case their getter is
+      /// captured instead of their value.
+      fn path(self) -> &'static str {
+        match self {
+          $(Intrinsic::$name => $path),*
+        }
+      }
+    }
+  };
+}
+
+intrinsics! {
+  ArraySplice => "Array.prototype.splice",
+  Date => "Date",
+  DateGetTime => "Date.prototype.getTime",
+  EvalError => "EvalError",
+  JsonParse => "JSON.parse",
+  JsonStringify => "JSON.stringify",
+  Map => "Map",
+  MapDelete => "Map.prototype.delete",
+  MapEntries => "Map.prototype.entries",
+  MapGet => "Map.prototype.get",
+  MapHas => "Map.prototype.has",
+  MapSet => "Map.prototype.set",
+  ObjectFreeze => "Object.freeze",
+  ObjectIsFrozen => "Object.isFrozen",
+  ObjectIsSealed => "Object.isSealed",
+  ObjectSeal => "Object.seal",
+  Promise => "Promise",
+  Proxy => "Proxy",
+  ReflectApply => "Reflect.apply",
+  ReflectConstruct => "Reflect.construct",
+  ReflectDeleteProperty => "Reflect.deleteProperty",
+  ReflectGet => "Reflect.get",
+  ReflectHas => "Reflect.has",
+  ReflectOwnKeys => "Reflect.ownKeys",
+  ReflectSet => "Reflect.set",
+  RegExp => "RegExp",
+  RegExpExec => "RegExp.prototype.exec",
+  RegExpSource => "get RegExp.prototype.source",
+  RegExpToString => "RegExp.prototype.toString",
+  Set => "Set",
+  SetAdd => "Set.prototype.add",
+  SetDelete => "Set.prototype.delete",
+  SetHas => "Set.prototype.has",
+  SetValues => "Set.prototype.values",
+  SymbolIterator => "Symbol.iterator",
+}
+
+/// Captures the intrinsics of the active context, indexed by `Intrinsic`.
+///
+/// This must be called before any script has been evaluated.
+pub(crate) fn capture(guard: &ContextGuard) -> Vec<Value<'static>> {
+  let global = guard.global();
+  Intrinsic::ALL
+    .iter()
+    .map(|intrinsic| {
+      let path = intrinsic.path();
+      let accessor = path.starts_with("get ");
+      let path = path.trim_start_matches("get ");
+
+      let mut names = path.rsplitn(2, '.');
+      let name = Property::new(guard, names.next().unwrap_or(path));
+      let parent = names.next().map_or(global.clone().into(), |parent| {
+        parent
+          .split('.')
+          .fold(global.clone().into(), |object: Value, name| {
+            let object = object.into_object().expect("resolving intrinsic");
+            object.get(guard, Property::new(guard, name))
+          })
+      });
+
+      let parent = parent.into_object().expect("resolving intrinsic");
+      let value = if accessor {
+        parent
+          .get_own_property_descriptor(guard, &name)
+          .and_then(|descriptor| descriptor.get)
+          .expect("resolving intrinsic getter")
+      } else {
+        parent.get(guard, &name)
+      };
+
+      // The handles are stored on the heap, so they must be reference counted
+      scope::unscoped(|| unsafe { Value::from_raw(value.as_raw()) })
+    })
+    .collect()
+}
+
+/// Returns an intrinsic from the values returned by `capture`.
+pub(crate) fn get<'ctx>(
+  _guard: &ContextGuard<'ctx>,
+  intrinsics: &[Value<'static>],
+  intrinsic: Intrinsic,
+) -> Value<'ctx> {
+  unsafe { Value::from_raw(intrinsics[intrinsic as usize].as_raw()) }
+}
+
+/// Calls an intrinsic function with a specified `this` value.
+pub(crate) fn call<'ctx, V: AsRef<Value<'ctx>>>(
+  guard: &ContextGuard<'ctx>,
+  intrinsic: Intrinsic,
+  this: V,
+  arguments: &[&Value<'ctx>],
+) -> Result<Value<'ctx>> {
+  let function = guard
+    .intrinsic(intrinsic)
+    .into_function()
+    .expect("retrieving intrinsic function");
+  function.call_with_this(guard, this, arguments)
+}
+
+/// Constructs an object using an intrinsic constructor.
+pub(crate) fn construct<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  intrinsic: Intrinsic,
+  arguments: &[&Value<'ctx>],
+) -> Result<Value<'ctx>> {
+  let constructor = guard
+    .intrinsic(intrinsic)
+    .into_function()
+    .expect("retrieving intrinsic constructor");
+  constructor.construct(guard, value::undefined(guard), arguments)
+}
+
+/// Returns whether a value has the internal slot an intrinsic method operates
+/// on (e.g `[[MapData]]` for `Map.prototype.has`), by calling the method with
+/// the value and checking that it does not throw.
+///
+/// Unlike `instance_of`, this is unaffected by prototype changes.
+pub(crate) fn has_slot(value: &Value, method: Intrinsic) -> bool {
+  value.is_object()
+    && Context::exec_with_value(value, |guard| {
+      call(guard, method, value, &[&value::undefined(guard)]).is_ok()
+    })
+    .ok()
+    .and_then(|result| result)
+    .unwrap_or(false)
+}
+
+/// Returns whether a value is an instance of a built-in type, by checking for
+/// the internal slot one of its methods operates on (see `has_slot`).
+///
+/// The type's prototype is never an instance, even though some methods
+/// accept it (e.g the `RegExp.prototype.source` getter).
+pub(crate) fn is_instance(value: &Value, constructor: Intrinsic, method: Intrinsic) -> bool {
+  has_slot(value, method)
+    && Context::exec_with_value(value, |guard| {
+      let constructor = guard.intrinsic(constructor).into_object();
+      let prototype = constructor.map(|constructor| {
+        // The property is neither writable nor configurable
+        constructor.get(guard, Property::new(guard, "prototype"))
+      });
+      prototype.map_or(false, |prototype| !prototype.strict_equals(guard, value))
+    })
+    .ok()
+    .and_then(|result| result)
+    .unwrap_or(false)
+}
+
+/// Returns whether a value is an instance of an intrinsic constructor, as
+/// captured by the value's own context.
+///
+/// Scripts may affect the result (e.g using `Symbol.hasInstance`), and any
+/// exception they throw is treated as a negative result.
+pub(crate) fn instance_of(value: &Value, intrinsic: Intrinsic) -> bool {
+  // See: https://github.com/Microsoft/ChakraCore/issues/135
+  // There is no straight foward way to do this with the current API.
+  value.clone().into_object().map_or(false, |object| {
+    Context::exec_with_value(&object, |guard| {
+      let constructor = guard.intrinsic(intrinsic);
+      let mut result = false;
+      let code = unsafe { JsInstanceOf(object.as_raw(), constructor.as_raw(), &mut result) };
+      jstry(code).map_or(false, |_| result)
+    })
+    .ok()
+    .and_then(|result| result)
+    .unwrap_or(false)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Intrinsic;
+  use crate::{script, test};
+
+  #[test]
+  fn tamper_resistance() {
+    test::run_with_context(|guard| {
+      script::eval(
+        guard,
+        "JSON = { stringify: () => 'tampered' }; Promise = null;",
+      )
+      .unwrap();
+
+      let stringify = guard.intrinsic(Intrinsic::JsonStringify);
+      assert!(stringify.is_function());
+
+      let result = script::eval(guard, "Object.getOwnPropertySymbols(this).length").unwrap();
+      assert_eq!(result.to_integer(guard), 0);
+
+      assert!(guard.intrinsic(Intrinsic::Promise).is_function());
+    });
+  }
+}
@@ -0,0 +1,317 @@
+//! Interoperability with the JavaScript iteration protocol.
+//!
+//! Any iterable (arrays, strings, generators, `Map`, `Set` or objects
+//! implementing `Symbol.iterator`) can be consumed as a Rust iterator, and
+//! any Rust iterator can be exposed to scripts as an iterable.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! let set = js::script::eval(guard, "new Set([1, 2, 3])").unwrap();
+//! let sum = js::iterator::iterate(guard, &set)
+//!   .unwrap()
+//!   .map(|value| value.unwrap().to_integer(guard))
+//!   .sum::<i32>();
+//! assert_eq!(sum, 6);
+//!
+//! let squares = js::iterator::iterable(guard, (1..4).map(|n| n * n));
+//! guard.global().set(guard, js::Property::new(guard, "squares"), squares);
+//! let result = js::script::eval(guard, "[...squares].join()").unwrap();
+//! assert_eq!(result.to_string(guard), "1,4,9");
+//! # }).unwrap();
+//! ```
+use crate::convert::IntoJs;
+use crate::intrinsics::Intrinsic;
+use crate::value::{self, Function, Object, Value};
+use crate::{util, ContextGuard, Error, Property, Result};
+use std::cell::RefCell;
+use std::iter::FusedIterator;
+
+/// An iterator over the values produced by a JavaScript iterator.
+///
+/// If the iterator is dropped before it is exhausted, the JavaScript
+/// iterator's `return` method is called (e.g to run a generator's `finally`
+/// blocks), just like when breaking out of a `for..of` loop.
+pub struct Iter<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  iterator: Object<'ctx>,
+  next: Function<'ctx>,
+  done: bool,
+}
+
+/// Returns an iterator over the values of a JavaScript iterable.
+///
+/// A `TypeError` exception is returned if the value is not iterable.
+pub fn iterate<'a, 'ctx>(
+  guard: &'a ContextGuard<'ctx>,
+  iterable: &Value<'ctx>,
+) -> Result<Iter<'a, 'ctx>> {
+  if iterable.is_null() || iterable.is_undefined() {
+    return Err(type_error(guard, "Value is not iterable"));
+  }
+
+  let symbol = guard.intrinsic(Intrinsic::SymbolIterator);
+  let method = iterable
+    .object_representation(guard)
+    .get_by_value(guard, symbol)?
+    .into_function()
+    .ok_or_else(|| type_error(guard, "Value is not iterable"))?;
+
+  let iterator = method
+    .call_with_this(guard, iterable, &[])?
+    .into_object()
+    .ok_or_else(|| {
+      type_error(
+        guard,
+        "Result of the Symbol.iterator method is not an object",
+      )
+    })?;
+  from_iterator(guard, iterator)
+}
+
+/// Returns an iterator over the values of a JavaScript iterator object.
+pub(crate) fn from_iterator<'a, 'ctx>(
+  guard: &'a ContextGuard<'ctx>,
+  iterator: Object<'ctx>,
+) -> Result<Iter<'a, 'ctx>> {
+  let next = iterator
+    .get(guard, Property::new(guard, "next"))
+    .into_function()
+    .ok_or_else(|| type_error(guard, "Iterator does not have a 'next' method"))?;
+
+  Ok(Iter {
+    guard,
+    iterator,
+    next,
+    done: false,
+  })
+}
+
+/// Creates a JavaScript iterable from a Rust iterator.
+///
+/// The returned object is its own iterator, so it can only be iterated
+/// once, like a generator. The Rust iterator is dropped once it's exhausted,
+/// and a `TypeError` is thrown if it's advanced while already running (e.g
+/// from within its own `next`).
+pub fn iterable<'ctx, I>(guard: &ContextGuard<'ctx>, iterator: I) -> Object<'ctx>
+where
+  I: Iterator + Send + 'static,
+  I::Item: for<'a> IntoJs<'a>,
+{
+  let iterator = RefCell::new(Some(iterator));
+  let next = Function::with_name(
+    guard,
+    "next",
+    Box::new(move |guard, _| {
+      let next = {
+        let mut iterator = iterator.try_borrow_mut().map_err(|_| {
+          Value::from(value::Error::type_error(
+            guard,
+            "Iterator is already running",
+          ))
+        })?;
+
+        let next = iterator.as_mut().and_then(Iterator::next);
+        if next.is_none() {
+          *iterator = None;
+        }
+        next
+      };
+      let done = next.is_none();
+      let value = next.map_or_else(|| value::undefined(guard), |value| value.into_js(guard));
+
+      let result = Object::new(guard);
+      result.set(guard, Property::new(guard, "value"), value);
+      result.set(
+        guard,
+        Property::new(guard, "done"),
+        value::Boolean::new(guard, done),
+      );
+      Ok(result.into())
+    }),
+  );
+  let this = Function::with_name(
+    guard,
+    "[Symbol.iterator]",
+    Box::new(|_, info| Ok(info.this)),
+  );
+
+  let object = Object::new(guard);
+  object.set(guard, Property::new(guard, "next"), next);
+  object
+    .set_by_value(guard, guard.intrinsic(Intrinsic::SymbolIterator), this)
+    .expect("defining Symbol.iterator");
+  object
+}
+
+impl<'a, 'ctx> Iterator for Iter<'a, 'ctx> {
+  type Item = Result<Value<'ctx>>;
+
+  /// Returns the next value, or an error if the iterator threw an
+  /// exception. The iteration ends after an error.
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+
+    let guard = self.guard;
+    let result = self
+      .next
+      .call_with_this(guard, &self.iterator, &[])
+      .and_then(|result| {
+        result
+          .into_object()
+          .ok_or_else(|| type_error(guard, "Iterator result is not an object"))
+      });
+
+    match result {
+      Ok(result) => {
+        self.done = result
+          .get(guard, Property::new(guard, "done"))
+          .to_bool(guard);
+        if self.done {
+          None
+        } else {
+          Some(Ok(result.get(guard, Property::new(guard, "value"))))
+        }
+      },
+      Err(error) => {
+        self.done = true;
+        Some(Err(error))
+      },
+    }
+  }
+}
+
+impl<'a, 'ctx> FusedIterator for Iter<'a, 'ctx> {}
+
+impl<'a, 'ctx> Drop for Iter<'a, 'ctx> {
+  fn drop(&mut self) {
+    if self.done {
+      return;
+    }
+
+    let guard = self.guard;
+    if let Some(method) = self
+      .iterator
+      .get(guard, Property::new(guard, "return"))
+      .into_function()
+    {
+      // Errors cannot be propagated from a destructor
+      let _ = method.call_with_this(guard, &self.iterator, &[]);
+    }
+  }
+}
+
+/// Throws a `TypeError` and returns it as an error.
+fn type_error(guard: &ContextGuard, message: &str) -> Error {
+  util::throw(guard, &value::Error::type_error(guard, message).into())
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{iterator, script, test, value, Context, Error, Property};
+  use matches::assert_matches;
+
+  #[test]
+  fn consume_iterables() {
+    test::run_with_context(|guard| {
+      let collect = |source: &str| {
+        let iterable = script::eval(guard, source).unwrap();
+        iterator::iterate(guard, &iterable)
+          .unwrap()
+          .map(|value| value.unwrap().to_string(guard))
+          .collect::<Vec<_>>()
+      };
+
+      assert_eq!(collect("[1, 2]"), ["1", "2"]);
+      assert_eq!(collect("'ab'"), ["a", "b"]);
+      assert_eq!(collect("new Map([['a', 1]])"), ["a,1"]);
+      assert_eq!(
+        collect("(function* () { yield 1; yield 2; })()"),
+        ["1", "2"]
+      );
+      assert_eq!(
+        collect("({ *[Symbol.iterator]() { yield 'custom'; } })"),
+        ["custom"]
+      );
+
+      let object = value::Object::new(guard);
+      assert_matches!(
+        iterator::iterate(guard, &object).err(),
+        Some(Error::ScriptException(ref message)) if message == "TypeError: Value is not iterable"
+      );
+      assert!(iterator::iterate(guard, &value::null(guard)).is_err());
+    });
+  }
+
+  #[test]
+  fn early_return_and_errors() {
+    test::run_with_context(|guard| {
+      let generator = script::eval(
+        guard,
+        "var closed = false;
+         (function* () { try { yield 1; yield 2; } finally { closed = true; } })()",
+      )
+      .unwrap();
+
+      let first = iterator::iterate(guard, &generator).unwrap().next();
+      assert_eq!(first.unwrap().unwrap().to_integer(guard), 1);
+      let closed = script::eval(guard, "closed").unwrap();
+      assert!(closed.to_bool(guard));
+
+      let failing = script::eval(guard, "(function* () { throw new Error('boom'); })()").unwrap();
+      let mut iter = iterator::iterate(guard, &failing).unwrap();
+      assert!(iter.next().unwrap().is_err());
+      assert!(iter.next().is_none());
+    });
+  }
+
+  #[test]
+  fn expose_rust_iterator() {
+    test::run_with_context(|guard| {
+      let words = vec!["foo", "bar"].into_iter().map(String::from);
+      let global = guard.global();
+      global.set(
+        guard,
+        Property::new(guard, "words"),
+        iterator::iterable(guard, words),
+      );
+      global.set(
+        guard,
+        Property::new(guard, "numbers"),
+        iterator::iterable(guard, 0..3),
+      );
+
+      let result = script::eval(
+        guard,
+        "var result = []; for (var word of words) { result.push(word); }
+         result.concat([...numbers], [...numbers]).join()",
+      )
+      .unwrap();
+      assert_eq!(result.to_string(guard), "foo,bar,0,1,2");
+
+      let reentrant = (0..1).map(|_| {
+        Context::exec_with_current(|guard| match script::eval(guard, "reentrant.next()") {
+          Err(Error::ScriptException(message)) => message,
+          _ => String::new(),
+        })
+      });
+      global.set(
+        guard,
+        Property::new(guard, "reentrant"),
+        iterator::iterable(guard, reentrant),
+      );
+
+      let result = script::eval(guard, "reentrant.next().value").unwrap();
+      assert_eq!(
+        result.to_string(guard),
+        "TypeError: Iterator is already running"
+      );
+      let result = script::eval(guard, "reentrant.next().done").unwrap();
+      assert!(result.to_bool(guard));
+    });
+  }
+}
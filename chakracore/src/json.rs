@@ -0,0 +1,176 @@
+//! JSON parsing and serialization.
+//!
+//! Scripts replacing the global `JSON` object, or its methods, do not affect
+//! these functions.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! let value = js::json::parse(guard, r#"{"foo":[1,2]}"#).unwrap();
+//! let json = js::json::stringify_pretty(guard, &value, "  ").unwrap();
+//! assert_eq!(json.unwrap(), "{\n  \"foo\": [\n    1,\n    2\n  ]\n}");
+//! # }).unwrap();
+//! ```
+use crate::intrinsics::Intrinsic;
+use crate::value::{self, Function, Value};
+use crate::{ContextGuard, Result};
+
+/// Parses JSON and returns it represented as a JavaScript value.
+///
+/// A `SyntaxError` exception is returned if the JSON is malformed.
+pub fn parse<'ctx>(guard: &ContextGuard<'ctx>, json: &str) -> Result<Value<'ctx>> {
+  call(
+    guard,
+    Intrinsic::JsonParse,
+    &[&value::String::new(guard, json).into()],
+  )
+}
+
+/// Parses JSON, transforming each value with a reviver.
+///
+/// The reviver is called with `this` set to the object containing the value,
+/// and the value's key and value as arguments. Its return value replaces the
+/// value (or removes it, if `undefined`).
+pub fn parse_with_reviver<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  json: &str,
+  reviver: &Function<'ctx>,
+) -> Result<Value<'ctx>> {
+  let json = value::String::new(guard, json).into();
+  call(guard, Intrinsic::JsonParse, &[&json, reviver])
+}
+
+/// Converts a value to its JSON representation.
+///
+/// Returns `None` if the value has no JSON representation (e.g `undefined`
+/// or a function), and a `TypeError` exception for cyclic structures or
+/// values that cannot be serialized.
+pub fn stringify<'ctx>(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Option<String>> {
+  stringify_impl(guard, value, None, None)
+}
+
+/// Converts a value to its JSON representation, indenting nested values.
+///
+/// The indentation is limited to 10 characters.
+pub fn stringify_pretty<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  value: &Value<'ctx>,
+  indent: &str,
+) -> Result<Option<String>> {
+  stringify_impl(guard, value, None, Some(indent))
+}
+
+/// Converts a value to its JSON representation, transforming each value with
+/// a replacer.
+///
+/// The replacer is called with `this` set to the object containing the
+/// value, and the value's key and value as arguments. Its return value is
+/// serialized in place of the value (or omitted, if `undefined`).
+pub fn stringify_with_replacer<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  value: &Value<'ctx>,
+  replacer: &Function<'ctx>,
+  indent: Option<&str>,
+) -> Result<Option<String>> {
+  stringify_impl(guard, value, Some(replacer), indent)
+}
+
+/// Calls the built-in `JSON.stringify`.
+fn stringify_impl<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  value: &Value<'ctx>,
+  replacer: Option<&Function<'ctx>>,
+  indent: Option<&str>,
+) -> Result<Option<String>> {
+  let replacer = replacer.map_or_else(
+    || value::undefined(guard),
+    |function| function.clone().into(),
+  );
+  let indent = indent.map_or_else(
+    || value::undefined(guard),
+    |indent| value::String::new(guard, indent).into(),
+  );
+
+  let result = call(
+    guard,
+    Intrinsic::JsonStringify,
+    &[value, &replacer, &indent],
+  )?;
+  Ok(result.into_string().map(|json| json.value()))
+}
+
+/// Calls a captured `JSON` function.
+fn call<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  intrinsic: Intrinsic,
+  arguments: &[&Value<'ctx>],
+) -> Result<Value<'ctx>> {
+  let function = guard
+    .intrinsic(intrinsic)
+    .into_function()
+    .expect("retrieving JSON function");
+  function.call(guard, arguments)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::value::{self, Persistent, Value};
+  use crate::{json, script, test, ContextGuard, Error};
+  use matches::assert_matches;
+
+  #[test]
+  fn round_trip() {
+    test::run_with_context(|guard| {
+      script::eval(guard, "JSON = undefined;").unwrap();
+
+      let value = json::parse(guard, r#"{"foo":"bar","baz":[1,null]}"#).unwrap();
+      let json = json::stringify(guard, &value).unwrap();
+      assert_eq!(json.unwrap(), r#"{"foo":"bar","baz":[1,null]}"#);
+
+      let undefined = value::undefined(guard);
+      assert_eq!(json::stringify(guard, &undefined).unwrap(), None);
+    });
+  }
+
+  #[test]
+  fn reviver_and_replacer() {
+    test::run_with_context(|guard| {
+      let double = value::Function::wrap(
+        guard,
+        |guard: &ContextGuard, _key: String, value: Persistent<Value<'static>>| match value
+          .get(guard)
+          .unwrap()
+          .into_number()
+        {
+          Some(number) => {
+            let number = value::Number::from_double(guard, number.value_double() * 2.0);
+            Persistent::new(guard, &Value::from(number))
+          },
+          None => value,
+        },
+      );
+
+      let value = json::parse_with_reviver(guard, "[1,[2]]", &double).unwrap();
+      let json = json::stringify(guard, &value).unwrap();
+      assert_eq!(json.unwrap(), "[2,[4]]");
+
+      let json = json::stringify_with_replacer(guard, &value, &double, None).unwrap();
+      assert_eq!(json.unwrap(), "[4,[8]]");
+    });
+  }
+
+  #[test]
+  fn errors() {
+    test::run_with_context(|guard| {
+      assert_matches!(json::parse(guard, "{"), Err(Error::ScriptException(_)));
+
+      let cyclic = script::eval(guard, "var foo = {}; foo.foo = foo; foo").unwrap();
+      assert_matches!(
+        json::stringify(guard, &cyclic),
+        Err(Error::ScriptException(_))
+      );
+    });
+  }
+}
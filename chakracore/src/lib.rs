@@ -10,27 +10,52 @@
 //! fn main() {
 //!   let runtime = js::Runtime::new().unwrap();
 //!   let context = js::Context::new(&runtime).unwrap();
-//!   let guard = context.make_current().unwrap();
 //!
-//!   let result = js::script::eval(&guard, "(5 + 5)").unwrap();
-//!   assert_eq!(result.to_integer(&guard), 10);
+//!   context
+//!     .exec_with(|guard| {
+//!       let result = js::script::eval(guard, "(5 + 5)").unwrap();
+//!       assert_eq!(result.to_integer(guard), 10);
+//!     })
+//!     .unwrap();
 //! }
 //! ```
 //!
 //! *NOTE: During pre-release (0.X.X) stability may vary.*
 
+#[cfg(feature = "derive")]
+pub use chakracore_derive::{js_class, js_function};
+
+pub use class::Class;
 pub use context::{Context, ContextGuard};
 pub use error::{Error, Result};
-pub use property::Property;
+pub use property::{Property, PropertyDescriptor};
 pub use runtime::Runtime;
+pub use scope::HandleScope;
 
 #[macro_use]
 mod macros;
-mod context;
+pub mod binding;
+pub mod class;
+pub mod context;
+pub mod convert;
+#[cfg(feature = "coverage")]
+pub mod coverage;
+pub mod debug;
+pub mod deterministic;
 mod error;
-mod property;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+mod intrinsics;
+pub mod iterator;
+pub mod json;
+pub mod property;
 pub mod runtime;
+mod scope;
 pub mod script;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "ttd")]
+mod ttd;
 mod util;
 pub mod value;
 
@@ -66,21 +91,90 @@ mod bench {
   fn property_bench(bench: &mut Bencher) {
     let (_runtime, context) = setup_env();
 
-    let guard = context.make_current().unwrap();
-    let object = value::Object::new(&guard);
-    object.set(
-      &guard,
-      Property::new(&guard, "test"),
-      value::Number::new(&guard, 10),
-    );
-
-    bench.iter(|| {
-      (0..10000).fold(0, |acc, _| {
-        acc
-          + object
-            .get(&guard, Property::new(&guard, "test"))
-            .to_integer(&guard)
-      });
-    });
+    context
+      .exec_with(|guard| {
+        let object = value::Object::new(guard);
+        object.set(
+          guard,
+          Property::new(guard, "test"),
+          value::Number::new(guard, 10),
+        );
+
+        bench.iter(|| {
+          (0..10000).fold(0, |acc, _| {
+            acc
+              + object
+                .get(guard, Property::new(guard, "test"))
+                .to_integer(guard)
+          });
+        });
+      })
+      .unwrap();
+  }
+
+  #[bench]
+  fn property_scope_bench(bench: &mut Bencher) {
+    let (_runtime, context) = setup_env();
+
+    context
+      .exec_with(|guard| {
+        let object = value::Object::new(guard);
+        object.set(
+          guard,
+          Property::new(guard, "test"),
+          value::Number::new(guard, 10),
+        );
+
+        bench.iter(|| unsafe {
+          HandleScope::exec_with(guard, |scope| {
+            let object = scope.adopt(&object);
+            (0..10000).fold(0, |acc, _| {
+              acc
+                + object
+                  .get(scope, Property::new(scope, "test"))
+                  .to_integer(scope)
+            })
+          })
+        });
+      })
+      .unwrap();
+  }
+
+  #[bench]
+  fn array_bench(bench: &mut Bencher) {
+    let (_runtime, context) = setup_env();
+    context
+      .exec_with(|guard| {
+        bench.iter(|| {
+          let array = value::Array::new(guard, 0);
+          for index in 0..1000 {
+            array.push(guard, value::Number::new(guard, index));
+          }
+          array
+            .iter(guard)
+            .fold(0, |acc, value| acc + value.to_integer(guard))
+        });
+      })
+      .unwrap();
+  }
+
+  #[bench]
+  fn array_scope_bench(bench: &mut Bencher) {
+    let (_runtime, context) = setup_env();
+    context
+      .exec_with(|guard| {
+        bench.iter(|| unsafe {
+          HandleScope::exec_with(guard, |scope| {
+            let array = value::Array::new(scope, 0);
+            for index in 0..1000 {
+              array.push(scope, value::Number::new(scope, index));
+            }
+            array
+              .iter(scope)
+              .fold(0, |acc, value| acc + value.to_integer(scope))
+          })
+        });
+      })
+      .unwrap();
   }
 }
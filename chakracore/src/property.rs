@@ -1,7 +1,23 @@
+//! Property identifiers and descriptors.
+use crate::value::function::CallbackResult;
+use crate::value::{self, Function, Object, Value};
 use crate::{Context, ContextGuard};
 use chakracore_sys::*;
 use std::fmt;
 
+/// Callback type for accessor getters. It receives the `this` value.
+pub type GetterCallback =
+  dyn for<'ctx> Fn(&ContextGuard<'ctx>, Value<'ctx>) -> CallbackResult<'ctx> + Send;
+
+/// Callback type for accessor setters. It receives the `this` value and the
+/// assigned value.
+pub type SetterCallback = dyn for<'ctx> Fn(
+    &ContextGuard<'ctx>,
+    Value<'ctx>,
+    Value<'ctx>,
+  ) -> ::std::result::Result<(), Value<'ctx>>
+  + Send;
+
 /// A property identifier used with objects.
 #[derive(PartialEq)]
 pub struct Property(JsPropertyIdRef);
@@ -45,9 +61,179 @@ impl fmt::Debug for Property {
 
 reference!(Property);
 
+/// A description of a property's value or accessors, and its attributes.
+///
+/// Unset fields are omitted, which means they retain their current value
+/// when modifying a property, and default to `false`/`undefined` when
+/// defining a new one.
+///
+/// ```rust
+/// # use chakracore as js;
+/// # let runtime = js::Runtime::new().unwrap();
+/// # let context = js::Context::new(&runtime).unwrap();
+/// # context.exec_with(|guard| {
+/// let descriptor = js::PropertyDescriptor::new()
+///   .getter(guard, Box::new(|guard, _| Ok(js::value::Number::new(guard, 42).into())))
+///   .enumerable(true);
+///
+/// let property = js::Property::new(guard, "answer");
+/// guard.global().define_own_property(guard, property, &descriptor).unwrap();
+///
+/// let result = js::script::eval(guard, "answer").unwrap();
+/// assert_eq!(result.to_integer(guard), 42);
+/// # }).unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct PropertyDescriptor<'ctx> {
+  /// The property's value.
+  pub value: Option<Value<'ctx>>,
+  /// Whether the value can be changed by assignment.
+  pub writable: Option<bool>,
+  /// Whether the property shows up during enumeration.
+  pub enumerable: Option<bool>,
+  /// Whether the property can be deleted, or its descriptor changed.
+  pub configurable: Option<bool>,
+  /// The property's getter, either a function or `undefined`.
+  pub get: Option<Value<'ctx>>,
+  /// The property's setter, either a function or `undefined`.
+  pub set: Option<Value<'ctx>>,
+}
+
+impl<'ctx> PropertyDescriptor<'ctx> {
+  /// Creates an empty property descriptor.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the property's value.
+  pub fn value<V: Into<Value<'ctx>>>(mut self, value: V) -> Self {
+    self.value = Some(value.into());
+    self
+  }
+
+  /// Sets whether the property's value can be changed.
+  pub fn writable(mut self, writable: bool) -> Self {
+    self.writable = Some(writable);
+    self
+  }
+
+  /// Sets whether the property shows up during enumeration.
+  pub fn enumerable(mut self, enumerable: bool) -> Self {
+    self.enumerable = Some(enumerable);
+    self
+  }
+
+  /// Sets whether the property can be deleted or reconfigured.
+  pub fn configurable(mut self, configurable: bool) -> Self {
+    self.configurable = Some(configurable);
+    self
+  }
+
+  /// Sets the property's getter function.
+  pub fn get(mut self, getter: Function<'ctx>) -> Self {
+    self.get = Some(getter.into());
+    self
+  }
+
+  /// Sets the property's setter function.
+  pub fn set(mut self, setter: Function<'ctx>) -> Self {
+    self.set = Some(setter.into());
+    self
+  }
+
+  /// Sets the property's getter to a native callback.
+  pub fn getter(self, guard: &ContextGuard<'ctx>, callback: Box<GetterCallback>) -> Self {
+    let getter = Function::new(
+      guard,
+      Box::new(move |guard, info| callback(guard, info.this)),
+    );
+    self.get(getter)
+  }
+
+  /// Sets the property's setter to a native callback.
+  pub fn setter(self, guard: &ContextGuard<'ctx>, callback: Box<SetterCallback>) -> Self {
+    let setter = Function::new(
+      guard,
+      Box::new(move |guard, info| {
+        let value = info
+          .arguments
+          .into_iter()
+          .next()
+          .unwrap_or_else(|| value::undefined(guard));
+        callback(guard, info.this, value).map(|_| value::undefined(guard))
+      }),
+    );
+    self.set(setter)
+  }
+
+  /// Returns true if the descriptor has a getter or setter, even if it's
+  /// `undefined`.
+  pub fn is_accessor(&self) -> bool {
+    self.get.is_some() || self.set.is_some()
+  }
+
+  /// Creates a descriptor from its JavaScript representation.
+  ///
+  /// Fields that are not present on the object are left unset.
+  pub fn from_object(guard: &ContextGuard<'ctx>, object: &Object<'ctx>) -> Self {
+    let field = |name| {
+      let property = Property::new(guard, name);
+      if object.has(guard, &property) {
+        Some(object.get(guard, &property))
+      } else {
+        None
+      }
+    };
+
+    PropertyDescriptor {
+      value: field("value"),
+      writable: field("writable").map(|value| value.to_bool(guard)),
+      enumerable: field("enumerable").map(|value| value.to_bool(guard)),
+      configurable: field("configurable").map(|value| value.to_bool(guard)),
+      get: field("get"),
+      set: field("set"),
+    }
+  }
+
+  /// Returns the descriptor's JavaScript representation.
+  pub fn to_object(&self, guard: &ContextGuard<'ctx>) -> Object<'ctx> {
+    let object = Object::new(guard);
+    let set = |name, value: Value<'ctx>| object.set(guard, Property::new(guard, name), value);
+
+    if let Some(ref value) = self.value {
+      set("value", value.clone());
+    }
+
+    let flags = [
+      ("writable", self.writable),
+      ("enumerable", self.enumerable),
+      ("configurable", self.configurable),
+    ];
+
+    for (name, flag) in flags.iter() {
+      if let Some(flag) = flag {
+        set(name, value::Boolean::new(guard, *flag).into());
+      }
+    }
+
+    if let Some(ref get) = self.get {
+      set("get", get.clone());
+    }
+
+    if let Some(ref setter) = self.set {
+      set("set", setter.clone());
+    }
+
+    object
+  }
+}
+
 #[cfg(test)]
 mod tests {
-  use crate::{test, Property};
+  use crate::{script, test, value, Error, Property, PropertyDescriptor};
+  use matches::assert_matches;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
 
   #[test]
   fn string_conversion() {
@@ -56,4 +242,123 @@ mod tests {
       assert_eq!(property.to_string(guard), "foo");
     });
   }
+
+  #[test]
+  fn lazy_accessor() {
+    test::run_with_context(|guard| {
+      let calls = Arc::new(AtomicUsize::new(0));
+      let counter = calls.clone();
+
+      let descriptor = PropertyDescriptor::new()
+        .getter(
+          guard,
+          Box::new(move |guard, this| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let value: value::Value = value::String::new(guard, "expensive").into();
+
+            // Replace the accessor with the computed value
+            let object = this.into_object().expect("accessor receiver");
+            let property = Property::new(guard, "lazy");
+            let descriptor = PropertyDescriptor::new().value(value.clone());
+            object
+              .define_own_property(guard, property, &descriptor)
+              .map_err(|_| value::Error::new(guard, "redefining property"))?;
+            Ok(value)
+          }),
+        )
+        .configurable(true);
+
+      let object = value::Object::new(guard);
+      let property = Property::new(guard, "lazy");
+      assert!(object
+        .define_own_property(guard, &property, &descriptor)
+        .unwrap());
+      assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+      assert_eq!(object.get(guard, &property).to_string(guard), "expensive");
+      assert_eq!(object.get(guard, &property).to_string(guard), "expensive");
+      assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+      let descriptor = object
+        .get_own_property_descriptor(guard, &property)
+        .unwrap();
+      assert!(!descriptor.is_accessor());
+      assert_eq!(descriptor.writable, Some(false));
+      assert_eq!(descriptor.configurable, Some(true));
+    });
+  }
+
+  #[test]
+  fn define_properties() {
+    test::run_with_context(|guard| {
+      let object = value::Object::new(guard);
+      let setter = PropertyDescriptor::new().setter(
+        guard,
+        Box::new(|guard, this, value| {
+          let object = this.into_object().expect("accessor receiver");
+          object.set(guard, Property::new(guard, "stored"), value);
+          Ok(())
+        }),
+      );
+
+      assert!(object
+        .define_properties(
+          guard,
+          vec![
+            (Property::new(guard, "input"), setter),
+            (
+              Property::new(guard, "constant"),
+              PropertyDescriptor::new()
+                .value(value::Number::new(guard, 10))
+                .enumerable(true),
+            ),
+          ],
+        )
+        .unwrap());
+
+      let global = guard.global();
+      global.set(guard, Property::new(guard, "object"), &object);
+      let result = script::eval(guard, "object.input = 5; object.constant = 20; Object.keys(object) + ':' + object.stored + object.constant").unwrap();
+      assert_eq!(result.to_string(guard), "constant,stored:510");
+
+      let missing = Property::new(guard, "missing");
+      assert!(object.get_own_property_descriptor(guard, missing).is_none());
+
+      let invalid = PropertyDescriptor::new()
+        .value(value::Number::new(guard, 1))
+        .get(value::Function::new(
+          guard,
+          Box::new(|guard, _| Ok(value::undefined(guard))),
+        ));
+      assert_matches!(
+        object.define_own_property(guard, Property::new(guard, "invalid"), &invalid),
+        Err(Error::ScriptException(_))
+      );
+    });
+  }
+
+  #[test]
+  fn undefined_accessor() {
+    test::run_with_context(|guard| {
+      let object = script::eval(guard, "({ get foo() {} })")
+        .unwrap()
+        .into_object()
+        .unwrap();
+      let property = Property::new(guard, "foo");
+      assert!(object.define_property(
+        guard,
+        &property,
+        script::eval(guard, "({ get: undefined })")
+          .unwrap()
+          .into_object()
+          .unwrap()
+      ));
+
+      let descriptor = object
+        .get_own_property_descriptor(guard, &property)
+        .unwrap();
+      assert!(descriptor.is_accessor());
+      assert!(descriptor.get.unwrap().is_undefined());
+    });
+  }
 }
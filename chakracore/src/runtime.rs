@@ -1,7 +1,14 @@
 //! Runtime and builder.
+#[cfg(feature = "ttd")]
+use crate::{ttd, Context, ContextGuard};
 use crate::{util::jstry, Result};
 use chakracore_sys::*;
 use libc::c_void;
+#[cfg(feature = "ttd")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "ttd")]
+use std::ptr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// A callback triggered before objects are collected.
@@ -11,14 +18,19 @@ pub type CollectCallback = dyn Fn() + Send;
 pub struct Builder {
   memory_limit: Option<usize>,
   collect_callback: Option<Box<CollectCallback>>,
+  #[cfg(feature = "ttd")]
+  recording: Option<PathBuf>,
   attributes: JsRuntimeAttributes,
 }
 
 /// An isolated instance of a runtime.
+///
+/// The underlying runtime is disposed once the instance, and any persistent
+/// handles created within it, have been dropped.
 pub struct Runtime {
-  #[allow(dead_code)]
-  callback: Option<Box<Box<CollectCallback>>>,
-  handle: JsRuntimeHandle,
+  instance: Arc<Instance>,
+  #[cfg(feature = "ttd")]
+  recording: Option<PathBuf>,
   last_idle_tick: Option<Duration>,
   last_idle: Option<Instant>,
 }
@@ -34,10 +46,106 @@ impl Runtime {
     Builder {
       memory_limit: None,
       collect_callback: None,
+      #[cfg(feature = "ttd")]
+      recording: None,
       attributes: JsRuntimeAttributeNone,
     }
   }
 
+  /// Creates a runtime that replays a time-travel debugging log.
+  ///
+  /// The log must have been recorded by a runtime created with `record_to`.
+  /// Call `replay` to execute it.
+  #[cfg(feature = "ttd")]
+  pub fn replay_from<P: AsRef<Path>>(directory: P) -> Result<Runtime> {
+    let uri = ttd::to_uri(directory.as_ref())?;
+    let mut handle = JsRuntimeHandle::new();
+    jstry(unsafe {
+      JsTTDCreateReplayRuntime(
+        JsRuntimeAttributeNone,
+        uri.as_ptr() as *const _,
+        uri.len(),
+        false,
+        Some(ttd::open_stream),
+        Some(ttd::read_stream),
+        Some(ttd::close_stream),
+        None,
+        &mut handle,
+      )
+    })?;
+
+    Ok(Runtime {
+      instance: Arc::new(Instance {
+        handle,
+        callback: None,
+      }),
+      last_idle: None,
+      last_idle_tick: None,
+      recording: None,
+    })
+  }
+
+  /// Starts recording the execution of the runtime's contexts.
+  ///
+  /// This requires a runtime created with `record_to`.
+  #[cfg(feature = "ttd")]
+  pub fn start_recording(&self, _guard: &ContextGuard) -> Result<()> {
+    jstry(unsafe { JsTTDStart() })
+  }
+
+  /// Stops recording, and writes the log to the runtime's directory.
+  #[cfg(feature = "ttd")]
+  pub fn stop_recording(&self, _guard: &ContextGuard) -> Result<()> {
+    if let Some(directory) = &self.recording {
+      let uri = ttd::to_uri(directory)?;
+      jstry(unsafe { JsTTDDiagWriteLog(uri.as_ptr() as *const _, uri.len()) })?;
+    }
+    jstry(unsafe { JsTTDStop() })
+  }
+
+  /// Replays a recorded log, from its first event until the end.
+  ///
+  /// This requires a runtime created with `replay_from`. Any exception thrown
+  /// during the recording is reproduced and returned as an error.
+  #[cfg(feature = "ttd")]
+  pub fn replay(&self) -> Result<()> {
+    // The context is set up like any other, so native callbacks invoked by
+    // the replay can access its data.
+    let context = unsafe {
+      let mut reference = JsContextRef::new();
+      jstry(JsTTDCreateContext(self.as_raw(), true, &mut reference))?;
+      Context::attach(reference, self)?
+    };
+
+    context.exec_with(|_| {
+      let mut mode = JsTTDMoveKthEvent;
+      let mut event_time = -2;
+
+      // The runtime reports an event time of -1 once the log has been replayed
+      while event_time != -1 {
+        let mut snapshot_time = -1;
+        unsafe {
+          jstry(JsTTDGetSnapTimeTopLevelEventMove(
+            self.as_raw(),
+            mode,
+            1,
+            &mut event_time,
+            &mut snapshot_time,
+            ptr::null_mut(),
+          ))?;
+          jstry(JsTTDMoveToTopLevelEvent(
+            self.as_raw(),
+            mode,
+            snapshot_time,
+            event_time,
+          ))?;
+          jstry(JsTTDReplayExecution(&mut mode, &mut event_time))?;
+        }
+      }
+      Ok(())
+    })?
+  }
+
   /// Performs a full garbage collection.
   pub fn collect(&self) -> Result<()> {
     jstry(unsafe { JsCollectGarbage(self.as_raw()) })
@@ -75,7 +183,12 @@ impl Runtime {
 
   /// Returns the underlying raw pointer behind this runtime.
   pub fn as_raw(&self) -> JsRuntimeHandle {
-    self.handle
+    self.instance.handle
+  }
+
+  /// Returns the runtime's shared instance.
+  pub(crate) fn instance(&self) -> &Arc<Instance> {
+    &self.instance
   }
 
   /// A collector callback, triggered before objects are released.
@@ -85,11 +198,32 @@ impl Runtime {
   }
 }
 
-impl Drop for Runtime {
+/// The disposable part of a runtime, shared with any persistent handles so
+/// the runtime outlives them.
+pub(crate) struct Instance {
+  handle: JsRuntimeHandle,
+  #[allow(dead_code)]
+  callback: Option<Box<Box<CollectCallback>>>,
+}
+
+impl Instance {
+  /// Returns the underlying raw pointer behind the runtime.
+  pub(crate) fn as_raw(&self) -> JsRuntimeHandle {
+    self.handle
+  }
+}
+
+// Once shared, the instance is only used to request async breaks, which the
+// runtime allows from any thread, and to be disposed.
+unsafe impl Sync for Instance {}
+
+impl Drop for Instance {
+  /// Disposes the runtime.
+  ///
+  /// This fails if the runtime is active in any thread, in which case it's
+  /// leaked rather than panicking during a drop.
   fn drop(&mut self) {
-    unsafe {
-      jsassert!(JsDisposeRuntime(self.as_raw()));
-    }
+    let _ = unsafe { JsDisposeRuntime(self.handle) };
   }
 }
 
@@ -152,10 +286,19 @@ impl Builder {
     self
   }
 
+  /// Record the runtime's execution to a directory, for time-travel
+  /// debugging. Recording is controlled using `start_recording` and
+  /// `stop_recording`, and the log is replayed using `Runtime::replay_from`.
+  #[cfg(feature = "ttd")]
+  pub fn record_to<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+    self.recording = Some(directory.into());
+    self
+  }
+
   /// Creates the runtime object with associated settings.
   pub fn build(self) -> Result<Runtime> {
     let mut handle = JsRuntimeHandle::new();
-    jstry(unsafe { JsCreateRuntime(self.attributes, None, &mut handle) })?;
+    jstry(unsafe { self.create(&mut handle) })?;
 
     if let Some(limit) = self.memory_limit {
       jstry(unsafe { JsSetRuntimeMemoryLimit(handle, limit) })?;
@@ -172,16 +315,45 @@ impl Builder {
     });
 
     Ok(Runtime {
+      instance: Arc::new(Instance { handle, callback }),
       last_idle: None,
       last_idle_tick: None,
-      handle,
-      callback,
+      #[cfg(feature = "ttd")]
+      recording: self.recording,
     })
   }
+
+  /// Creates the underlying runtime, recording its execution if requested.
+  #[cfg(feature = "ttd")]
+  unsafe fn create(&self, handle: &mut JsRuntimeHandle) -> JsErrorCode {
+    if self.recording.is_some() {
+      // Snapshots are only taken when required
+      JsTTDCreateRecordRuntime(
+        self.attributes,
+        false,
+        u32::MAX as usize,
+        u32::MAX as usize,
+        Some(ttd::open_stream),
+        Some(ttd::write_stream),
+        Some(ttd::close_stream),
+        None,
+        handle,
+      )
+    } else {
+      JsCreateRuntime(self.attributes, None, handle)
+    }
+  }
+
+  /// Creates the underlying runtime.
+  #[cfg(not(feature = "ttd"))]
+  unsafe fn create(&self, handle: &mut JsRuntimeHandle) -> JsErrorCode {
+    JsCreateRuntime(self.attributes, None, handle)
+  }
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::value::Persistent;
   use crate::{script, test, Context, Runtime};
   use std::{
     sync::{Arc, Mutex},
@@ -214,19 +386,47 @@ mod tests {
   fn thread_send() {
     let runtime = Runtime::new().unwrap();
     let context = Context::new(&runtime).unwrap();
-    let result = {
-      let guard = context.make_current().unwrap();
-      script::eval(&guard, "[5, 'foo', {}]")
-        .unwrap()
-        .into_array()
-        .unwrap()
-    };
+    let result = context
+      .exec_with(|guard| {
+        let array = script::eval(guard, "[5, 'foo', {}]")
+          .unwrap()
+          .into_array()
+          .unwrap();
+        Persistent::new(guard, &array)
+      })
+      .unwrap();
 
     thread::spawn(move || {
-      let guard = context.make_current().unwrap();
-      assert_eq!(result.len(&guard), 3);
+      context
+        .exec_with(|guard| assert_eq!(result.get(guard).unwrap().len(guard), 3))
+        .unwrap();
     })
     .join()
     .unwrap();
   }
+
+  #[test]
+  #[cfg(feature = "ttd")]
+  fn time_travel() {
+    use std::{env, fs, process};
+
+    let directory = env::temp_dir().join(format!("chakracore-ttd-{}", process::id()));
+    {
+      let runtime = Runtime::builder().record_to(&directory).build().unwrap();
+      let context = Context::new(&runtime).unwrap();
+      context
+        .exec_with(|guard| {
+          runtime.start_recording(guard).unwrap();
+          let result = script::eval(guard, "[1, 2, 3].map(x => x * 2).join()").unwrap();
+          assert_eq!(result.to_string(guard), "2,4,6");
+          runtime.stop_recording(guard).unwrap();
+        })
+        .unwrap();
+    }
+
+    assert!(fs::read_dir(&directory).unwrap().next().is_some());
+    let runtime = Runtime::replay_from(&directory).unwrap();
+    runtime.replay().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+  }
 }
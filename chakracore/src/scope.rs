@@ -0,0 +1,218 @@
+//! Handle scopes, for values that are rooted by the stack.
+use crate::value::{Rebind, Value};
+use crate::ContextGuard;
+use chakracore_sys::*;
+use std::{cell::Cell, marker::PhantomData, ops::Deref, ptr};
+
+thread_local! {
+  /// The number of active handle scopes in the current thread.
+  static DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// A scope in which values are rooted by the stack, instead of being
+/// reference counted.
+///
+/// By default, every value increments its reference count when it's created
+/// and decrements it when it's dropped, so it can safely be stored on the
+/// heap. This is unnecessary for short-lived temporaries, since the runtime
+/// observes the stack. Values created while a scope is active skip this
+/// overhead, which benefits property-heavy loops in particular.
+///
+/// The scope dereferences to a `ContextGuard`, branded with a lifetime unique
+/// to the scope, so values created with it cannot escape it. Values created
+/// outside of the scope are bound to it using `adopt`:
+///
+/// ```rust
+/// # use chakracore as js;
+/// # let runtime = js::Runtime::new().unwrap();
+/// # let context = js::Context::new(&runtime).unwrap();
+/// # context.exec_with(|guard| {
+/// let object = js::script::eval(guard, "({ foo: 10 })").unwrap().into_object().unwrap();
+///
+/// let sum = unsafe {
+///   js::HandleScope::exec_with(guard, |scope| {
+///     let object = scope.adopt(&object);
+///     (0..100).fold(0, |sum, _| {
+///       sum + object.get(scope, js::Property::new(scope, "foo")).to_integer(scope)
+///     })
+///   })
+/// };
+/// assert_eq!(sum, 1000);
+/// # }).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct HandleScope<'s, 'ctx> {
+  guard: ContextGuard<'s>,
+  phantom: PhantomData<(*const (), fn(&'ctx ()) -> &'ctx ())>,
+}
+
+impl<'s, 'ctx> HandleScope<'s, 'ctx> {
+  /// Executes a closure with a handle scope, active until it returns.
+  ///
+  /// This is unsafe because values created within the scope are only kept
+  /// alive by the stack of the current thread. The caller must ensure that
+  /// none of them are stored elsewhere (e.g in a `Vec`, a `Box`, or sent to
+  /// another thread), since the garbage collector does not observe it. This
+  /// includes values created within the closure using another guard, which
+  /// are not reference counted either. Containers returned by the API, such as
+  /// `Array::to_vec`, are always reference counted. Use a `Persistent` handle
+  /// to keep a value beyond the scope.
+  pub unsafe fn exec_with<T, Ret>(guard: &ContextGuard<'ctx>, callback: T) -> Ret
+  where
+    T: for<'a> FnOnce(&HandleScope<'a, 'ctx>) -> Ret,
+  {
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let scope = HandleScope {
+      guard: guard.rebrand(),
+      phantom: PhantomData,
+    };
+    callback(&scope)
+  }
+
+  /// Returns a value created outside of the scope, bound to the scope.
+  pub fn adopt<V>(&self, value: &V) -> <V as Rebind<'s>>::Bound
+  where
+    V: AsRef<Value<'ctx>> + Rebind<'s>,
+  {
+    // The value belongs to the scope's context, since it shares its brand
+    unsafe { V::rebind(value.as_ref().as_raw()) }
+  }
+}
+
+impl<'s, 'ctx> Deref for HandleScope<'s, 'ctx> {
+  type Target = ContextGuard<'s>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.guard
+  }
+}
+
+impl<'s, 'ctx> Drop for HandleScope<'s, 'ctx> {
+  /// Deactivates the scope.
+  fn drop(&mut self) {
+    DEPTH.with(|depth| depth.set(depth.get() - 1));
+  }
+}
+
+/// The rooting of a value; either reference counted or by the stack.
+///
+/// It also carries the brand of the value's `ContextGuard`, which is
+/// invariant.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Root<'ctx> {
+  counted: bool,
+  phantom: PhantomData<fn(&'ctx ()) -> &'ctx ()>,
+}
+
+impl<'ctx> Root<'ctx> {
+  /// Roots a value, incrementing its reference count unless a handle scope
+  /// is active.
+  pub unsafe fn acquire(value: JsValueRef) -> Self {
+    let counted = DEPTH.with(|depth| depth.get() == 0);
+    if counted {
+      jsassert!(JsAddRef(value, ptr::null_mut()));
+    }
+
+    Root {
+      counted,
+      phantom: PhantomData,
+    }
+  }
+
+  /// Returns whether the value's reference count must be decremented.
+  pub fn is_counted(&self) -> bool {
+    self.counted
+  }
+}
+
+/// Executes a closure with any active handle scopes suspended.
+///
+/// This is used when values are stored on the heap internally.
+pub(crate) fn unscoped<T, Ret>(callback: T) -> Ret
+where
+  T: FnOnce() -> Ret,
+{
+  /// Resumes the suspended scopes, even if the callback panics.
+  struct Resume(usize);
+
+  impl Drop for Resume {
+    fn drop(&mut self) {
+      DEPTH.with(|depth| depth.set(self.0));
+    }
+  }
+
+  let _resume = Resume(DEPTH.with(|depth| depth.replace(0)));
+  callback()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{script, test, value, HandleScope};
+  use chakracore_sys::*;
+
+  /// Returns the reference count of a value, excluding the probe itself.
+  fn references(value: &value::Value) -> u32 {
+    let mut count = 0;
+    unsafe {
+      JsAddRef(value.as_raw(), &mut count);
+      JsRelease(value.as_raw(), std::ptr::null_mut());
+    }
+    count - 1
+  }
+
+  #[test]
+  fn uncounted_values() {
+    test::run_with_context(|guard| {
+      let counted: value::Value = value::Object::new(guard).into();
+      assert_eq!(references(&counted), 1);
+
+      unsafe {
+        HandleScope::exec_with(guard, |scope| {
+          let scoped: value::Value = value::Object::new(scope).into();
+          assert_eq!(references(&scoped), 0);
+          assert_eq!(references(&counted.clone()), 1);
+
+          let adopted = scope.adopt(&counted);
+          assert_eq!(references(&adopted), 1);
+
+          let inner = super::unscoped(|| scoped.clone());
+          assert_eq!(references(&inner), 1);
+        })
+      };
+
+      let counted = counted.clone();
+      assert_eq!(references(&counted), 2);
+    });
+  }
+
+  #[test]
+  fn counted_containers() {
+    test::run_with_context(|guard| {
+      let object = script::eval(guard, "({ foo: 1, bar: 2 })").unwrap();
+      let object = object.into_object().unwrap();
+      unsafe {
+        HandleScope::exec_with(guard, |scope| {
+          let object = scope.adopt(&object);
+          let (_, name) = object.keys(scope).next().unwrap();
+          assert_eq!(references(&name), 1);
+
+          let names = object.get_own_property_names(scope).to_vec(scope);
+          assert!(names.iter().all(|name| references(name) == 1));
+        })
+      };
+    });
+  }
+
+  #[test]
+  fn unscoped_panic() {
+    test::run_with_context(|guard| unsafe {
+      HandleScope::exec_with(guard, |scope| {
+        let result = std::panic::catch_unwind(|| super::unscoped(|| panic!("unscoped")));
+        assert!(result.is_err());
+
+        let scoped: value::Value = value::Object::new(scope).into();
+        assert_eq!(references(&scoped), 0);
+      })
+    });
+  }
+}
@@ -7,9 +7,10 @@
 //! # use chakracore as js;
 //! # let runtime = js::Runtime::new().unwrap();
 //! # let context = js::Context::new(&runtime).unwrap();
-//! # let guard = context.make_current().unwrap();
-//! let result = js::script::eval(&guard, "10 + 10").unwrap();
-//! assert_eq!(result.to_integer(&guard), 20);
+//! # context.exec_with(|guard| {
+//! let result = js::script::eval(guard, "10 + 10").unwrap();
+//! assert_eq!(result.to_integer(guard), 20);
+//! # }).unwrap();
 //! ```
 //!
 //! Another option is to parse the source code and execute it at a later time
@@ -19,31 +20,40 @@
 //! # use chakracore as js;
 //! # let runtime = js::Runtime::new().unwrap();
 //! # let context = js::Context::new(&runtime).unwrap();
-//! # let guard = context.make_current().unwrap();
-//! let add = js::script::parse(&guard, "10 + 10").unwrap();
-//! let result = add.call(&guard, &[]).unwrap();
-//! assert_eq!(result.to_integer(&guard), 20);
+//! # context.exec_with(|guard| {
+//! let add = js::script::parse(guard, "10 + 10").unwrap();
+//! let result = add.call(guard, &[]).unwrap();
+//! assert_eq!(result.to_integer(guard), 20);
+//! # }).unwrap();
 //! ```
 use crate::{util::jstry, value, ContextGuard, Result};
 use chakracore_sys::*;
 
 /// Evaluates code directly.
-pub fn eval(guard: &ContextGuard, code: &str) -> Result<value::Value> {
+pub fn eval<'ctx>(guard: &ContextGuard<'ctx>, code: &str) -> Result<value::Value<'ctx>> {
   eval_with_name(guard, "", code)
 }
 
 /// Evaluates code and associates it with a name.
-pub fn eval_with_name(guard: &ContextGuard, name: &str, code: &str) -> Result<value::Value> {
+pub fn eval_with_name<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  name: &str,
+  code: &str,
+) -> Result<value::Value<'ctx>> {
   process_code(guard, name, code, CodeAction::Execute)
 }
 
 /// Parses code and returns it as a function.
-pub fn parse(guard: &ContextGuard, code: &str) -> Result<value::Function> {
+pub fn parse<'ctx>(guard: &ContextGuard<'ctx>, code: &str) -> Result<value::Function<'ctx>> {
   parse_with_name(guard, "", code)
 }
 
 /// Parses code and associates it with a name, returns it as a function.
-pub fn parse_with_name(guard: &ContextGuard, name: &str, code: &str) -> Result<value::Function> {
+pub fn parse_with_name<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  name: &str,
+  code: &str,
+) -> Result<value::Function<'ctx>> {
   process_code(guard, name, code, CodeAction::Parse).map(|value| {
     value
       .into_function()
@@ -60,12 +70,12 @@ enum CodeAction {
 
 // TODO: Should `JavascriptExternalArrayBuffer` be supported? Lifetime issues.
 /// Either parses or executes a script.
-fn process_code(
-  guard: &ContextGuard,
+fn process_code<'ctx>(
+  guard: &ContextGuard<'ctx>,
   name: &str,
   code: &str,
   action: CodeAction,
-) -> Result<value::Value> {
+) -> Result<value::Value<'ctx>> {
   let name = value::String::new(guard, name);
   let buffer = value::String::new(guard, code);
 
@@ -0,0 +1,313 @@
+use super::{Error, Result};
+use crate::value::{self, Value};
+use crate::{ContextGuard, Property};
+use ::serde::de::{self, DeserializeSeed, IntoDeserializer, Unexpected, Visitor};
+use chakracore_sys::JsValueType;
+
+/// The largest integer that can be represented exactly by a number.
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// A deserializer that inspects JavaScript values.
+pub struct Deserializer<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  value: Value<'ctx>,
+}
+
+impl<'a, 'ctx> Deserializer<'a, 'ctx> {
+  /// Creates a new deserializer for a value.
+  pub fn new(guard: &'a ContextGuard<'ctx>, value: Value<'ctx>) -> Self {
+    Deserializer { guard, value }
+  }
+
+  /// Returns an error describing the value as unexpected.
+  fn invalid_type<'de, V: Visitor<'de>>(&self, visitor: &V) -> Error {
+    let guard = self.guard;
+    let string;
+    let unexpected = match self.value.get_type() {
+      JsValueType::Undefined => Unexpected::Other("undefined"),
+      JsValueType::Null => Unexpected::Other("null"),
+      JsValueType::Boolean => Unexpected::Bool(self.value.to_bool(guard)),
+      JsValueType::Number => Unexpected::Float(self.value.to_double(guard)),
+      JsValueType::String => {
+        string = self.value.to_string(guard);
+        Unexpected::Str(&string)
+      },
+      JsValueType::Array => Unexpected::Seq,
+      JsValueType::Object => Unexpected::Map,
+      JsValueType::Function => Unexpected::Other("function"),
+      JsValueType::Error => Unexpected::Other("error"),
+      JsValueType::Symbol => Unexpected::Other("symbol"),
+      JsValueType::ArrayBuffer => Unexpected::Other("array buffer"),
+      JsValueType::TypedArray => Unexpected::Other("typed array"),
+      JsValueType::DataView => Unexpected::Other("data view"),
+    };
+    de::Error::invalid_type(unexpected, visitor)
+  }
+}
+
+impl<'de, 'a, 'ctx> de::Deserializer<'de> for Deserializer<'a, 'ctx> {
+  type Error = Error;
+
+  fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    let guard = self.guard;
+    match self.value.get_type() {
+      JsValueType::Undefined | JsValueType::Null => visitor.visit_unit(),
+      JsValueType::Boolean => visitor.visit_bool(self.value.to_bool(guard)),
+      JsValueType::Number => {
+        let number = self.value.to_double(guard);
+        if number.fract() != 0.0 || number.abs() > MAX_SAFE_INTEGER {
+          visitor.visit_f64(number)
+        } else if number < 0.0 {
+          visitor.visit_i64(number as i64)
+        } else {
+          visitor.visit_u64(number as u64)
+        }
+      },
+      JsValueType::String => visitor.visit_string(self.value.to_string(guard)),
+      JsValueType::Array => {
+        let array = self.value.into_array().expect("converting array");
+        visitor.visit_seq(ArrayAccess::new(guard, array))
+      },
+      JsValueType::ArrayBuffer => {
+        let buffer = self
+          .value
+          .into_array_buffer()
+          .expect("converting array buffer");
+        visitor.visit_byte_buf(buffer.as_slice().to_vec())
+      },
+      JsValueType::Object => {
+        let object = self.value.into_object().expect("converting object");
+        visitor.visit_map(ObjectAccess::new(guard, object))
+      },
+      _ => Err(self.invalid_type(&visitor)),
+    }
+  }
+
+  /// Both `null` and `undefined` are represented as `None`.
+  fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    if self.value.is_null() || self.value.is_undefined() {
+      visitor.visit_none()
+    } else {
+      visitor.visit_some(self)
+    }
+  }
+
+  fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    if self.value.is_null() || self.value.is_undefined() {
+      visitor.visit_unit()
+    } else {
+      Err(self.invalid_type(&visitor))
+    }
+  }
+
+  fn deserialize_unit_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value> {
+    self.deserialize_unit(visitor)
+  }
+
+  fn deserialize_newtype_struct<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    visitor: V,
+  ) -> Result<V::Value> {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_byte_buf(visitor)
+  }
+
+  /// Bytes may be represented by an `ArrayBuffer`, or an array of numbers.
+  fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+    self.deserialize_any(visitor)
+  }
+
+  /// An enum is either represented by a string (unit variants) or an object
+  /// with a single property, keyed by the variant's name.
+  fn deserialize_enum<V: Visitor<'de>>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value> {
+    let guard = self.guard;
+    if self.value.is_string() {
+      let variant: de::value::StringDeserializer<Error> =
+        self.value.to_string(guard).into_deserializer();
+      return visitor.visit_enum(variant);
+    }
+
+    if let Some(object) = self.value.clone().into_object() {
+      let keys = object.keys(guard).collect::<Vec<_>>();
+      if self.value.get_type() == JsValueType::Object && keys.len() == 1 {
+        return visitor.visit_enum(EnumAccess {
+          guard,
+          variant: keys[0].0.to_string(guard),
+          value: object.get(guard, &keys[0].0),
+        });
+      }
+    }
+
+    Err(self.invalid_type(&visitor))
+  }
+
+  ::serde::forward_to_deserialize_any! {
+    bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string seq
+    tuple tuple_struct map struct identifier ignored_any
+  }
+}
+
+/// Provides access to the elements of an array.
+struct ArrayAccess<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  array: value::Array<'ctx>,
+  length: usize,
+  index: usize,
+}
+
+impl<'a, 'ctx> ArrayAccess<'a, 'ctx> {
+  fn new(guard: &'a ContextGuard<'ctx>, array: value::Array<'ctx>) -> Self {
+    ArrayAccess {
+      guard,
+      length: array.len(guard),
+      array,
+      index: 0,
+    }
+  }
+}
+
+impl<'de, 'a, 'ctx> de::SeqAccess<'de> for ArrayAccess<'a, 'ctx> {
+  type Error = Error;
+
+  fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+    if self.index >= self.length {
+      return Ok(None);
+    }
+
+    let index = self.index;
+    self.index += 1;
+
+    let value = self.array.get_index(self.guard, index as u32);
+    seed
+      .deserialize(Deserializer::new(self.guard, value))
+      .map(Some)
+      .map_err(|error| error.at_index(index))
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.length - self.index)
+  }
+}
+
+/// Provides access to the own enumerable properties of an object.
+struct ObjectAccess<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  object: value::Object<'ctx>,
+  keys: ::std::vec::IntoIter<String>,
+  value: Option<(String, Value<'ctx>)>,
+}
+
+impl<'a, 'ctx> ObjectAccess<'a, 'ctx> {
+  fn new(guard: &'a ContextGuard<'ctx>, object: value::Object<'ctx>) -> Self {
+    let keys = object
+      .keys(guard)
+      .map(|(key, _)| key.to_string(guard))
+      .collect::<Vec<_>>();
+
+    ObjectAccess {
+      guard,
+      object,
+      keys: keys.into_iter(),
+      value: None,
+    }
+  }
+}
+
+impl<'de, 'a, 'ctx> de::MapAccess<'de> for ObjectAccess<'a, 'ctx> {
+  type Error = Error;
+
+  fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+    let key = match self.keys.next() {
+      Some(key) => key,
+      None => return Ok(None),
+    };
+
+    let value = self.object.get(self.guard, Property::new(self.guard, &key));
+    self.value = Some((key.clone(), value));
+
+    let key: de::value::StringDeserializer<Error> = key.into_deserializer();
+    seed.deserialize(key).map(Some)
+  }
+
+  fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+    let (key, value) = self
+      .value
+      .take()
+      .expect("deserializing map key before value");
+    seed
+      .deserialize(Deserializer::new(self.guard, value))
+      .map_err(|error| error.at_key(&key))
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.keys.len())
+  }
+}
+
+/// Provides access to an externally tagged enum variant.
+struct EnumAccess<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  variant: String,
+  value: Value<'ctx>,
+}
+
+impl<'de, 'a, 'ctx> de::EnumAccess<'de> for EnumAccess<'a, 'ctx> {
+  type Error = Error;
+  type Variant = Self;
+
+  fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+    let variant: de::value::StrDeserializer<Error> = self.variant.as_str().into_deserializer();
+    let variant = seed.deserialize(variant)?;
+    Ok((variant, self))
+  }
+}
+
+impl<'a, 'ctx> EnumAccess<'a, 'ctx> {
+  /// Returns the variant's name and a deserializer for its value.
+  fn into_parts(self) -> (String, Deserializer<'a, 'ctx>) {
+    (self.variant, Deserializer::new(self.guard, self.value))
+  }
+}
+
+impl<'de, 'a, 'ctx> de::VariantAccess<'de> for EnumAccess<'a, 'ctx> {
+  type Error = Error;
+
+  fn unit_variant(self) -> Result<()> {
+    let (variant, deserializer) = self.into_parts();
+    de::Deserialize::deserialize(deserializer).map_err(|error: Error| error.at_key(&variant))
+  }
+
+  fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+    let (variant, deserializer) = self.into_parts();
+    seed
+      .deserialize(deserializer)
+      .map_err(|error| error.at_key(&variant))
+  }
+
+  fn tuple_variant<V: Visitor<'de>>(self, _length: usize, visitor: V) -> Result<V::Value> {
+    let (variant, deserializer) = self.into_parts();
+    de::Deserializer::deserialize_seq(deserializer, visitor).map_err(|error| error.at_key(&variant))
+  }
+
+  fn struct_variant<V: Visitor<'de>>(
+    self,
+    _fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value> {
+    let (variant, deserializer) = self.into_parts();
+    de::Deserializer::deserialize_map(deserializer, visitor).map_err(|error| error.at_key(&variant))
+  }
+}
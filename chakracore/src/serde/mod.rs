@@ -0,0 +1,281 @@
+//! Serialization of Rust types to, and from, JavaScript values.
+//!
+//! In contrast to `Value::to_json` and `Value::from_json`, values are created
+//! and inspected directly, without an intermediate JSON string. This also
+//! allows representations that JSON lacks:
+//!
+//! - `()` and unit structs are represented as `undefined`.
+//! - `None` is represented as `null`.
+//! - Byte arrays (e.g `serde_bytes`) are represented as an `ArrayBuffer`.
+//! - Enums are externally tagged, i.e `{ "Variant": value }`.
+//!
+//! Errors include the path of the value that failed, e.g `foo.bar[2]`.
+//!
+//! ```rust
+//! # use chakracore as js;
+//! # use serde_derive::{Deserialize, Serialize};
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Point {
+//!   x: i32,
+//!   y: i32,
+//! }
+//!
+//! # let runtime = js::Runtime::new().unwrap();
+//! # let context = js::Context::new(&runtime).unwrap();
+//! # context.exec_with(|guard| {
+//! let value = js::serde::to_value(guard, &Point { x: 1, y: 2 }).unwrap();
+//! assert_eq!(value.to_json(guard).unwrap(), r#"{"x":1,"y":2}"#);
+//!
+//! let value = js::script::eval(guard, "({ x: 3, y: 4 })").unwrap();
+//! let point: Point = js::serde::from_value(guard, &value).unwrap();
+//! assert_eq!(point, Point { x: 3, y: 4 });
+//! # }).unwrap();
+//! ```
+use crate::value::Value;
+use crate::ContextGuard;
+use std::error::Error as StdError;
+use std::fmt;
+
+pub use self::de::Deserializer;
+pub use self::ser::Serializer;
+
+mod de;
+mod ser;
+
+/// The result of a serialization.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Serializes a Rust type to a JavaScript value.
+pub fn to_value<'ctx, T>(guard: &ContextGuard<'ctx>, value: &T) -> Result<Value<'ctx>>
+where
+  T: ::serde::Serialize + ?Sized,
+{
+  value.serialize(Serializer::new(guard))
+}
+
+/// Deserializes a Rust type from a JavaScript value.
+pub fn from_value<'ctx, T>(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<T>
+where
+  T: ::serde::de::DeserializeOwned,
+{
+  T::deserialize(Deserializer::new(guard, value.clone()))
+}
+
+/// A segment of the path to a nested value.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+  Index(usize),
+  Key(String),
+}
+
+/// An error that occured during (de)serialization.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+  message: String,
+  /// The path, with the innermost segment first.
+  path: Vec<Segment>,
+}
+
+impl Error {
+  /// Creates a new error with a message.
+  fn new<T: fmt::Display>(message: T) -> Self {
+    Error {
+      message: message.to_string(),
+      path: Vec::new(),
+    }
+  }
+
+  /// Returns the error's message, excluding the path.
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+
+  /// Returns the path to the value that caused the error, e.g `foo[2]`. The
+  /// path is empty if it's the root value.
+  pub fn path(&self) -> String {
+    let mut path = String::new();
+    for segment in self.path.iter().rev() {
+      match segment {
+        Segment::Index(index) => path.push_str(&format!("[{}]", index)),
+        Segment::Key(key) if path.is_empty() => path.push_str(key),
+        Segment::Key(key) => path.push_str(&format!(".{}", key)),
+      }
+    }
+    path
+  }
+
+  /// Prepends an array index to the error's path.
+  fn at_index(mut self, index: usize) -> Self {
+    self.path.push(Segment::Index(index));
+    self
+  }
+
+  /// Prepends a property key to the error's path.
+  fn at_key(mut self, key: &str) -> Self {
+    self.path.push(Segment::Key(key.to_string()));
+    self
+  }
+}
+
+impl StdError for Error {}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.path.is_empty() {
+      write!(f, "{}", self.message)
+    } else {
+      write!(f, "{} at `{}`", self.message, self.path())
+    }
+  }
+}
+
+impl ::serde::ser::Error for Error {
+  fn custom<T: fmt::Display>(message: T) -> Self {
+    Error::new(message)
+  }
+}
+
+impl ::serde::de::Error for Error {
+  fn custom<T: fmt::Display>(message: T) -> Self {
+    Error::new(message)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{from_value, to_value};
+  use crate::{script, test, value};
+  use serde_derive::{Deserialize, Serialize};
+  use std::collections::BTreeMap;
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  enum Shape {
+    Empty,
+    Circle(f64),
+    Rectangle { width: u32, height: u32 },
+  }
+
+  #[derive(Serialize, Deserialize, Debug, PartialEq)]
+  struct Scene {
+    name: String,
+    shapes: Vec<Shape>,
+    tags: BTreeMap<String, Option<bool>>,
+    origin: (i32, i32),
+    #[serde(with = "bytes")]
+    data: Vec<u8>,
+  }
+
+  mod bytes {
+    use serde::de::{Deserializer, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+      serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+      struct BytesVisitor;
+
+      impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+          write!(f, "a byte array")
+        }
+
+        fn visit_byte_buf<E>(self, data: Vec<u8>) -> Result<Self::Value, E> {
+          Ok(data)
+        }
+      }
+
+      deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+  }
+
+  #[test]
+  fn round_trip() {
+    test::run_with_context(|guard| {
+      let mut tags = BTreeMap::new();
+      tags.insert("visible".to_string(), Some(true));
+      tags.insert("locked".to_string(), None);
+
+      let scene = Scene {
+        name: "scene".to_string(),
+        shapes: vec![
+          Shape::Empty,
+          Shape::Circle(1.5),
+          Shape::Rectangle {
+            width: 2,
+            height: 3,
+          },
+        ],
+        tags,
+        origin: (-1, 1),
+        data: vec![1, 2, 3],
+      };
+
+      let value = to_value(guard, &scene).unwrap();
+      let data = script::eval(guard, "(scene) => scene.data instanceof ArrayBuffer")
+        .unwrap()
+        .into_function()
+        .unwrap()
+        .call(guard, &[&value])
+        .unwrap();
+      assert!(data.to_bool(guard));
+      assert_eq!(from_value::<Scene>(guard, &value).unwrap(), scene);
+    });
+  }
+
+  #[test]
+  fn representation() {
+    test::run_with_context(|guard| {
+      let value = to_value(guard, &()).unwrap();
+      assert!(value.is_undefined());
+
+      let value = to_value(
+        guard,
+        &Shape::Rectangle {
+          width: 1,
+          height: 2,
+        },
+      )
+      .unwrap();
+      assert_eq!(
+        value.to_json(guard).unwrap(),
+        r#"{"Rectangle":{"width":1,"height":2}}"#
+      );
+
+      let value = value::Number::from_double(guard, 2.0).into();
+      assert_eq!(from_value::<u8>(guard, &value).unwrap(), 2);
+
+      let value = script::eval(
+        guard,
+        "Object.defineProperty({ Circle: 1 }, 'radius', { value: 2, enumerable: false })",
+      )
+      .unwrap();
+      assert_eq!(
+        from_value::<Shape>(guard, &value).unwrap(),
+        Shape::Circle(1.0)
+      );
+    });
+  }
+
+  #[test]
+  fn error_path() {
+    test::run_with_context(|guard| {
+      let value = script::eval(
+        guard,
+        "({ name: '', shapes: [{ Circle: 1 }, { Rectangle: { width: -1, height: 0 } }] })",
+      )
+      .unwrap();
+
+      let error = from_value::<Scene>(guard, &value).unwrap_err();
+      assert_eq!(error.path(), "shapes[1].Rectangle.width");
+      assert_eq!(
+        error.to_string(),
+        "invalid value: integer `-1`, expected u32 at `shapes[1].Rectangle.width`"
+      );
+    });
+  }
+}
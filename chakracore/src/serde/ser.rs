@@ -0,0 +1,360 @@
+use super::{Error, Result};
+use crate::value::{self, Value};
+use crate::{ContextGuard, Property};
+use ::serde::ser::{self, Serialize};
+
+/// A serializer that creates JavaScript values.
+pub struct Serializer<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+}
+
+impl<'a, 'ctx> Serializer<'a, 'ctx> {
+  /// Creates a new serializer for the active context.
+  pub fn new(guard: &'a ContextGuard<'ctx>) -> Self {
+    Serializer { guard }
+  }
+}
+
+impl<'a, 'ctx> ser::Serializer for Serializer<'a, 'ctx> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  type SerializeSeq = SerializeArray<'a, 'ctx>;
+  type SerializeTuple = SerializeArray<'a, 'ctx>;
+  type SerializeTupleStruct = SerializeArray<'a, 'ctx>;
+  type SerializeTupleVariant = SerializeVariant<'a, 'ctx, SerializeArray<'a, 'ctx>>;
+  type SerializeMap = SerializeObject<'a, 'ctx>;
+  type SerializeStruct = SerializeObject<'a, 'ctx>;
+  type SerializeStructVariant = SerializeVariant<'a, 'ctx, SerializeObject<'a, 'ctx>>;
+
+  fn serialize_bool(self, value: bool) -> Result<Value<'ctx>> {
+    Ok(value::Boolean::new(self.guard, value).into())
+  }
+
+  fn serialize_i8(self, value: i8) -> Result<Value<'ctx>> {
+    self.serialize_i32(value as i32)
+  }
+
+  fn serialize_i16(self, value: i16) -> Result<Value<'ctx>> {
+    self.serialize_i32(value as i32)
+  }
+
+  fn serialize_i32(self, value: i32) -> Result<Value<'ctx>> {
+    Ok(value::Number::new(self.guard, value).into())
+  }
+
+  /// Integers beyond 2^53 cannot be represented exactly.
+  fn serialize_i64(self, value: i64) -> Result<Value<'ctx>> {
+    self.serialize_f64(value as f64)
+  }
+
+  fn serialize_u8(self, value: u8) -> Result<Value<'ctx>> {
+    self.serialize_i32(value as i32)
+  }
+
+  fn serialize_u16(self, value: u16) -> Result<Value<'ctx>> {
+    self.serialize_i32(value as i32)
+  }
+
+  fn serialize_u32(self, value: u32) -> Result<Value<'ctx>> {
+    self.serialize_f64(value as f64)
+  }
+
+  /// Integers beyond 2^53 cannot be represented exactly.
+  fn serialize_u64(self, value: u64) -> Result<Value<'ctx>> {
+    self.serialize_f64(value as f64)
+  }
+
+  fn serialize_f32(self, value: f32) -> Result<Value<'ctx>> {
+    self.serialize_f64(value as f64)
+  }
+
+  fn serialize_f64(self, value: f64) -> Result<Value<'ctx>> {
+    Ok(value::Number::from_double(self.guard, value).into())
+  }
+
+  fn serialize_char(self, value: char) -> Result<Value<'ctx>> {
+    self.serialize_str(value.encode_utf8(&mut [0; 4]))
+  }
+
+  fn serialize_str(self, value: &str) -> Result<Value<'ctx>> {
+    Ok(value::String::new(self.guard, value).into())
+  }
+
+  fn serialize_bytes(self, value: &[u8]) -> Result<Value<'ctx>> {
+    Ok(value::ArrayBuffer::with_data(self.guard, value.to_vec()).into())
+  }
+
+  fn serialize_none(self) -> Result<Value<'ctx>> {
+    Ok(value::null(self.guard))
+  }
+
+  fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value<'ctx>> {
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<Value<'ctx>> {
+    Ok(value::undefined(self.guard))
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<Value<'ctx>> {
+    self.serialize_unit()
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+  ) -> Result<Value<'ctx>> {
+    self.serialize_str(variant)
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    value: &T,
+  ) -> Result<Value<'ctx>> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+    value: &T,
+  ) -> Result<Value<'ctx>> {
+    let value = value
+      .serialize(Serializer::new(self.guard))
+      .map_err(|error| error.at_key(variant))?;
+    Ok(wrap_variant(self.guard, variant, value))
+  }
+
+  fn serialize_seq(self, length: Option<usize>) -> Result<Self::SerializeSeq> {
+    Ok(SerializeArray {
+      guard: self.guard,
+      array: value::Array::new(self.guard, length.unwrap_or(0) as u32),
+      index: 0,
+    })
+  }
+
+  fn serialize_tuple(self, length: usize) -> Result<Self::SerializeTuple> {
+    self.serialize_seq(Some(length))
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    length: usize,
+  ) -> Result<Self::SerializeTupleStruct> {
+    self.serialize_seq(Some(length))
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+    length: usize,
+  ) -> Result<Self::SerializeTupleVariant> {
+    Ok(SerializeVariant {
+      guard: self.guard,
+      variant,
+      inner: Serializer::new(self.guard).serialize_seq(Some(length))?,
+    })
+  }
+
+  fn serialize_map(self, _length: Option<usize>) -> Result<Self::SerializeMap> {
+    Ok(SerializeObject {
+      guard: self.guard,
+      object: value::Object::new(self.guard),
+      key: None,
+    })
+  }
+
+  fn serialize_struct(self, _name: &'static str, length: usize) -> Result<Self::SerializeStruct> {
+    self.serialize_map(Some(length))
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _index: u32,
+    variant: &'static str,
+    length: usize,
+  ) -> Result<Self::SerializeStructVariant> {
+    Ok(SerializeVariant {
+      guard: self.guard,
+      variant,
+      inner: Serializer::new(self.guard).serialize_map(Some(length))?,
+    })
+  }
+}
+
+/// Serializes sequences and tuples as an `Array`.
+pub struct SerializeArray<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  array: value::Array<'ctx>,
+  index: u32,
+}
+
+impl<'a, 'ctx> ser::SerializeSeq for SerializeArray<'a, 'ctx> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+    let value = value
+      .serialize(Serializer::new(self.guard))
+      .map_err(|error| error.at_index(self.index as usize))?;
+    self.array.set_index(self.guard, self.index, value);
+    self.index += 1;
+    Ok(())
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    Ok(self.array.into())
+  }
+}
+
+impl<'a, 'ctx> ser::SerializeTuple for SerializeArray<'a, 'ctx> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+    ser::SerializeSeq::serialize_element(self, value)
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    ser::SerializeSeq::end(self)
+  }
+}
+
+impl<'a, 'ctx> ser::SerializeTupleStruct for SerializeArray<'a, 'ctx> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+    ser::SerializeSeq::serialize_element(self, value)
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    ser::SerializeSeq::end(self)
+  }
+}
+
+/// Serializes maps and structs as an `Object`.
+pub struct SerializeObject<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  object: value::Object<'ctx>,
+  key: Option<String>,
+}
+
+impl<'a, 'ctx> SerializeObject<'a, 'ctx> {
+  /// Sets a serialized property on the object.
+  fn set<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+    let value = value
+      .serialize(Serializer::new(self.guard))
+      .map_err(|error| error.at_key(key))?;
+    self
+      .object
+      .set(self.guard, Property::new(self.guard, key), value);
+    Ok(())
+  }
+}
+
+impl<'a, 'ctx> ser::SerializeMap for SerializeObject<'a, 'ctx> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  /// Keys must be serialized as strings or numbers.
+  fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+    let key = key.serialize(Serializer::new(self.guard))?;
+    self.key = Some(if key.is_string() || key.is_number() {
+      key.to_string(self.guard)
+    } else {
+      return Err(Error::new(format!(
+        "key must be a string or a number, found {:?}",
+        key.get_type()
+      )));
+    });
+    Ok(())
+  }
+
+  fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+    let key = self.key.take().expect("serializing map key before value");
+    self.set(&key, value)
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    Ok(self.object.into())
+  }
+}
+
+impl<'a, 'ctx> ser::SerializeStruct for SerializeObject<'a, 'ctx> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+    self.set(key, value)
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    Ok(self.object.into())
+  }
+}
+
+/// Serializes an enum variant's fields, wrapped in an object keyed by the
+/// variant's name.
+pub struct SerializeVariant<'a, 'ctx, S> {
+  guard: &'a ContextGuard<'ctx>,
+  variant: &'static str,
+  inner: S,
+}
+
+impl<'a, 'ctx> ser::SerializeTupleVariant for SerializeVariant<'a, 'ctx, SerializeArray<'a, 'ctx>> {
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+    let variant = self.variant;
+    ser::SerializeSeq::serialize_element(&mut self.inner, value)
+      .map_err(|error| error.at_key(variant))
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    let value = ser::SerializeSeq::end(self.inner)?;
+    Ok(wrap_variant(self.guard, self.variant, value))
+  }
+}
+
+impl<'a, 'ctx> ser::SerializeStructVariant
+  for SerializeVariant<'a, 'ctx, SerializeObject<'a, 'ctx>>
+{
+  type Ok = Value<'ctx>;
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+    let variant = self.variant;
+    self
+      .inner
+      .set(key, value)
+      .map_err(|error| error.at_key(variant))
+  }
+
+  fn end(self) -> Result<Value<'ctx>> {
+    let value = ser::SerializeStruct::end(self.inner)?;
+    Ok(wrap_variant(self.guard, self.variant, value))
+  }
+}
+
+/// Creates an object with a single property, representing an enum variant.
+fn wrap_variant<'ctx, V: AsRef<Value<'ctx>>>(
+  guard: &ContextGuard<'ctx>,
+  variant: &str,
+  value: V,
+) -> Value<'ctx> {
+  let object = value::Object::new(guard);
+  object.set(guard, Property::new(guard, variant), value);
+  object.into()
+}
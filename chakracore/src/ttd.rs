@@ -0,0 +1,131 @@
+//! Time-travel debugging streams, backed by files in a local directory.
+//!
+//! The runtime identifies each resource by a URI (the directory) and a name.
+//! A stream handle is a boxed `File`, released once the stream is closed.
+//!
+//! The URI is passed as raw bytes, so on Unix any path can be used. Elsewhere
+//! it must be valid UTF-8.
+use crate::Result;
+use chakracore_sys::*;
+use libc::c_char;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::slice;
+
+/// Converts a directory to the URI passed to the runtime.
+#[cfg(unix)]
+pub(crate) fn to_uri(directory: &Path) -> Result<Vec<u8>> {
+  use std::os::unix::ffi::OsStrExt;
+  Ok(directory.as_os_str().as_bytes().to_vec())
+}
+
+/// Converts a directory to the URI passed to the runtime.
+#[cfg(not(unix))]
+pub(crate) fn to_uri(directory: &Path) -> Result<Vec<u8>> {
+  use std::io::{Error, ErrorKind};
+  directory
+    .to_str()
+    .map(|directory| directory.as_bytes().to_vec())
+    .ok_or_else(|| {
+      crate::Error::Io(Error::new(
+        ErrorKind::InvalidInput,
+        "path is not valid UTF-8",
+      ))
+    })
+}
+
+/// Converts a URI, or a resource name, received from the runtime to a path.
+#[cfg(unix)]
+fn from_uri(uri: &[u8]) -> Option<PathBuf> {
+  use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+  Some(PathBuf::from(OsStr::from_bytes(uri)))
+}
+
+/// Converts a URI, or a resource name, received from the runtime to a path.
+#[cfg(not(unix))]
+fn from_uri(uri: &[u8]) -> Option<PathBuf> {
+  std::str::from_utf8(uri).ok().map(PathBuf::from)
+}
+
+/// Opens a stream to a resource in a directory.
+pub(crate) unsafe extern "system" fn open_stream(
+  uri_length: usize,
+  uri: *const c_char,
+  name_length: usize,
+  name: *const c_char,
+  read: bool,
+  write: bool,
+) -> JsTTDStreamHandle {
+  let uri = slice::from_raw_parts(uri as *const u8, uri_length);
+  let name = slice::from_raw_parts(name as *const u8, name_length);
+
+  let path = match (from_uri(uri), from_uri(name)) {
+    (Some(uri), Some(name)) => uri.join(name),
+    _ => return JsTTDStreamHandle::new(),
+  };
+
+  if write {
+    if let Some(directory) = path.parent() {
+      if fs::create_dir_all(directory).is_err() {
+        return JsTTDStreamHandle::new();
+      }
+    }
+  }
+
+  OpenOptions::new()
+    .read(read)
+    .write(write)
+    .create(write)
+    .truncate(write)
+    .open(path)
+    .map(|file| JsTTDStreamHandle(Box::into_raw(Box::new(file)) as *mut _))
+    .unwrap_or_else(|_| JsTTDStreamHandle::new())
+}
+
+/// Reads bytes from a stream, until the buffer is full or the end is reached.
+pub(crate) unsafe extern "system" fn read_stream(
+  handle: JsTTDStreamHandle,
+  buffer: *mut u8,
+  size: usize,
+  read_count: *mut usize,
+) -> bool {
+  let file = &mut *(handle.0 as *mut File);
+  let buffer = slice::from_raw_parts_mut(buffer, size);
+
+  let mut total = 0;
+  while total < size {
+    match file.read(&mut buffer[total..]) {
+      Ok(0) => break,
+      Ok(count) => total += count,
+      Err(_) => return false,
+    }
+  }
+
+  *read_count = total;
+  true
+}
+
+/// Writes bytes to a stream.
+pub(crate) unsafe extern "system" fn write_stream(
+  handle: JsTTDStreamHandle,
+  data: *const u8,
+  size: usize,
+  written_count: *mut usize,
+) -> bool {
+  let file = &mut *(handle.0 as *mut File);
+  let data = slice::from_raw_parts(data, size);
+
+  let result = file.write_all(data).is_ok();
+  *written_count = if result { size } else { 0 };
+  result
+}
+
+/// Flushes and closes a stream.
+pub(crate) unsafe extern "system" fn close_stream(handle: JsTTDStreamHandle, _: bool, write: bool) {
+  let mut file = Box::from_raw(handle.0 as *mut File);
+  if write {
+    // There is no way to report the error to the runtime
+    let _ = file.flush();
+  }
+}
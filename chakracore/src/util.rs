@@ -1,6 +1,11 @@
-use crate::{value, Context, ContextGuard, Error, Result};
+use crate::{scope, value, Context, ContextGuard, Error, Result};
 use chakracore_sys::*;
-use std::ptr;
+use std::{cell::RefCell, ptr};
+
+thread_local! {
+  /// The last exception cleared by `jstry`, while `rethrow` is active.
+  static CAUGHT: RefCell<Option<Option<value::Value<'static>>>> = RefCell::new(None);
+}
 
 /// Type for `JsCreateString` & `JsCreatePropertyId`
 pub type StringCall = unsafe extern "system" fn(JsRef, *mut i8, usize, *mut usize) -> JsErrorCode;
@@ -26,6 +31,55 @@ pub fn to_string_impl(reference: JsRef, callback: StringCall) -> Result<String>
   }
 }
 
+/// Throws an exception and returns it as an error, exactly as if it had been
+/// thrown by a script.
+pub fn throw<'ctx>(_guard: &ContextGuard<'ctx>, exception: &value::Value<'ctx>) -> Error {
+  jsassert!(unsafe { JsSetException(exception.as_raw()) });
+  match jstry(JsErrorCode::ScriptException) {
+    Err(error) => error,
+    Ok(_) => unreachable!(),
+  }
+}
+
+/// Executes a closure that is part of a native callback, converting its error
+/// to an exception.
+///
+/// A script exception is returned as the value that was originally thrown,
+/// instead of a new error with its message. Other errors are converted by
+/// `convert`.
+pub fn rethrow<'ctx, T, F, C>(
+  _guard: &ContextGuard<'ctx>,
+  callback: F,
+  convert: C,
+) -> std::result::Result<T, value::Value<'ctx>>
+where
+  F: FnOnce() -> Result<T>,
+  C: FnOnce(Error) -> value::Value<'ctx>,
+{
+  /// Restores the enclosing capture, even if the callback panics.
+  struct Restore(Option<Option<value::Value<'static>>>);
+
+  impl Drop for Restore {
+    fn drop(&mut self) {
+      let previous = self.0.take();
+      CAUGHT.with(|caught| *caught.borrow_mut() = previous);
+    }
+  }
+
+  let _restore = Restore(CAUGHT.with(|caught| caught.replace(Some(None))));
+  let result = callback();
+  let exception = CAUGHT
+    .with(|caught| caught.replace(Some(None)))
+    .and_then(|exception| exception);
+
+  result.map_err(|error| match (error, exception) {
+    (Error::ScriptException(_), Some(exception)) => unsafe {
+      value::Value::from_raw(exception.as_raw())
+    },
+    (error, _) => convert(error),
+  })
+}
+
 /// Decrements a reference counter and asserts its value.
 pub fn release_reference(reference: JsRef) {
   let mut count = 0;
@@ -33,16 +87,6 @@ pub fn release_reference(reference: JsRef) {
   debug_assert!(count < libc::c_uint::max_value());
 }
 
-/// Returns a script result as a `Function`.
-///
-/// This is useful for functionality that the underlying JSRT API does not
-/// provide, such as JSON methods, or `RegExp` constructor.
-pub fn jsfunc(guard: &ContextGuard, function: &str) -> Option<value::Function> {
-  crate::script::eval(guard, function)
-    .ok()
-    .and_then(|val| val.into_function())
-}
-
 /// Converts a JSRT error code to a result.
 pub fn jstry(code: JsErrorCode) -> Result<()> {
   match code {
@@ -52,6 +96,7 @@ pub fn jstry(code: JsErrorCode) -> Result<()> {
         // TODO: Use an exception with stack trace.
         let exception = get_and_clear_exception(guard);
         let message = exception.to_string(guard);
+        capture(&exception);
 
         Err(if code == JsErrorCode::ScriptException {
           Error::ScriptException(message)
@@ -65,10 +110,21 @@ pub fn jstry(code: JsErrorCode) -> Result<()> {
   }
 }
 
+/// Stores a cleared exception for an active `rethrow`.
+fn capture(exception: &value::Value) {
+  CAUGHT.with(|caught| {
+    if let Some(slot) = caught.borrow_mut().as_mut() {
+      *slot = Some(scope::unscoped(|| unsafe {
+        value::Value::from_raw(exception.as_raw())
+      }));
+    }
+  });
+}
+
 /// Retrieves and clears any exception thrown during compilation or execution.
 ///
 /// The runtime is set to a disabled state whenever an exception is thrown.
-fn get_and_clear_exception(_guard: &ContextGuard) -> value::Value {
+fn get_and_clear_exception<'ctx>(_guard: &ContextGuard<'ctx>) -> value::Value<'ctx> {
   let mut exception = JsValueRef::new();
   unsafe {
     jsassert!(JsGetAndClearException(&mut exception));
@@ -1,27 +1,30 @@
-use crate::value::{Object, Value};
-use crate::{context::ContextGuard, Property};
+use crate::convert::ConversionError;
+use crate::intrinsics::{self, Intrinsic};
+use crate::scope::{self, Root};
+use crate::value::{Number, Object, Value};
+use crate::{context::ContextGuard, Error, Property, Result};
 use boolinator::Boolinator;
 use chakracore_sys::*;
 use libc::c_void;
 use std::{mem, ptr, slice};
 
 /// A JavaScript array.
-pub struct Array(JsValueRef);
+pub struct Array<'ctx>(JsValueRef, Root<'ctx>);
 
 /// An iterator for a JavaScript array.
-pub struct ArrayIter<'a> {
-  guard: &'a ContextGuard<'a>,
-  array: &'a Array,
+pub struct ArrayIter<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  array: Array<'ctx>,
   index: u32,
   size: u32,
 }
 
 /// A JavaScript array buffer.
-pub struct ArrayBuffer(JsValueRef);
+pub struct ArrayBuffer<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Array {
+impl<'ctx> Array<'ctx> {
   /// Creates a new array with a specified length.
-  pub fn new(_guard: &ContextGuard, length: u32) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>, length: u32) -> Self {
     let mut reference = JsValueRef::new();
     unsafe {
       jsassert!(JsCreateArray(length, &mut reference));
@@ -30,27 +33,189 @@ impl Array {
   }
 
   /// Returns the length of the array.
-  pub fn len(&self, guard: &ContextGuard) -> usize {
+  pub fn len(&self, guard: &ContextGuard<'ctx>) -> usize {
     let length = Property::new(&guard, "length");
-    self.get(guard, &length).to_integer(&guard) as usize
+    // Lengths may exceed `i32::max_value()`
+    self.get(guard, &length).to_double(&guard) as usize
+  }
+
+  /// Creates a new array from the elements of an iterator.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the iterator yields more elements than an array can hold
+  /// (i.e 2^32 - 1).
+  pub fn from_iter<I>(guard: &ContextGuard<'ctx>, elements: I) -> Self
+  where
+    I: IntoIterator,
+    I::Item: AsRef<Value<'ctx>>,
+  {
+    let array = Self::new(guard, 0);
+    array
+      .extend(guard, elements)
+      .expect("array length exceeds its maximum");
+    array
+  }
+
+  /// Returns the element at an index, or an error if the index is out of
+  /// bounds.
+  pub fn at(&self, guard: &ContextGuard<'ctx>, index: usize) -> Result<Value<'ctx>> {
+    self.check_bounds(guard, index, false)?;
+    Ok(self.get_index(guard, index as u32))
+  }
+
+  /// Replaces the element at an index, or returns an error if the index is
+  /// out of bounds.
+  pub fn set_at<V: AsRef<Value<'ctx>>>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    index: usize,
+    value: V,
+  ) -> Result<()> {
+    self.check_bounds(guard, index, false)?;
+    self.set_index(guard, index as u32, value);
+    Ok(())
+  }
+
+  /// Appends an element to the end of the array.
+  pub fn push<V: AsRef<Value<'ctx>>>(&self, guard: &ContextGuard<'ctx>, value: V) {
+    let length = self.len(guard);
+    self.set_index(guard, length as u32, value);
+  }
+
+  /// Removes the last element of the array and returns it, or `None` if the
+  /// array is empty.
+  pub fn pop(&self, guard: &ContextGuard<'ctx>) -> Option<Value<'ctx>> {
+    let length = self.len(guard);
+    if length == 0 {
+      return None;
+    }
+
+    let value = self.get_index(guard, length as u32 - 1);
+    let length_property = Property::new(guard, "length");
+    self.set(
+      guard,
+      length_property,
+      Number::from_double(guard, (length - 1) as f64),
+    );
+    Some(value)
+  }
+
+  /// Inserts an element at an index, shifting all elements after it.
+  ///
+  /// An index equal to the array's length appends the element.
+  pub fn insert<V: AsRef<Value<'ctx>>>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    index: usize,
+    value: V,
+  ) -> Result<()> {
+    self.check_bounds(guard, index, true)?;
+    self.splice(guard, index, 0, Some(value)).map(|_| ())
+  }
+
+  /// Removes the element at an index and returns it, shifting all elements
+  /// after it.
+  pub fn remove(&self, guard: &ContextGuard<'ctx>, index: usize) -> Result<Value<'ctx>> {
+    self.check_bounds(guard, index, false)?;
+    let removed = self.splice(guard, index, 1, None::<Value>)?;
+    Ok(removed.get_index(guard, 0))
+  }
+
+  /// Removes `count` elements starting at `start`, inserts `elements` in
+  /// their place, and returns an array of the removed elements.
+  ///
+  /// This is equivalent to `Array.prototype.splice()`, so out of range
+  /// arguments are clamped to the array's bounds. An error is returned if
+  /// the array's `Symbol.species` constructor does not create an array.
+  pub fn splice<I>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    start: usize,
+    count: usize,
+    elements: I,
+  ) -> Result<Array<'ctx>>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<Value<'ctx>>,
+  {
+    let start = Number::from_double(guard, start as f64).into();
+    let count = Number::from_double(guard, count as f64).into();
+    // The elements are stored on the heap, so they must be reference counted
+    let elements = scope::unscoped(|| {
+      elements
+        .into_iter()
+        .map(|element| element.as_ref().clone())
+        .collect::<Vec<_>>()
+    });
+
+    let mut arguments = vec![&start, &count];
+    arguments.extend(elements.iter());
+
+    let removed = intrinsics::call(guard, Intrinsic::ArraySplice, self, &arguments)?;
+    removed
+      .clone()
+      .into_array()
+      .ok_or_else(|| Error::Conversion(ConversionError::new(JsValueType::Array, &removed)))
+  }
+
+  /// Appends the elements of an iterator to the end of the array.
+  ///
+  /// An error is returned once the array's length would exceed its maximum
+  /// (i.e 2^32 - 1), in which case the preceding elements remain appended.
+  pub fn extend<I>(&self, guard: &ContextGuard<'ctx>, elements: I) -> Result<()>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<Value<'ctx>>,
+  {
+    let length = self.len(guard) as u64;
+    for (index, element) in (length..).zip(elements) {
+      if index >= u64::from(u32::max_value()) {
+        let index = index as usize;
+        return Err(Error::IndexOutOfBounds {
+          index,
+          length: index,
+        });
+      }
+      self.set_index(guard, index as u32, element);
+    }
+    Ok(())
+  }
+
+  /// Returns the array's elements as a vector.
+  ///
+  /// The elements are reference counted, even within a `HandleScope`.
+  pub fn to_vec(&self, guard: &ContextGuard<'ctx>) -> Vec<Value<'ctx>> {
+    scope::unscoped(|| self.iter(guard).collect())
   }
 
   /// Returns an iterator for the array.
-  pub fn iter<'a>(&'a self, guard: &'a ContextGuard) -> ArrayIter<'a> {
+  pub fn iter<'a>(&self, guard: &'a ContextGuard<'ctx>) -> ArrayIter<'a, 'ctx> {
     ArrayIter {
       guard,
       size: self.len(guard) as u32,
-      array: self,
+      array: self.clone(),
       index: 0,
     }
   }
 
   is_same!(Array, "Returns true if the value is an `Array`.");
+
+  /// Returns an error if an index is out of bounds. The index may equal the
+  /// array's length if `inclusive` is true.
+  fn check_bounds(&self, guard: &ContextGuard<'ctx>, index: usize, inclusive: bool) -> Result<()> {
+    let length = self.len(guard);
+    if index < length || (inclusive && index == length) {
+      Ok(())
+    } else {
+      Err(Error::IndexOutOfBounds { index, length })
+    }
+  }
 }
 
-impl ArrayBuffer {
+impl<'ctx> ArrayBuffer<'ctx> {
   /// Creates a new array buffer with a specified size.
-  pub fn new(_guard: &ContextGuard, size: u32) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>, size: u32) -> Self {
     let mut reference = JsValueRef::new();
     unsafe {
       jsassert!(JsCreateArrayBuffer(size, &mut reference));
@@ -59,7 +224,7 @@ impl ArrayBuffer {
   }
 
   /// Creates a new array buffer, owning the data.
-  pub fn with_data<T: Sized>(_guard: &ContextGuard, data: Vec<T>) -> Self {
+  pub fn with_data<T: Sized>(_guard: &ContextGuard<'ctx>, data: Vec<T>) -> Self {
     let mut data = Box::new(data);
     let base = data.as_mut_ptr() as *mut _;
     let size = data.len() * mem::size_of::<T>();
@@ -82,7 +247,7 @@ impl ArrayBuffer {
   /// This is unsafe because the object does not take ownership of the
   /// resource. Therefore the data may become a dangling pointer. The caller is
   /// responsible for keeping the reference alive.
-  pub unsafe fn from_slice<T: Sized>(_guard: &ContextGuard, data: &mut [T]) -> Self {
+  pub unsafe fn from_slice<T: Sized>(_guard: &ContextGuard<'ctx>, data: &mut [T]) -> Self {
     let base = data.as_mut_ptr() as *mut _;
     let size = (data.len() * mem::size_of::<T>()) as _;
 
@@ -121,8 +286,8 @@ impl ArrayBuffer {
   }
 }
 
-impl<'a> Iterator for ArrayIter<'a> {
-  type Item = Value;
+impl<'a, 'ctx> Iterator for ArrayIter<'a, 'ctx> {
+  type Item = Value<'ctx>;
 
   /// Returns the next element in the array.
   fn next(&mut self) -> Option<Self::Item> {
@@ -142,7 +307,8 @@ subtype!(ArrayBuffer, Value);
 
 #[cfg(test)]
 mod tests {
-  use crate::{test, value};
+  use crate::{script, test, value, Error, Property};
+  use matches::assert_matches;
 
   #[test]
   fn iterator() {
@@ -164,6 +330,90 @@ mod tests {
     });
   }
 
+  #[test]
+  fn manipulation() {
+    test::run_with_context(|guard| {
+      let numbers = (1..=3).map(|n| value::Number::new(guard, n));
+      let array = value::Array::from_iter(guard, numbers);
+      let to_vec = |array| {
+        value::Array::to_vec(array, guard)
+          .iter()
+          .map(|value| value.to_integer(guard))
+          .collect::<Vec<_>>()
+      };
+
+      array.push(guard, value::Number::new(guard, 4));
+      array
+        .extend(guard, vec![value::Number::new(guard, 5)])
+        .unwrap();
+      assert_eq!(to_vec(&array), [1, 2, 3, 4, 5]);
+
+      assert_eq!(array.pop(guard).unwrap().to_integer(guard), 5);
+      assert_eq!(array.remove(guard, 0).unwrap().to_integer(guard), 1);
+      array
+        .insert(guard, 3, value::Number::new(guard, 0))
+        .unwrap();
+      array
+        .set_at(guard, 0, value::Number::new(guard, 9))
+        .unwrap();
+      assert_eq!(to_vec(&array), [9, 3, 4, 0]);
+
+      let removed = array
+        .splice(guard, 1, 2, vec![value::Number::new(guard, 7)])
+        .unwrap();
+      assert_eq!(to_vec(&removed), [3, 4]);
+      assert_eq!(to_vec(&array), [9, 7, 0]);
+
+      assert_matches!(
+        array.at(guard, 3),
+        Err(Error::IndexOutOfBounds {
+          index: 3,
+          length: 3
+        })
+      );
+      assert!(array.remove(guard, 3).is_err());
+      assert!(array.insert(guard, 4, value::null(guard)).is_err());
+
+      let empty = value::Array::new(guard, 0);
+      assert!(empty.pop(guard).is_none());
+
+      let sparse = value::Array::new(guard, u32::max_value());
+      assert!(sparse.pop(guard).unwrap().is_undefined());
+      assert_eq!(sparse.len(guard), u32::max_value() as usize - 1);
+      assert!(sparse
+        .extend(guard, vec![value::null(guard), value::null(guard)])
+        .is_err());
+      assert_eq!(sparse.len(guard), u32::max_value() as usize);
+
+      let large = value::Array::new(guard, 0);
+      let index = 1 << 31;
+      large.set_index(guard, index, value::Number::new(guard, 1));
+      large.set_index(guard, index + 1, value::Number::new(guard, 2));
+      assert_eq!(large.len(guard), index as usize + 2);
+      assert_eq!(
+        large.at(guard, index as usize).unwrap().to_integer(guard),
+        1
+      );
+      assert_eq!(large.pop(guard).unwrap().to_integer(guard), 2);
+      assert_eq!(large.pop(guard).unwrap().to_integer(guard), 1);
+      assert!(!large.has_index(guard, index));
+      let negative = large.get(guard, Property::new(guard, "-2147483648"));
+      assert!(negative.is_undefined());
+
+      let species = script::eval(
+        guard,
+        "var array = [1, 2]; array.constructor = { [Symbol.species]: Object }; array",
+      )
+      .unwrap()
+      .into_array()
+      .unwrap();
+      assert_matches!(
+        species.splice(guard, 0, 1, None::<value::Value>).err(),
+        Some(Error::Conversion(_))
+      );
+    });
+  }
+
   #[test]
   fn buffer_storage() {
     test::run_with_context(|guard| {
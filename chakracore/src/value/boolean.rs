@@ -1,12 +1,13 @@
+use crate::scope::Root;
 use crate::{value::Value, ContextGuard};
 use chakracore_sys::*;
 
 /// A JavaScript boolean.
-pub struct Boolean(JsValueRef);
+pub struct Boolean<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Boolean {
+impl<'ctx> Boolean<'ctx> {
   /// Creates a new boolean.
-  pub fn new(_guard: &ContextGuard, boolean: bool) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>, boolean: bool) -> Self {
     let mut value = JsValueRef::new();
     unsafe {
       jsassert!(JsBoolToBoolean(boolean, &mut value));
@@ -0,0 +1,162 @@
+use crate::intrinsics::{self, Intrinsic};
+use crate::scope::Root;
+use crate::value::{Number, Object, Value};
+use crate::{ContextGuard, Result};
+use chakracore_sys::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A JavaScript `Date`.
+///
+/// A date is represented by the number of milliseconds since the Unix epoch,
+/// or `NaN` if the date is invalid.
+pub struct Date<'ctx>(JsValueRef, Root<'ctx>);
+
+impl<'ctx> Date<'ctx> {
+  /// Creates a new date representing a point in time.
+  ///
+  /// The time is truncated to millisecond precision.
+  pub fn new(guard: &ContextGuard<'ctx>, time: SystemTime) -> Self {
+    let millis = match time.duration_since(UNIX_EPOCH) {
+      Ok(duration) => duration.as_millis() as f64,
+      Err(error) => -(error.duration().as_millis() as f64),
+    };
+    Self::from_millis(guard, millis)
+  }
+
+  /// Creates a new date from the number of milliseconds since the Unix epoch.
+  pub fn from_millis(guard: &ContextGuard<'ctx>, millis: f64) -> Self {
+    let millis = Number::from_double(guard, millis).into();
+    let date = intrinsics::construct(guard, Intrinsic::Date, &[&millis]).expect("creating date");
+    unsafe { Self::from_raw(date.as_raw()) }
+  }
+
+  /// Returns the number of milliseconds since the Unix epoch, or `NaN` if
+  /// the date is invalid.
+  pub fn millis(&self, guard: &ContextGuard<'ctx>) -> Result<f64> {
+    intrinsics::call(guard, Intrinsic::DateGetTime, self, &[]).map(|time| time.to_double(guard))
+  }
+
+  /// Returns the point in time represented by the date, or `None` if the
+  /// date is invalid or cannot be represented by the platform (e.g before
+  /// 1601 on Windows).
+  pub fn to_system_time(&self, guard: &ContextGuard<'ctx>) -> Result<Option<SystemTime>> {
+    self.millis(guard).map(|millis| {
+      if millis.is_nan() {
+        None
+      } else if millis >= 0.0 {
+        UNIX_EPOCH.checked_add(Duration::from_millis(millis as u64))
+      } else {
+        UNIX_EPOCH.checked_sub(Duration::from_millis(-millis as u64))
+      }
+    })
+  }
+
+  /// Creates a new date from a `chrono` date and time.
+  #[cfg(feature = "chrono")]
+  pub fn from_chrono<Tz: chrono::TimeZone>(
+    guard: &ContextGuard<'ctx>,
+    time: &chrono::DateTime<Tz>,
+  ) -> Self {
+    Self::from_millis(guard, time.timestamp_millis() as f64)
+  }
+
+  /// Returns the date as a `chrono` date and time, or `None` if the date is
+  /// invalid.
+  #[cfg(feature = "chrono")]
+  pub fn to_chrono(
+    &self,
+    guard: &ContextGuard<'ctx>,
+  ) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+    use chrono::TimeZone;
+    self.millis(guard).map(|millis| {
+      if millis.is_nan() {
+        None
+      } else {
+        chrono::Utc.timestamp_millis_opt(millis as i64).single()
+      }
+    })
+  }
+
+  /// Returns true if the value is a `Date`, including a `Date` of another
+  /// context or one whose prototype has been changed.
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
+    intrinsics::is_instance(value.as_ref(), Intrinsic::Date, Intrinsic::DateGetTime)
+  }
+}
+
+reference!(Date);
+inherit!(Date, Object);
+subtype!(Date, Value);
+
+#[cfg(test)]
+mod tests {
+  use crate::{script, test, value};
+  use std::time::{Duration, UNIX_EPOCH};
+
+  #[test]
+  fn system_time() {
+    test::run_with_context(|guard| {
+      let time = UNIX_EPOCH + Duration::from_millis(1_500_000_000_123);
+      let date = value::Date::new(guard, time);
+      assert_eq!(date.millis(guard).unwrap(), 1_500_000_000_123.0);
+      assert_eq!(date.to_system_time(guard).unwrap(), Some(time));
+
+      let before = UNIX_EPOCH - Duration::from_millis(1000);
+      let date = value::Date::new(guard, before);
+      assert_eq!(date.to_system_time(guard).unwrap(), Some(before));
+
+      let date = script::eval(guard, "new Date('2000-01-01T00:00:00Z')").unwrap();
+      assert!(date.is_date());
+      let date = date.into_date().unwrap();
+      assert_eq!(date.millis(guard).unwrap(), 946_684_800_000.0);
+
+      let invalid = value::Date::from_millis(guard, f64::NAN);
+      assert_eq!(invalid.to_system_time(guard).unwrap(), None);
+      assert!(!value::Object::new(guard).is_date());
+    });
+  }
+
+  #[test]
+  fn detection() {
+    test::run_with_context(|guard| {
+      let date = script::eval(guard, "new Date(0)").unwrap();
+      let prototype = script::eval(guard, "Date.prototype").unwrap();
+      let inherited = script::eval(guard, "Object.create(Date.prototype)").unwrap();
+      let revoked = script::eval(
+        guard,
+        "var proxy = Proxy.revocable(new Date(), {}); proxy.revoke(); proxy.proxy",
+      )
+      .unwrap();
+
+      script::eval(
+        guard,
+        "Object.defineProperty(Date, Symbol.hasInstance, { value: () => true })",
+      )
+      .unwrap();
+      assert!(date.is_date());
+      assert!(!prototype.is_date());
+      assert!(!inherited.is_date());
+      assert!(!revoked.is_date());
+      assert!(!value::Object::new(guard).is_date());
+
+      script::eval(
+        guard,
+        "Object.defineProperty(Date, Symbol.hasInstance, { value() { throw 1; } })",
+      )
+      .unwrap();
+      assert!(date.is_date());
+      assert!(!value::Object::new(guard).is_date());
+    });
+  }
+
+  #[test]
+  #[cfg(feature = "chrono")]
+  fn chrono() {
+    use chrono::TimeZone;
+    test::run_with_context(|guard| {
+      let time = chrono::Utc.timestamp_millis_opt(946_684_800_000).unwrap();
+      let date = value::Date::from_chrono(guard, &time);
+      assert_eq!(date.to_chrono(guard).unwrap(), Some(time));
+    });
+  }
+}
@@ -1,3 +1,4 @@
+use crate::scope::Root;
 use crate::value::{Object, Value};
 use crate::{context::ContextGuard, Property};
 use chakracore_sys::*;
@@ -5,16 +6,16 @@ use chakracore_sys::*;
 macro_rules! ctor {
   ($name:ident, $errtype:ident, $doc:expr) => {
     #[doc=$doc]
-    pub fn $name(guard: &ContextGuard, message: &str) -> Self {
+    pub fn $name(guard: &ContextGuard<'ctx>, message: &str) -> Self {
       create_error(guard, message, $errtype)
     }
   };
 }
 
 /// A JavaScript error.
-pub struct Error(JsValueRef);
+pub struct Error<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Error {
+impl<'ctx> Error<'ctx> {
   ctor!(new, JsCreateError, "Creates a new error.");
   ctor!(range_error, JsCreateRangeError, "Creates a new range error.");
   ctor!(reference_error, JsCreateReferenceError, "Creates a new reference error.");
@@ -23,7 +24,7 @@ impl Error {
   ctor!(uri_error, JsCreateURIError, "Creates a new URI error.");
 
   /// Returns the error's message.
-  pub fn message(&self, guard: &ContextGuard) -> String {
+  pub fn message(&self, guard: &ContextGuard<'ctx>) -> String {
     self
       .get(guard, Property::new(guard, "message"))
       .to_string(guard)
@@ -36,7 +37,7 @@ impl Error {
 type ErrorCall = unsafe extern "system" fn(JsValueRef, *mut JsValueRef) -> JsErrorCode;
 
 /// Creates an error object from a specified API.
-fn create_error(guard: &ContextGuard, message: &str, api: ErrorCall) -> Error {
+fn create_error<'ctx>(guard: &ContextGuard<'ctx>, message: &str, api: ErrorCall) -> Error<'ctx> {
   let message = super::String::new(guard, message);
   let mut value = JsValueRef::new();
   unsafe {
@@ -1,14 +1,53 @@
+use crate::scope::Root;
 use crate::value::{Object, Value};
 use crate::ContextGuard;
 use chakracore_sys::*;
 use libc::c_void;
+use std::any::TypeId;
+use std::collections::BTreeSet;
 use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+
+/// The addresses of all external data created by this crate, which is
+/// distinguished from data created elsewhere without dereferencing it.
+static REGISTRY: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
+
+/// The data associated with an external object.
+///
+/// The native type is recorded alongside the pointer, so the data can only be
+/// retrieved as the type it was created with.
+struct Data {
+  type_id: TypeId,
+  pointer: *mut c_void,
+  finalize: Option<unsafe fn(*mut c_void)>,
+}
+
+impl Data {
+  /// Creates data that owns a boxed resource.
+  fn owned<T: 'static>(external: Box<T>) -> Self {
+    Data {
+      type_id: TypeId::of::<T>(),
+      pointer: Box::into_raw(external) as *mut _,
+      finalize: Some(Self::drop_box::<T>),
+    }
+  }
+
+  /// Releases owned external data.
+  unsafe fn drop_box<T>(pointer: *mut c_void) {
+    Box::from_raw(pointer as *mut T);
+  }
+
+  /// Locks the registry of data addresses.
+  fn registry() -> MutexGuard<'static, BTreeSet<usize>> {
+    // The set is never left in an inconsistent state
+    REGISTRY.lock().unwrap_or_else(|error| error.into_inner())
+  }
+}
 
 /// A JavaScript external object.
-pub struct External(JsValueRef);
+pub struct External<'ctx>(JsValueRef, Root<'ctx>);
 
-// TODO: Make the entire implementation generic
-impl External {
+impl<'ctx> External<'ctx> {
   /// Creates a new object with external data.
   ///
   /// The object takes ownership of the resource. It is undetermined when, and
@@ -18,16 +57,19 @@ impl External {
   /// As long as the object is referenced on the stack or in any script
   /// context, the `external` data will be kept alive (i.e it is not tied to
   /// the handle).
-  pub fn new<T>(_guard: &ContextGuard, external: Box<T>) -> Self {
-    let mut value = JsValueRef::new();
-    unsafe {
-      jsassert!(JsCreateExternalObject(
-        Box::into_raw(external) as *mut _,
-        Some(Self::finalize::<T>),
-        &mut value
-      ));
-      Self::from_raw(value)
-    }
+  pub fn new<T: 'static>(_guard: &ContextGuard<'ctx>, external: Box<T>) -> Self {
+    unsafe { Self::create(Data::owned(external), None) }
+  }
+
+  /// Creates a new object with external data and a specified prototype.
+  ///
+  /// The ownership semantics are the same as for `new`.
+  pub fn with_prototype<T, O>(_guard: &ContextGuard<'ctx>, external: Box<T>, prototype: O) -> Self
+  where
+    T: 'static,
+    O: AsRef<Object<'ctx>>,
+  {
+    unsafe { Self::create(Data::owned(external), Some(prototype.as_ref())) }
   }
 
   /// Creates a new object with external data.
@@ -35,24 +77,99 @@ impl External {
   /// This is unsafe because the object does not take ownership of the
   /// resource. Therefore the data may become a dangling pointer. The caller
   /// is responsible for keeping the reference alive.
-  pub unsafe fn from_ptr<T>(_guard: &ContextGuard, external: *mut T) -> Self {
-    let mut value = JsValueRef::new();
-    jsassert!(JsCreateExternalObject(external as *mut _, None, &mut value));
-    Self::from_raw(value)
+  pub unsafe fn from_ptr<T: 'static>(_guard: &ContextGuard<'ctx>, external: *mut T) -> Self {
+    let data = Data {
+      type_id: TypeId::of::<T>(),
+      pointer: external as *mut _,
+      finalize: None,
+    };
+    Self::create(data, None)
+  }
+
+  /// Returns true if the external data is of type `T`.
+  pub fn is<T: 'static>(&self) -> bool {
+    self.downcast_ptr::<T>().is_some()
+  }
+
+  /// Returns a reference to the external data if it is of type `T`.
+  pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+    self
+      .downcast_ptr::<T>()
+      .and_then(|pointer| unsafe { pointer.as_ref() })
+  }
+
+  /// Returns a mutable reference to the external data if it is of type `T`.
+  ///
+  /// This is unsafe because all handles to the same object share the data.
+  /// The caller must ensure that no other reference to the data exists while
+  /// the returned reference is alive, including any created by script code.
+  /// Use a `RefCell` as the external data for checked mutable access.
+  pub unsafe fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+    self
+      .downcast_ptr::<T>()
+      .and_then(|pointer| pointer.as_mut())
   }
 
   /// Returns the external object's data.
-  pub unsafe fn value<T>(&self) -> &mut T {
-    let mut data = ptr::null_mut();
-    jsassert!(JsGetExternalData(self.as_raw(), &mut data));
-    (data as *mut T).as_mut().expect("retrieving external data")
+  ///
+  /// This is unsafe for the same reasons as `downcast_mut`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the data is not of type `T`.
+  #[deprecated(note = "use `downcast_ref` or `downcast_mut` instead")]
+  #[allow(clippy::mut_from_ref)]
+  pub unsafe fn value<T: 'static>(&self) -> &mut T {
+    self
+      .downcast_ptr::<T>()
+      .and_then(|pointer| pointer.as_mut())
+      .expect("retrieving external data")
   }
 
   /// Returns true if the value is an `External`.
-  pub fn is_same<V: AsRef<Value>>(value: V) -> bool {
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
     value.as_ref().get_type() == JsValueType::Object && Self::has_external_data(value.as_ref())
   }
 
+  /// Creates an external object, taking ownership of its data.
+  unsafe fn create(data: Data, prototype: Option<&Object>) -> Self {
+    let data = Box::into_raw(Box::new(data)) as *mut c_void;
+    Data::registry().insert(data as usize);
+
+    let mut value = JsValueRef::new();
+    jsassert!(match prototype {
+      Some(prototype) => JsCreateExternalObjectWithPrototype(
+        data,
+        Some(Self::finalize),
+        prototype.as_raw(),
+        &mut value
+      ),
+      None => JsCreateExternalObject(data, Some(Self::finalize), &mut value),
+    });
+    Self::from_raw(value)
+  }
+
+  /// Returns the external data pointer if it is of type `T`.
+  fn downcast_ptr<T: 'static>(&self) -> Option<*mut T> {
+    self
+      .data()
+      .filter(|data| data.type_id == TypeId::of::<T>())
+      .map(|data| data.pointer as *mut T)
+  }
+
+  /// Returns the external object's data, if it was created by this crate.
+  fn data(&self) -> Option<&Data> {
+    let mut data = ptr::null_mut();
+    jsassert!(unsafe { JsGetExternalData(self.as_raw(), &mut data) });
+
+    // The data stays registered as long as the object is alive
+    if Data::registry().contains(&(data as usize)) {
+      unsafe { (data as *const Data).as_ref() }
+    } else {
+      None
+    }
+  }
+
   /// Returns whether the value has external data or not.
   fn has_external_data(value: &Value) -> bool {
     let mut result = false;
@@ -61,8 +178,13 @@ impl External {
   }
 
   /// A finalizer callback, triggered before an external is removed.
-  unsafe extern "system" fn finalize<T>(data: *mut c_void) {
-    Box::from_raw(data as *mut T);
+  unsafe extern "system" fn finalize(data: *mut c_void) {
+    // Unregistered before it's released, so the address can be reused
+    Data::registry().remove(&(data as usize));
+    let data = Box::from_raw(data as *mut Data);
+    if let Some(finalize) = data.finalize {
+      finalize(data.pointer);
+    }
   }
 }
 
@@ -73,6 +195,7 @@ subtype!(External, Value);
 #[cfg(test)]
 mod tests {
   use crate::{test, value};
+  use chakracore_sys::*;
 
   #[test]
   fn destructor() {
@@ -92,4 +215,38 @@ mod tests {
     }
     assert!(unsafe { CALLED });
   }
+
+  #[test]
+  fn downcast() {
+    test::run_with_context(|guard| {
+      let mut external = value::External::new(guard, Box::new(10u32));
+
+      assert!(external.is::<u32>());
+      assert!(!external.is::<i32>());
+      assert!(external.downcast_ref::<String>().is_none());
+
+      *unsafe { external.downcast_mut::<u32>() }.unwrap() += 5;
+      assert_eq!(external.downcast_ref::<u32>(), Some(&15));
+
+      #[allow(deprecated)]
+      let value = unsafe { external.value::<u32>() };
+      assert_eq!(*value, 15);
+    });
+  }
+
+  #[test]
+  fn foreign_data() {
+    test::run_with_context(|_| {
+      let mut data = 0u8;
+      let mut object = JsValueRef::new();
+      jsassert!(unsafe {
+        JsCreateExternalObject(&mut data as *mut _ as *mut _, None, &mut object)
+      });
+
+      let external = unsafe { value::External::from_raw(object) };
+      assert!(value::External::is_same(&external));
+      assert!(!external.is::<u8>());
+      assert!(external.downcast_ref::<u8>().is_none());
+    });
+  }
 }
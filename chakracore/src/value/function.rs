@@ -1,4 +1,6 @@
 //! A JavaScript function and associated types.
+use crate::binding::TypedCallback;
+use crate::scope::{self, Root};
 use crate::value::{Object, Value};
 use crate::{util::jstry, Context, ContextGuard, Result};
 use chakracore_sys::*;
@@ -7,81 +9,139 @@ use std::slice;
 
 /// The information passed to `FunctionCallback` closures.
 #[derive(Clone, Debug)]
-pub struct CallbackInfo {
+pub struct CallbackInfo<'ctx> {
   /// Whether it's a constructor call or not.
   pub is_construct_call: bool,
   /// Arguments supplied by the caller.
-  pub arguments: Vec<Value>,
+  pub arguments: Vec<Value<'ctx>>,
   /// The source of this function call.
-  pub callee: Value,
+  pub callee: Value<'ctx>,
   /// The function's `this` context.
-  pub this: Value,
+  pub this: Value<'ctx>,
+  /// The constructor `new` was initially applied to (i.e `new.target`), which
+  /// differs from the callee when a derived class is constructed. This is
+  /// `undefined` unless it's a constructor call.
+  pub new_target: Value<'ctx>,
 }
 
 /// The result returned from a function callback.
-pub type CallbackResult = ::std::result::Result<Value, Value>;
+pub type CallbackResult<'ctx> = ::std::result::Result<Value<'ctx>, Value<'ctx>>;
 
 /// Callback type for functions.
-pub type FunctionCallback = dyn Fn(&ContextGuard, CallbackInfo) -> CallbackResult + Send;
+///
+/// The values passed to, and returned from, the callback are bound to the
+/// guard of the call, so they cannot be stored by the closure (use a
+/// `Persistent` handle instead).
+pub type FunctionCallback =
+  dyn for<'ctx> Fn(&ContextGuard<'ctx>, CallbackInfo<'ctx>) -> CallbackResult<'ctx> + Send;
 
 /// A JavaScript function object.
-pub struct Function(JsValueRef);
+pub struct Function<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Function {
+impl<'ctx> Function<'ctx> {
   /// Creates an anonymous function
-  pub fn new(_guard: &ContextGuard, callback: Box<FunctionCallback>) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>, callback: Box<FunctionCallback>) -> Self {
     Self::create(callback, |context, reference| unsafe {
-      JsCreateFunction(Some(Self::callback), context, reference)
+      JsCreateEnhancedFunction(Some(Self::callback), JsValueRef::new(), context, reference)
     })
   }
 
   /// Creates a named function
-  pub fn with_name(guard: &ContextGuard, name: &str, callback: Box<FunctionCallback>) -> Self {
+  pub fn with_name(
+    guard: &ContextGuard<'ctx>,
+    name: &str,
+    callback: Box<FunctionCallback>,
+  ) -> Self {
     Self::create(callback, |context, reference| unsafe {
       let name = super::String::new(guard, name);
-      JsCreateNamedFunction(name.as_raw(), Some(Self::callback), context, reference)
+      JsCreateEnhancedFunction(Some(Self::callback), name.as_raw(), context, reference)
     })
   }
 
+  /// Creates an anonymous function from a closure with typed arguments.
+  ///
+  /// Arguments are converted using `FromJs`, and a `TypeError` is thrown if
+  /// an argument is missing or has an incompatible type. Trailing arguments
+  /// of type `Option` may be omitted by the caller. The `this` value and the
+  /// active context's guard are available using the `This` extractor and a
+  /// leading `&ContextGuard` argument.
+  ///
+  /// The return value is converted using `IntoJs`. If the closure returns a
+  /// `Result`, an error is thrown as a JavaScript `Error`.
+  ///
+  /// ```rust
+  /// # use chakracore as js;
+  /// # let runtime = js::Runtime::new().unwrap();
+  /// # let context = js::Context::new(&runtime).unwrap();
+  /// # context.exec_with(|guard| {
+  /// let range = js::value::Function::wrap(guard, |start: u32, end: u32| {
+  ///   if start <= end {
+  ///     Ok((start..end).collect::<Vec<_>>())
+  ///   } else {
+  ///     Err("Invalid range")
+  ///   }
+  /// });
+  ///
+  /// let property = js::Property::new(guard, "range");
+  /// guard.global().set(guard, property, range);
+  ///
+  /// let result = js::script::eval(guard, "range(1, 4).join()").unwrap();
+  /// assert_eq!(result.to_string(guard), "1,2,3");
+  /// # }).unwrap();
+  /// ```
+  pub fn wrap<F, Args>(guard: &ContextGuard<'ctx>, callback: F) -> Self
+  where
+    F: TypedCallback<Args>,
+  {
+    Self::new(
+      guard,
+      Box::new(move |guard, info| callback.invoke(guard, &info)),
+    )
+  }
+
   /// Calls a function and returns the result. The context (i.e `this`) will
   /// be the global object associated with the `ContextGuard`.
-  pub fn call(&self, guard: &ContextGuard, arguments: &[&Value]) -> Result<Value> {
-    self.call_with_this(guard, &guard.global(), arguments)
+  pub fn call(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    arguments: &[&Value<'ctx>],
+  ) -> Result<Value<'ctx>> {
+    self.invoke(guard, &guard.global(), arguments, false)
   }
 
   /// Calls a function, with a context, and returns the result.
-  pub fn call_with_this<V: AsRef<Value>>(
+  pub fn call_with_this<V: AsRef<Value<'ctx>>>(
     &self,
-    _guard: &ContextGuard,
+    _guard: &ContextGuard<'ctx>,
     this: V,
-    arguments: &[&Value],
-  ) -> Result<Value> {
-    self.invoke(_guard, this, arguments, false)
+    arguments: &[&Value<'ctx>],
+  ) -> Result<Value<'ctx>> {
+    self.invoke(_guard, this.as_ref(), arguments, false)
   }
 
   /// Calls a function as a constructor and returns the result.
-  pub fn construct<V: AsRef<Value>>(
+  pub fn construct<V: AsRef<Value<'ctx>>>(
     &self,
-    _guard: &ContextGuard,
+    _guard: &ContextGuard<'ctx>,
     this: V,
-    args: &[&Value],
-  ) -> Result<Value> {
-    self.invoke(_guard, this, args, true)
+    args: &[&Value<'ctx>],
+  ) -> Result<Value<'ctx>> {
+    self.invoke(_guard, this.as_ref(), args, true)
   }
 
   is_same!(Function, "Returns true if the value is a `Function`.");
 
   /// Invokes a function and returns the result.
-  fn invoke<V: AsRef<Value>>(
+  fn invoke(
     &self,
-    _guard: &ContextGuard,
-    this: V,
-    arguments: &[&Value],
+    _guard: &ContextGuard<'ctx>,
+    this: &Value<'ctx>,
+    arguments: &[&Value<'ctx>],
     constructor: bool,
-  ) -> Result<Value> {
+  ) -> Result<Value<'ctx>> {
     // Combine the context with the arguments
     let mut forward = Vec::with_capacity(arguments.len() + 1);
-    forward.push(this.as_ref().as_raw());
+    forward.push(this.as_raw());
     forward.extend(arguments.iter().map(|value| value.as_raw()));
 
     let api = if constructor {
@@ -127,9 +187,9 @@ impl Function {
   /// A function callback, triggered on call.
   unsafe extern "system" fn callback(
     callee: JsValueRef,
-    is_construct_call: bool,
     arguments: *mut JsValueRef,
     len: c_ushort,
+    call: *mut JsNativeFunctionInfo,
     data: *mut c_void,
   ) -> JsRef {
     // This memory is cleaned up during object collection
@@ -138,15 +198,34 @@ impl Function {
     // There is always an active context in callbacks
     Context::exec_with_current(|guard| {
       // Construct the callback information object
+      let call = &*call;
       let arguments = slice::from_raw_parts_mut(arguments, len as usize);
+      let callee = Value::from_raw(callee);
+
+      // Only constructors have a `new.target`, which defaults to the callee
+      let new_target = if !call.isConstructCall {
+        super::undefined(guard)
+      } else if call.newTargetArg.0.is_null() {
+        callee.clone()
+      } else {
+        Some(Value::from_raw(call.newTargetArg))
+          .filter(|target| target.is_function())
+          .unwrap_or_else(|| callee.clone())
+      };
+
       let info = CallbackInfo {
-        is_construct_call,
-        arguments: arguments[1..]
-          .iter()
-          .map(|value| Value::from_raw(*value))
-          .collect(),
-        callee: Value::from_raw(callee),
-        this: Value::from_raw(arguments[0]),
+        is_construct_call: call.isConstructCall,
+        // The arguments are stored on the heap, so they must be reference
+        // counted
+        arguments: scope::unscoped(|| {
+          arguments[1..]
+            .iter()
+            .map(|value| Value::from_raw(*value))
+            .collect()
+        }),
+        callee,
+        this: Value::from_raw(call.thisArg),
+        new_target,
       };
 
       // Call the user supplied callback
@@ -168,7 +247,9 @@ subtype!(Function, Value);
 
 #[cfg(test)]
 mod tests {
-  use crate::{script, test, value, Property};
+  use crate::binding::This;
+  use crate::value::Persistent;
+  use crate::{script, test, value, ContextGuard, Error, Property};
 
   #[test]
   fn multiply() {
@@ -221,4 +302,52 @@ mod tests {
       assert_eq!(result.to_string(guard), "Exception");
     });
   }
+
+  #[test]
+  fn wrap() {
+    test::run_with_context(|guard| {
+      let function = value::Function::wrap(
+        guard,
+        |guard: &ContextGuard,
+         This(this): This<Persistent<value::Object<'static>>>,
+         key: String,
+         count: Option<u8>| {
+          let this = this.get(guard).unwrap();
+          let value = this.get(guard, Property::new(guard, &key));
+          vec![Persistent::new(guard, &value); count.unwrap_or(1) as usize]
+        },
+      );
+
+      let property = Property::new(guard, "repeat");
+      guard.global().set(guard, property, function);
+
+      let result = script::eval(guard, "repeat.call({ foo: 1 }, 'foo', 3)").unwrap();
+      assert_eq!(result.to_json(guard).unwrap(), "[1,1,1]");
+
+      let result = script::eval(guard, "repeat.call({ bar: true }, 'bar')").unwrap();
+      assert_eq!(result.to_json(guard).unwrap(), "[true]");
+
+      let error = |code| match script::eval(guard, code) {
+        Err(Error::ScriptException(message)) => message,
+        _ => unreachable!(),
+      };
+
+      assert_eq!(
+        error("repeat.call({}, 'foo', 'bar')"),
+        "TypeError: Argument 2: Expected Number, found String"
+      );
+      assert_eq!(
+        error("repeat.call({}, 'foo', 256)"),
+        "RangeError: Argument 2: Number 256 cannot be represented as u8"
+      );
+      assert_eq!(
+        error("repeat.call({})"),
+        "TypeError: Expected at least 1 argument, found 0"
+      );
+      assert_eq!(
+        error("repeat.call(1, 'foo')"),
+        "TypeError: Invalid 'this': Expected Object, found Number"
+      );
+    });
+  }
 }
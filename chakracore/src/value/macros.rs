@@ -2,19 +2,78 @@
 /// `reference` macro definition).
 macro_rules! reference {
   ($typ:ident) => {
-    reference_base!($typ);
+    impl<'ctx> $typ<'ctx> {
+      /// Creates an instance from a raw pointer.
+      ///
+      /// This is used for managing the lifetime of JSRT objects. They are
+      /// tracked using reference counting; incrementing with `from_raw`,
+      /// and decrementing with `drop`.
+      ///
+      /// This is required to support items stored on the heap, since the
+      /// JSRT runtime only observes the stack.
+      ///
+      /// The caller is responsible for choosing the lifetime of the
+      /// `ContextGuard` of the value's context, and for not letting it
+      /// outlive an active `HandleScope` (in which case the reference count is
+      /// left untouched).
+      pub unsafe fn from_raw(value: JsValueRef) -> $typ<'ctx> {
+        $typ(value, crate::scope::Root::acquire(value))
+      }
+
+      /// Returns the underlying raw pointer.
+      pub fn as_raw(&self) -> JsValueRef {
+        self.0
+      }
+    }
+
+    impl<'ctx> AsRef<Self> for $typ<'ctx> {
+      fn as_ref(&self) -> &Self {
+        self
+      }
+    }
+
+    impl<'ctx> Clone for $typ<'ctx> {
+      /// Duplicates a reference counted type.
+      ///
+      /// The underlying pointer will be copied, and its reference count
+      /// will be incremented, returned wrapped as the type.
+      fn clone(&self) -> $typ<'ctx> {
+        unsafe { $typ::from_raw(self.as_raw()) }
+      }
+    }
 
-    impl Drop for $typ {
+    unsafe impl<'ctx> crate::value::Handle for $typ<'ctx> {
+      type Static = $typ<'static>;
+
+      fn as_raw(&self) -> JsValueRef {
+        self.0
+      }
+    }
+
+    unsafe impl<'a, 'ctx> crate::value::Rebind<'ctx> for $typ<'a> {
+      type Bound = $typ<'ctx>;
+
+      unsafe fn rebind(value: JsValueRef) -> $typ<'ctx> {
+        $typ::from_raw(value)
+      }
+    }
+
+    impl<'ctx> Drop for $typ<'ctx> {
       /// Decrements the reference counter if the object is recyclable.
       fn drop(&mut self) {
         use crate::{util, Context};
-        Context::exec_with_value(self, |_| {
+        if !self.1.is_counted() {
+          return;
+        }
+
+        // If the value's context cannot be activated, the reference is leaked
+        // rather than panicking during a drop.
+        let _ = Context::exec_with_value(self, |_| {
           // This requires that the active context is the same as the
           // one it was created with (this is not mentioned whatsoever
           // in the ChakraCore documentation).
           util::release_reference(self.as_raw());
-        })
-        .expect("changing active context for release");
+        });
       }
     }
   };
@@ -23,14 +82,14 @@ macro_rules! reference {
 /// Implements a relationship between two subtypes.
 macro_rules! subtype {
   ($child:ident, $parent:ident) => {
-    impl From<$child> for $parent {
-      fn from(child: $child) -> $parent {
+    impl<'ctx> From<$child<'ctx>> for $parent<'ctx> {
+      fn from(child: $child<'ctx>) -> $parent<'ctx> {
         unsafe { ::std::mem::transmute(child) }
       }
     }
 
-    impl AsRef<$parent> for $child {
-      fn as_ref(&self) -> &$parent {
+    impl<'ctx> AsRef<$parent<'ctx>> for $child<'ctx> {
+      fn as_ref(&self) -> &$parent<'ctx> {
         unsafe { ::std::mem::transmute(self) }
       }
     }
@@ -42,8 +101,8 @@ macro_rules! inherit {
   ($child:ident, $parent:ident) => {
     subtype!($child, $parent);
 
-    impl ::std::ops::Deref for $child {
-      type Target = $parent;
+    impl<'ctx> ::std::ops::Deref for $child<'ctx> {
+      type Target = $parent<'ctx>;
 
       fn deref(&self) -> &Self::Target {
         unsafe { ::std::mem::transmute(self) }
@@ -56,7 +115,7 @@ macro_rules! inherit {
 macro_rules! is_same {
   ($target:ident, $target_doc:expr) => {
     #[doc=$target_doc]
-    pub fn is_same<V: AsRef<Value>>(value: V) -> bool {
+    pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
       value.as_ref().get_type() == JsValueType::$target
     }
   };
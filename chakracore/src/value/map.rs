@@ -0,0 +1,244 @@
+use crate::convert::ConversionError;
+use crate::intrinsics::{self, Intrinsic};
+use crate::iterator::{self, Iter};
+use crate::scope::Root;
+use crate::value::{Object, Value};
+use crate::{ContextGuard, Error, Property, Result};
+use chakracore_sys::*;
+
+/// A JavaScript `Map`.
+///
+/// The `get`, `set`, `has` and `delete` methods operate on the map's entries.
+/// They shadow the `Object` methods of the same names, which remain available
+/// for properties (e.g `Object::get(&map, guard, key)`).
+pub struct Map<'ctx>(JsValueRef, Root<'ctx>);
+
+/// A JavaScript `Set`.
+pub struct Set<'ctx>(JsValueRef, Root<'ctx>);
+
+/// An iterator over the entries of a `Map`, in insertion order.
+pub struct MapIter<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  entries: Iter<'a, 'ctx>,
+}
+
+impl<'ctx> Map<'ctx> {
+  /// Creates a new empty map.
+  pub fn new(guard: &ContextGuard<'ctx>) -> Self {
+    let map = intrinsics::construct(guard, Intrinsic::Map, &[]).expect("creating map");
+    unsafe { Self::from_raw(map.as_raw()) }
+  }
+
+  /// Returns the value associated with a key, or `undefined`.
+  pub fn get<K: AsRef<Value<'ctx>>>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    key: K,
+  ) -> Result<Value<'ctx>> {
+    intrinsics::call(guard, Intrinsic::MapGet, self, &[key.as_ref()])
+  }
+
+  /// Associates a value with a key.
+  pub fn set<K, V>(&self, guard: &ContextGuard<'ctx>, key: K, value: V) -> Result<()>
+  where
+    K: AsRef<Value<'ctx>>,
+    V: AsRef<Value<'ctx>>,
+  {
+    intrinsics::call(
+      guard,
+      Intrinsic::MapSet,
+      self,
+      &[key.as_ref(), value.as_ref()],
+    )
+    .map(|_| ())
+  }
+
+  /// Returns whether the map contains a key.
+  pub fn has<K: AsRef<Value<'ctx>>>(&self, guard: &ContextGuard<'ctx>, key: K) -> Result<bool> {
+    intrinsics::call(guard, Intrinsic::MapHas, self, &[key.as_ref()])
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Removes a key from the map. Returns whether the key was present.
+  pub fn delete<K: AsRef<Value<'ctx>>>(&self, guard: &ContextGuard<'ctx>, key: K) -> Result<bool> {
+    intrinsics::call(guard, Intrinsic::MapDelete, self, &[key.as_ref()])
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Returns the number of entries in the map.
+  pub fn len(&self, guard: &ContextGuard<'ctx>) -> usize {
+    let size = Property::new(guard, "size");
+    Object::get(self, guard, size).to_integer(guard) as usize
+  }
+
+  /// Returns an iterator over the map's entries.
+  pub fn iter<'a>(&self, guard: &'a ContextGuard<'ctx>) -> Result<MapIter<'a, 'ctx>> {
+    let entries = intrinsics::call(guard, Intrinsic::MapEntries, self, &[])?;
+    let entries = iterator::from_iterator(guard, to_object(&entries)?)?;
+    Ok(MapIter { guard, entries })
+  }
+
+  /// Returns true if the value is a `Map`, including a `Map` of another
+  /// context or one whose prototype has been changed.
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
+    intrinsics::has_slot(value.as_ref(), Intrinsic::MapHas)
+  }
+}
+
+impl<'ctx> Set<'ctx> {
+  /// Creates a new empty set.
+  pub fn new(guard: &ContextGuard<'ctx>) -> Self {
+    let set = intrinsics::construct(guard, Intrinsic::Set, &[]).expect("creating set");
+    unsafe { Self::from_raw(set.as_raw()) }
+  }
+
+  /// Adds a value to the set.
+  pub fn add<V: AsRef<Value<'ctx>>>(&self, guard: &ContextGuard<'ctx>, value: V) -> Result<()> {
+    intrinsics::call(guard, Intrinsic::SetAdd, self, &[value.as_ref()]).map(|_| ())
+  }
+
+  /// Returns whether the set contains a value.
+  pub fn has<V: AsRef<Value<'ctx>>>(&self, guard: &ContextGuard<'ctx>, value: V) -> Result<bool> {
+    intrinsics::call(guard, Intrinsic::SetHas, self, &[value.as_ref()])
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Removes a value from the set. Returns whether the value was present.
+  pub fn delete<V: AsRef<Value<'ctx>>>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    value: V,
+  ) -> Result<bool> {
+    intrinsics::call(guard, Intrinsic::SetDelete, self, &[value.as_ref()])
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Returns the number of values in the set.
+  pub fn len(&self, guard: &ContextGuard<'ctx>) -> usize {
+    let size = Property::new(guard, "size");
+    Object::get(self, guard, size).to_integer(guard) as usize
+  }
+
+  /// Returns an iterator over the set's values, in insertion order.
+  pub fn iter<'a>(&self, guard: &'a ContextGuard<'ctx>) -> Result<Iter<'a, 'ctx>> {
+    let values = intrinsics::call(guard, Intrinsic::SetValues, self, &[])?;
+    iterator::from_iterator(guard, to_object(&values)?)
+  }
+
+  /// Returns true if the value is a `Set`, including a `Set` of another
+  /// context or one whose prototype has been changed.
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
+    intrinsics::has_slot(value.as_ref(), Intrinsic::SetHas)
+  }
+}
+
+impl<'a, 'ctx> Iterator for MapIter<'a, 'ctx> {
+  type Item = Result<(Value<'ctx>, Value<'ctx>)>;
+
+  /// Returns the next key and value.
+  fn next(&mut self) -> Option<Self::Item> {
+    let guard = self.guard;
+    self.entries.next().map(|entry| {
+      let entry = to_object(&entry?)?;
+      Ok((entry.get_index(guard, 0), entry.get_index(guard, 1)))
+    })
+  }
+}
+
+/// Converts a value returned by the map's iteration methods, which scripts
+/// may replace through the iterator prototypes, to an object.
+fn to_object<'ctx>(value: &Value<'ctx>) -> Result<Object<'ctx>> {
+  value
+    .clone()
+    .into_object()
+    .ok_or_else(|| Error::Conversion(ConversionError::new(JsValueType::Object, value)))
+}
+
+reference!(Map);
+inherit!(Map, Object);
+subtype!(Map, Value);
+reference!(Set);
+inherit!(Set, Object);
+subtype!(Set, Value);
+
+#[cfg(test)]
+mod tests {
+  use crate::{script, test, value, Error};
+  use matches::assert_matches;
+
+  #[test]
+  fn map() {
+    test::run_with_context(|guard| {
+      script::eval(guard, "Map.prototype.get = null;").unwrap();
+
+      let map = value::Map::new(guard);
+      let key = value::Object::new(guard);
+      map.set(guard, &key, value::Number::new(guard, 1)).unwrap();
+      map
+        .set(guard, value::String::new(guard, "foo"), value::null(guard))
+        .unwrap();
+
+      assert_eq!(map.len(guard), 2);
+      assert!(map.has(guard, &key).unwrap());
+      assert_eq!(map.get(guard, &key).unwrap().to_integer(guard), 1);
+
+      let keys = map
+        .iter(guard)
+        .unwrap()
+        .map(|entry| entry.unwrap().0.to_string(guard))
+        .collect::<Vec<_>>();
+      assert_eq!(keys, ["[object Object]", "foo"]);
+
+      assert!(map.delete(guard, &key).unwrap());
+      assert!(!map.delete(guard, &key).unwrap());
+
+      let map = script::eval(guard, "new Map([[1, 2]])").unwrap();
+      assert!(map.is_map() && !map.is_set());
+      assert_eq!(map.into_map().unwrap().len(guard), 1);
+      assert!(!value::Object::new(guard).is_map());
+
+      let fake = script::eval(guard, "Object.create(Map.prototype)").unwrap();
+      assert!(!fake.is_map());
+      let orphan = script::eval(guard, "Object.setPrototypeOf(new Map(), null)").unwrap();
+      assert!(orphan.is_map());
+
+      script::eval(
+        guard,
+        "Object.getPrototypeOf(new Map().entries()).next = () => ({ value: 1 });",
+      )
+      .unwrap();
+      let map = script::eval(guard, "new Map([[1, 2]])")
+        .unwrap()
+        .into_map()
+        .unwrap();
+      assert_matches!(
+        map.iter(guard).unwrap().next().unwrap().err(),
+        Some(Error::Conversion(_))
+      );
+    });
+  }
+
+  #[test]
+  fn set() {
+    test::run_with_context(|guard| {
+      let set = value::Set::new(guard);
+      set.add(guard, value::Number::new(guard, 1)).unwrap();
+      set.add(guard, value::Number::new(guard, 1)).unwrap();
+      set.add(guard, value::Number::new(guard, 2)).unwrap();
+
+      assert_eq!(set.len(guard), 2);
+      assert!(set.has(guard, value::Number::new(guard, 2)).unwrap());
+      assert!(set.delete(guard, value::Number::new(guard, 2)).unwrap());
+
+      let values = set
+        .iter(guard)
+        .unwrap()
+        .map(|value| value.unwrap().to_integer(guard))
+        .collect::<Vec<_>>();
+      assert_eq!(values, [1]);
+
+      let set = script::eval(guard, "new Set()").unwrap();
+      assert!(set.is_set() && set.into_set().is_some());
+    });
+  }
+}
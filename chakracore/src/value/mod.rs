@@ -17,21 +17,30 @@
 //! Prefer `to_string` for safety.
 //!
 //! All created values are tied to a specific context. Because of this a
-//! `ContextGuard` is required whenever creating new values, and they should
-//! not be passed between different contexts.
+//! `ContextGuard` is required whenever creating new values, and each value
+//! borrows the guard it was created with (its `'ctx` lifetime). A value can
+//! therefore not be used once its guard has been dropped. To store a value
+//! beyond the guard's scope, use a [Persistent](struct.Persistent.html)
+//! handle.
 use crate::ContextGuard;
 use chakracore_sys::*;
 
 // TODO: Add typed arrays and buffer view.
 pub use self::array::*;
 pub use self::boolean::Boolean;
+pub use self::date::Date;
 pub use self::error::Error;
 pub use self::external::External;
 pub use self::function::Function;
+pub use self::map::{Map, MapIter, Set};
 pub use self::number::Number;
-pub use self::object::Object;
+pub use self::object::{Entries, Keys, Object};
+pub use self::persistent::{Handle, Persistent, Rebind};
 pub use self::promise::Promise;
+pub use self::proxy::{Proxy, ProxyHandler};
+pub use self::regexp::RegExp;
 pub use self::string::String;
+pub use self::transfer::{define_structured_clone, structured_clone, Transfer};
 pub use self::value::Value;
 
 #[macro_use]
@@ -40,17 +49,23 @@ mod macros;
 // Modules
 mod array;
 mod boolean;
+mod date;
 mod error;
 mod external;
 pub mod function;
+mod map;
 mod number;
 mod object;
+mod persistent;
 pub mod promise;
+pub mod proxy;
+mod regexp;
 mod string;
+mod transfer;
 mod value;
 
 /// Creates a `false` value.
-pub fn false_(_guard: &ContextGuard) -> Value {
+pub fn false_<'ctx>(_guard: &ContextGuard<'ctx>) -> Value<'ctx> {
   let mut value = JsValueRef::new();
   unsafe {
     jsassert!(JsGetFalseValue(&mut value));
@@ -59,7 +74,7 @@ pub fn false_(_guard: &ContextGuard) -> Value {
 }
 
 /// Creates a `true` value.
-pub fn true_(_guard: &ContextGuard) -> Value {
+pub fn true_<'ctx>(_guard: &ContextGuard<'ctx>) -> Value<'ctx> {
   let mut value = JsValueRef::new();
   unsafe {
     jsassert!(JsGetTrueValue(&mut value));
@@ -68,7 +83,7 @@ pub fn true_(_guard: &ContextGuard) -> Value {
 }
 
 /// Creates a `null` value.
-pub fn null(_guard: &ContextGuard) -> Value {
+pub fn null<'ctx>(_guard: &ContextGuard<'ctx>) -> Value<'ctx> {
   let mut value = JsValueRef::new();
   unsafe {
     jsassert!(JsGetNullValue(&mut value));
@@ -77,7 +92,7 @@ pub fn null(_guard: &ContextGuard) -> Value {
 }
 
 /// Creates an `undefined` value.
-pub fn undefined(_guard: &ContextGuard) -> Value {
+pub fn undefined<'ctx>(_guard: &ContextGuard<'ctx>) -> Value<'ctx> {
   let mut value = JsValueRef::new();
   unsafe {
     jsassert!(JsGetUndefinedValue(&mut value));
@@ -1,12 +1,13 @@
+use crate::scope::Root;
 use crate::{value::Value, ContextGuard};
 use chakracore_sys::*;
 
 /// A JavaScript number.
-pub struct Number(JsValueRef);
+pub struct Number<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Number {
+impl<'ctx> Number<'ctx> {
   /// Creates a new number.
-  pub fn new(_guard: &ContextGuard, number: i32) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>, number: i32) -> Self {
     let mut value = JsValueRef::new();
     unsafe {
       jsassert!(JsIntToNumber(number, &mut value));
@@ -15,7 +16,7 @@ impl Number {
   }
 
   /// Creates a new number from a double.
-  pub fn from_double(_guard: &ContextGuard, number: f64) -> Self {
+  pub fn from_double(_guard: &ContextGuard<'ctx>, number: f64) -> Self {
     let mut value = JsValueRef::new();
     unsafe {
       jsassert!(JsDoubleToNumber(number, &mut value));
@@ -1,18 +1,37 @@
-use crate::value::{Array, Function, Value};
-use crate::{util::jstry, ContextGuard, Property, Result};
+use crate::intrinsics::{self, Intrinsic};
+use crate::scope::{self, Root};
+use crate::value::{self, Array, Function, Value};
+use crate::{util::jstry, ContextGuard, Property, PropertyDescriptor, Result};
 use chakracore_sys::*;
 use libc::c_void;
+use std::collections::HashSet;
+use std::{ptr, vec};
 
 /// Callback type for collector.
 type BeforeCollectCallback = dyn Fn(&Value);
 
 /// A JavaScript object.
-pub struct Object(JsValueRef);
+pub struct Object<'ctx>(JsValueRef, Root<'ctx>);
+
+/// An iterator over an object's property keys, yielding each key's identifier
+/// along with the key itself (i.e a string or a symbol).
+///
+/// The keys are collected when the iterator is created, so properties added
+/// during iteration are not visited.
+pub struct Keys<'ctx>(vec::IntoIter<(Property, Value<'ctx>)>);
+
+/// An iterator over an object's property keys and their values.
+///
+/// The values are retrieved from the object during iteration.
+pub struct Entries<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  object: Object<'ctx>,
+  keys: Keys<'ctx>,
+}
 
-// TODO: Add `for .. in` iterator
-impl Object {
+impl<'ctx> Object<'ctx> {
   /// Creates a new empty object.
-  pub fn new(_guard: &ContextGuard) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>) -> Self {
     let mut value = JsValueRef::new();
     unsafe {
       jsassert!(JsCreateObject(&mut value));
@@ -21,10 +40,10 @@ impl Object {
   }
 
   /// Sets an object's property's value.
-  pub fn set<P, V>(&self, _guard: &ContextGuard, key: P, value: V)
+  pub fn set<P, V>(&self, _guard: &ContextGuard<'ctx>, key: P, value: V)
   where
     P: AsRef<Property>,
-    V: AsRef<Value>,
+    V: AsRef<Value<'ctx>>,
   {
     jsassert!(unsafe {
       JsSetProperty(
@@ -37,15 +56,15 @@ impl Object {
   }
 
   /// Sets an object's index value.
-  pub fn set_index<V: AsRef<Value>>(&self, guard: &ContextGuard, index: u32, value: V) {
-    let index = super::Number::new(guard, index as i32);
+  pub fn set_index<V: AsRef<Value<'ctx>>>(&self, guard: &ContextGuard<'ctx>, index: u32, value: V) {
+    let index = super::Number::from_double(guard, f64::from(index));
     jsassert!(unsafe {
       JsSetIndexedProperty(self.as_raw(), index.as_raw(), value.as_ref().as_raw())
     });
   }
 
   /// Returns an object's property's value.
-  pub fn get<P: AsRef<Property>>(&self, _guard: &ContextGuard, key: P) -> Value {
+  pub fn get<P: AsRef<Property>>(&self, _guard: &ContextGuard<'ctx>, key: P) -> Value<'ctx> {
     let mut result = JsValueRef::new();
     unsafe {
       jsassert!(JsGetProperty(
@@ -58,8 +77,8 @@ impl Object {
   }
 
   /// Returns an object's index value.
-  pub fn get_index(&self, guard: &ContextGuard, index: u32) -> Value {
-    let index = super::Number::new(guard, index as i32);
+  pub fn get_index(&self, guard: &ContextGuard<'ctx>, index: u32) -> Value<'ctx> {
+    let index = super::Number::from_double(guard, f64::from(index));
     let mut result = JsValueRef::new();
     unsafe {
       jsassert!(JsGetIndexedProperty(
@@ -72,7 +91,7 @@ impl Object {
   }
 
   /// Deletes an object's property.
-  pub fn delete<P: AsRef<Property>>(&self, _guard: &ContextGuard, key: P) -> bool {
+  pub fn delete<P: AsRef<Property>>(&self, _guard: &ContextGuard<'ctx>, key: P) -> bool {
     let mut result = JsValueRef::new();
     unsafe {
       jsassert!(JsDeleteProperty(
@@ -86,33 +105,106 @@ impl Object {
   }
 
   /// Deletes an object's index.
-  pub fn delete_index(&self, guard: &ContextGuard, index: u32) {
-    let index = super::Number::new(guard, index as i32);
+  pub fn delete_index(&self, guard: &ContextGuard<'ctx>, index: u32) {
+    let index = super::Number::from_double(guard, f64::from(index));
     jsassert!(unsafe { JsDeleteIndexedProperty(self.as_raw(), index.as_raw()) });
   }
 
   /// Determines whether an object has a property.
-  pub fn has<P: AsRef<Property>>(&self, _guard: &ContextGuard, key: P) -> bool {
+  pub fn has<P: AsRef<Property>>(&self, _guard: &ContextGuard<'ctx>, key: P) -> bool {
     let mut result = false;
     jsassert!(unsafe { JsHasProperty(self.as_raw(), key.as_ref().as_raw(), &mut result) });
     result
   }
 
   /// Determines whether an object has a value at the specified index.
-  pub fn has_index(&self, guard: &ContextGuard, index: u32) -> bool {
+  pub fn has_index(&self, guard: &ContextGuard<'ctx>, index: u32) -> bool {
     let mut result = false;
-    let index = super::Number::new(guard, index as i32);
+    let index = super::Number::from_double(guard, f64::from(index));
     jsassert!(unsafe { JsHasIndexedProperty(self.as_raw(), index.as_raw(), &mut result) });
     result
   }
 
-  /// Defines or modifies a property directly on an object.
+  /// Returns the value of a property keyed by an arbitrary value.
+  ///
+  /// The key is converted using JavaScript semantics (i.e symbols are used
+  /// as is, and other values are converted to strings), which may invoke
+  /// script code and therefore fail.
+  pub fn get_by_value<K: AsRef<Value<'ctx>>>(
+    &self,
+    _guard: &ContextGuard<'ctx>,
+    key: K,
+  ) -> Result<Value<'ctx>> {
+    let mut result = JsValueRef::new();
+    unsafe {
+      jstry(JsObjectGetProperty(
+        self.as_raw(),
+        key.as_ref().as_raw(),
+        &mut result,
+      ))?;
+      Ok(Value::from_raw(result))
+    }
+  }
+
+  /// Sets the value of a property keyed by an arbitrary value.
+  pub fn set_by_value<K, V>(&self, _guard: &ContextGuard<'ctx>, key: K, value: V) -> Result<()>
+  where
+    K: AsRef<Value<'ctx>>,
+    V: AsRef<Value<'ctx>>,
+  {
+    unsafe {
+      jstry(JsObjectSetProperty(
+        self.as_raw(),
+        key.as_ref().as_raw(),
+        value.as_ref().as_raw(),
+        false,
+      ))
+    }
+  }
+
+  /// Determines whether an object has a property keyed by an arbitrary value.
+  pub fn has_by_value<K: AsRef<Value<'ctx>>>(
+    &self,
+    _guard: &ContextGuard<'ctx>,
+    key: K,
+  ) -> Result<bool> {
+    let mut result = false;
+    unsafe {
+      jstry(JsObjectHasProperty(
+        self.as_raw(),
+        key.as_ref().as_raw(),
+        &mut result,
+      ))?;
+    }
+    Ok(result)
+  }
+
+  /// Deletes a property keyed by an arbitrary value.
+  pub fn delete_by_value<K: AsRef<Value<'ctx>>>(
+    &self,
+    _guard: &ContextGuard<'ctx>,
+    key: K,
+  ) -> Result<bool> {
+    let mut result = JsValueRef::new();
+    unsafe {
+      jstry(JsObjectDeleteProperty(
+        self.as_raw(),
+        key.as_ref().as_raw(),
+        false,
+        &mut result,
+      ))?;
+      Ok(super::Boolean::from_raw(result).value())
+    }
+  }
+
+  /// Defines or modifies a property directly on an object, using a
+  /// descriptor object.
   ///
   /// This is equivalent to `Object.defineProperty()`.
-  pub fn define_property<P, O>(&self, _guard: &ContextGuard, key: P, desc: O) -> bool
+  pub fn define_property<P, O>(&self, _guard: &ContextGuard<'ctx>, key: P, desc: O) -> bool
   where
     P: AsRef<Property>,
-    O: AsRef<Object>,
+    O: AsRef<Object<'ctx>>,
   {
     let mut result = false;
     jsassert!(unsafe {
@@ -126,14 +218,82 @@ impl Object {
     result
   }
 
+  /// Defines or modifies a property directly on an object, and returns
+  /// whether it succeeded. An invalid descriptor (e.g one with both a value
+  /// and a getter) results in a `TypeError`.
+  ///
+  /// This is equivalent to `Reflect.defineProperty()`.
+  pub fn define_own_property<P: AsRef<Property>>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    key: P,
+    descriptor: &PropertyDescriptor<'ctx>,
+  ) -> Result<bool> {
+    let mut result = false;
+    let descriptor = descriptor.to_object(guard);
+    unsafe {
+      jstry(JsDefineProperty(
+        self.as_raw(),
+        key.as_ref().as_raw(),
+        descriptor.as_raw(),
+        &mut result,
+      ))?;
+    }
+    Ok(result)
+  }
+
+  /// Defines or modifies multiple properties directly on an object. Returns
+  /// false, without defining the remaining properties, if any definition
+  /// fails.
+  ///
+  /// This is similar to `Object.defineProperties()`.
+  pub fn define_properties<P, I>(&self, guard: &ContextGuard<'ctx>, properties: I) -> Result<bool>
+  where
+    P: AsRef<Property>,
+    I: IntoIterator<Item = (P, PropertyDescriptor<'ctx>)>,
+  {
+    for (key, descriptor) in properties {
+      if !self.define_own_property(guard, key, &descriptor)? {
+        return Ok(false);
+      }
+    }
+    Ok(true)
+  }
+
+  /// Returns the descriptor of an own property, or `None` if the object has
+  /// no such property.
+  ///
+  /// This is equivalent to `Object.getOwnPropertyDescriptor()`.
+  pub fn get_own_property_descriptor<P: AsRef<Property>>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    key: P,
+  ) -> Option<PropertyDescriptor<'ctx>> {
+    let mut descriptor = JsValueRef::new();
+    unsafe {
+      jsassert!(JsGetOwnPropertyDescriptor(
+        self.as_raw(),
+        key.as_ref().as_raw(),
+        &mut descriptor
+      ));
+      Value::from_raw(descriptor)
+    }
+    .into_object()
+    .map(|descriptor| PropertyDescriptor::from_object(guard, &descriptor))
+  }
+
   /// Sets the object's prototype. This will result in an error if it's called
   /// on the context's global object.
-  pub fn set_prototype<V: AsRef<Value>>(&self, _guard: &ContextGuard, prototype: V) -> Result<()> {
+  pub fn set_prototype<V: AsRef<Value<'ctx>>>(
+    &self,
+    _guard: &ContextGuard<'ctx>,
+    prototype: V,
+  ) -> Result<()> {
     unsafe { jstry(JsSetPrototype(self.as_raw(), prototype.as_ref().as_raw())) }
   }
 
   /// Returns the object's prototype.
-  pub fn get_prototype(&self, _guard: &ContextGuard) -> Value {
+  pub fn get_prototype(&self, _guard: &ContextGuard<'ctx>) -> Value<'ctx> {
     let mut prototype = JsValueRef::new();
     unsafe {
       jsassert!(JsGetPrototype(self.as_raw(), &mut prototype));
@@ -142,7 +302,7 @@ impl Object {
   }
 
   /// Returns the object's property names
-  pub fn get_own_property_names(&self, _guard: &ContextGuard) -> Array {
+  pub fn get_own_property_names(&self, _guard: &ContextGuard<'ctx>) -> Array<'ctx> {
     let mut properties = JsValueRef::new();
     unsafe {
       jsassert!(JsGetOwnPropertyNames(self.as_raw(), &mut properties));
@@ -150,11 +310,90 @@ impl Object {
     }
   }
 
+  /// Returns the object's own symbol properties.
+  pub fn get_own_property_symbols(&self, _guard: &ContextGuard<'ctx>) -> Array<'ctx> {
+    let mut properties = JsValueRef::new();
+    unsafe {
+      jsassert!(JsGetOwnPropertySymbols(self.as_raw(), &mut properties));
+      Array::from_raw(properties)
+    }
+  }
+
+  /// Returns an iterator over the object's own enumerable string keys.
+  ///
+  /// This is equivalent to `Object.keys()`.
+  pub fn keys(&self, guard: &ContextGuard<'ctx>) -> Keys<'ctx> {
+    let mut keys = self.own_names(guard);
+    keys.retain(|(key, _)| self.is_enumerable(guard, key));
+    Keys(keys.into_iter())
+  }
+
+  /// Returns an iterator over the object's own enumerable string keys and
+  /// their values.
+  ///
+  /// This is equivalent to `Object.entries()`.
+  pub fn entries<'a>(&self, guard: &'a ContextGuard<'ctx>) -> Entries<'a, 'ctx> {
+    Entries {
+      guard,
+      object: self.clone(),
+      keys: self.keys(guard),
+    }
+  }
+
+  /// Returns an iterator over all of the object's own keys, including
+  /// non-enumerable and symbol keys.
+  ///
+  /// This is equivalent to `Reflect.ownKeys()`.
+  pub fn own_keys(&self, guard: &ContextGuard<'ctx>) -> Keys<'ctx> {
+    let symbols = self.get_own_property_symbols(guard);
+    let mut keys = self.own_names(guard);
+    scope::unscoped(|| {
+      keys.extend(symbols.iter(guard).map(|symbol| unsafe {
+        let mut property = JsPropertyIdRef::new();
+        jsassert!(JsGetPropertyIdFromSymbol(symbol.as_raw(), &mut property));
+        (Property::from_raw(property), symbol)
+      }))
+    });
+    Keys(keys.into_iter())
+  }
+
+  /// Returns an iterator over the enumerable string keys of the object and
+  /// its prototype chain, and their values.
+  ///
+  /// This is equivalent to a `for .. in` loop; a property shadowed by
+  /// another one, earlier in the prototype chain, is only visited once.
+  pub fn for_in<'a>(&self, guard: &'a ContextGuard<'ctx>) -> Entries<'a, 'ctx> {
+    let mut visited = HashSet::new();
+    let mut keys = Vec::new();
+    let mut object = Some(self.clone());
+
+    while let Some(current) = object {
+      for (key, name) in current.own_names(guard) {
+        // Non-enumerable properties shadow their prototypes' as well, and
+        // identifiers are unique per name, so they can be compared directly
+        if visited.insert(key.as_raw()) && current.is_enumerable(guard, &key) {
+          keys.push((key, name));
+        }
+      }
+      object = current.get_prototype(guard).into_object();
+    }
+
+    Entries {
+      guard,
+      object: self.clone(),
+      keys: Keys(keys.into_iter()),
+    }
+  }
+
   /// Returns whether the object is an instance of this `Function` or not.
   ///
   /// This must only be used on values that exists within the same context as
   /// the constructor, otherwise the result will always be `false`.
-  pub fn instance_of<F: AsRef<Function>>(&self, _guard: &ContextGuard, constructor: F) -> bool {
+  pub fn instance_of<F: AsRef<Function<'ctx>>>(
+    &self,
+    _guard: &ContextGuard<'ctx>,
+    constructor: F,
+  ) -> bool {
     let mut result = false;
     // TODO: #[cfg(debug_assertions)] validate same context
     unsafe {
@@ -179,6 +418,61 @@ impl Object {
     result
   }
 
+  /// Prevents modification of the object's existing properties, and the
+  /// addition of new ones. A `TypeError` is thrown if any property could not
+  /// be made read-only (e.g a typed array's elements).
+  ///
+  /// This is equivalent to `Object.freeze()`.
+  pub fn freeze(&self, guard: &ContextGuard<'ctx>) -> Result<()> {
+    self.integrity(guard, Intrinsic::ObjectFreeze).map(|_| ())
+  }
+
+  /// Prevents the object's properties from being deleted or reconfigured,
+  /// and the addition of new ones. Existing values can still be changed.
+  ///
+  /// This is equivalent to `Object.seal()`.
+  pub fn seal(&self, guard: &ContextGuard<'ctx>) -> Result<()> {
+    self.integrity(guard, Intrinsic::ObjectSeal).map(|_| ())
+  }
+
+  /// Returns whether the object is frozen or not.
+  ///
+  /// This is equivalent to `Object.isFrozen()`.
+  pub fn is_frozen(&self, guard: &ContextGuard<'ctx>) -> Result<bool> {
+    self
+      .integrity(guard, Intrinsic::ObjectIsFrozen)
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Returns whether the object is sealed or not.
+  ///
+  /// This is equivalent to `Object.isSealed()`.
+  pub fn is_sealed(&self, guard: &ContextGuard<'ctx>) -> Result<bool> {
+    self
+      .integrity(guard, Intrinsic::ObjectIsSealed)
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Determines whether the object has an own (i.e not inherited) property.
+  pub fn has_own_property<P: AsRef<Property>>(&self, _guard: &ContextGuard<'ctx>, key: P) -> bool {
+    let mut result = false;
+    jsassert!(unsafe { JsHasOwnProperty(self.as_raw(), key.as_ref().as_raw(), &mut result) });
+    result
+  }
+
+  /// Creates a shallow copy of the object, with the same prototype and own
+  /// properties.
+  ///
+  /// Only ordinary objects can be cloned; an error is returned for exotic
+  /// objects such as arrays and functions.
+  pub fn clone_shallow(&self, _guard: &ContextGuard<'ctx>) -> Result<Object<'ctx>> {
+    let mut clone = JsValueRef::new();
+    unsafe {
+      jstry(JsCloneObject(self.as_raw(), &mut clone))?;
+      Ok(Object::from_raw(clone))
+    }
+  }
+
   /// Sets a callback that is executed before the object is collected.
   ///
   /// This is highly unsafe to use. There is no bookkeeping whether any other
@@ -195,7 +489,7 @@ impl Object {
   }
 
   /// Returns true if the value is an `Object`.
-  pub fn is_same<V: AsRef<Value>>(value: V) -> bool {
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
     match value.as_ref().get_type() {
       JsValueType::Object
       | JsValueType::Function
@@ -208,6 +502,55 @@ impl Object {
     }
   }
 
+  /// Returns the object's own string keys, and their identifiers.
+  ///
+  /// The keys are stored on the heap, and scripted getters may run while
+  /// they are visited, so they are reference counted even within a scope.
+  fn own_names(&self, guard: &ContextGuard<'ctx>) -> Vec<(Property, Value<'ctx>)> {
+    // `JsGetPropertyIdFromName` is only available on Windows, so each name is
+    // copied, as UTF-8, into a buffer shared by all keys instead.
+    let mut buffer = Vec::new();
+    let names = self.get_own_property_names(guard);
+    scope::unscoped(|| {
+      names
+        .iter(guard)
+        .map(|name| unsafe {
+          let mut size = 0;
+          jsassert!(JsCopyString(name.as_raw(), ptr::null_mut(), 0, &mut size));
+          buffer.resize(size, 0u8);
+          jsassert!(JsCopyString(
+            name.as_raw(),
+            buffer.as_mut_ptr() as _,
+            size,
+            ptr::null_mut()
+          ));
+
+          let mut property = JsPropertyIdRef::new();
+          jsassert!(JsCreatePropertyId(
+            buffer.as_ptr() as _,
+            size,
+            &mut property
+          ));
+          (Property::from_raw(property), name)
+        })
+        .collect()
+    })
+  }
+
+  /// Returns whether an own property is enumerable.
+  fn is_enumerable(&self, guard: &ContextGuard<'ctx>, key: &Property) -> bool {
+    self
+      .get_own_property_descriptor(guard, key)
+      .and_then(|descriptor| descriptor.enumerable)
+      .unwrap_or(false)
+  }
+
+  /// Calls one of the captured `Object` integrity functions with the object.
+  fn integrity(&self, guard: &ContextGuard<'ctx>, intrinsic: Intrinsic) -> Result<Value<'ctx>> {
+    let object: &Value<'ctx> = self;
+    intrinsics::call(guard, intrinsic, value::undefined(guard), &[object])
+  }
+
   /// A collect callback, triggered before the object is destroyed.
   unsafe extern "system" fn collect(value: JsValueRef, data: *mut c_void) {
     let wrapper: Box<Box<BeforeCollectCallback>> = Box::from_raw(data as *mut _);
@@ -215,12 +558,41 @@ impl Object {
   }
 }
 
+impl<'ctx> Iterator for Keys<'ctx> {
+  type Item = (Property, Value<'ctx>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.0.next()
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.0.size_hint()
+  }
+}
+
+impl<'a, 'ctx> Iterator for Entries<'a, 'ctx> {
+  type Item = (Property, Value<'ctx>);
+
+  /// Returns the next key and its current value.
+  fn next(&mut self) -> Option<Self::Item> {
+    self.keys.next().map(|(key, _)| {
+      let value = self.object.get(self.guard, &key);
+      (key, value)
+    })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.keys.size_hint()
+  }
+}
+
 reference!(Object);
 inherit!(Object, Value);
 
 #[cfg(test)]
 mod tests {
   use crate::{script, test, value, Context, Property, Runtime};
+  use chakracore_sys::JsValueType;
 
   #[test]
   fn properties() {
@@ -253,6 +625,143 @@ mod tests {
     });
   }
 
+  #[test]
+  fn value_keys() {
+    test::run_with_context(|guard| {
+      let object = value::Object::new(guard);
+      let symbol = script::eval(guard, "Symbol('key')").unwrap();
+      let number = value::Number::new(guard, 7);
+      let string = value::String::new(guard, "7");
+
+      object
+        .set_by_value(guard, &symbol, value::true_(guard))
+        .unwrap();
+      object
+        .set_by_value(guard, &number, value::Number::new(guard, 42))
+        .unwrap();
+
+      assert!(object.has_by_value(guard, &symbol).unwrap());
+      assert!(object.get_by_value(guard, &symbol).unwrap().to_bool(guard));
+      assert_eq!(
+        object
+          .get_by_value(guard, &string)
+          .unwrap()
+          .to_integer(guard),
+        42
+      );
+      assert_eq!(object.get_index(guard, 7).to_integer(guard), 42);
+
+      assert!(object.delete_by_value(guard, &string).unwrap());
+      assert!(!object.has_by_value(guard, &number).unwrap());
+
+      let key = script::eval(guard, "({ toString() { throw new Error('key'); } })").unwrap();
+      assert!(object.get_by_value(guard, &key).is_err());
+    });
+  }
+
+  #[test]
+  fn integrity() {
+    test::run_with_context(|guard| {
+      let config = script::eval(guard, "({ port: 80, get host() { return 'localhost'; } })")
+        .unwrap()
+        .into_object()
+        .unwrap();
+
+      let port = Property::new(guard, "port");
+      assert!(config.has_own_property(guard, &port));
+      assert!(!config.has_own_property(guard, Property::new(guard, "toString")));
+
+      let clone = config.clone_shallow(guard).unwrap();
+      clone.set(guard, &port, value::Number::new(guard, 8080));
+      assert_eq!(config.get(guard, &port).to_integer(guard), 80);
+
+      clone.seal(guard).unwrap();
+      assert!(clone.is_sealed(guard).unwrap() && !clone.is_frozen(guard).unwrap());
+
+      config.freeze(guard).unwrap();
+      assert!(config.is_frozen(guard).unwrap() && config.is_sealed(guard).unwrap());
+
+      let global = guard.global();
+      global.set(guard, Property::new(guard, "config"), &config);
+      let result = script::eval(
+        guard,
+        "'use strict'; try { config.port = 1; } catch (e) { e.name }",
+      )
+      .unwrap();
+      assert_eq!(result.to_string(guard), "TypeError");
+      assert!(script::eval(guard, "Object.isFrozen(config)")
+        .unwrap()
+        .to_bool(guard));
+
+      let array = value::Array::new(guard, 0);
+      assert!(array.clone_shallow(guard).is_err());
+
+      let proxy = script::eval(
+        guard,
+        "Object.freeze = null; new Proxy({}, { preventExtensions: () => false })",
+      )
+      .unwrap()
+      .into_object()
+      .unwrap();
+      assert!(proxy.freeze(guard).is_err());
+      assert!(!proxy.is_frozen(guard).unwrap());
+    });
+  }
+
+  #[test]
+  fn iteration() {
+    test::run_with_context(|guard| {
+      let object = script::eval(
+        guard,
+        "
+        var base = { foo: 1, bar: 2, baz: 3 };
+        var object = Object.create(base, { hidden: { value: 4 }, bar: { value: 5 } });
+        object.qux = 6;
+        object[Symbol('sym')] = 7;
+        object",
+      )
+      .unwrap()
+      .into_object()
+      .unwrap();
+
+      let entries = |iter: &mut dyn Iterator<Item = (Property, _)>| {
+        iter
+          .map(|(key, value)| {
+            (
+              key.to_string(guard),
+              value::Value::to_integer(&value, guard),
+            )
+          })
+          .collect::<Vec<_>>()
+      };
+
+      let keys = object
+        .keys(guard)
+        .map(|(key, name)| (key.to_string(guard), name.to_string(guard)))
+        .collect::<Vec<_>>();
+      assert_eq!(keys, [("qux".to_string(), "qux".to_string())]);
+      assert_eq!(
+        entries(&mut object.entries(guard)),
+        [("qux".to_string(), 6)]
+      );
+
+      // The non-enumerable `bar` shadows the prototype's
+      assert_eq!(
+        entries(&mut object.for_in(guard)),
+        [
+          ("qux".to_string(), 6),
+          ("foo".to_string(), 1),
+          ("baz".to_string(), 3),
+        ]
+      );
+
+      let own_keys = object.own_keys(guard).collect::<Vec<_>>();
+      assert_eq!(own_keys.len(), 4);
+      assert_eq!(own_keys[3].1.get_type(), JsValueType::Symbol);
+      assert_eq!(object.get(guard, &own_keys[3].0).to_integer(guard), 7);
+    });
+  }
+
   #[test]
   fn instance_of() {
     test::run_with_context(|guard| {
@@ -282,17 +791,19 @@ mod tests {
     let c1 = Context::new(&runtime).unwrap();
     let c2 = Context::new(&runtime).unwrap();
 
-    let g1 = c1.make_current().unwrap();
-    let p1 = script::eval(&g1, "new Promise(() => true)")
-      .ok()
-      .and_then(|v| v.into_object())
+    let (p1, p2) = c1
+      .exec_with(|g1| {
+        let p1 = script::eval(g1, "new Promise(() => true)").unwrap();
+        let p2 = script::eval(g1, "Promise").unwrap();
+        (p1.marshal_to(&c2).unwrap(), p2.marshal_to(&c2).unwrap())
+      })
       .unwrap();
 
-    let g2 = c2.make_current().unwrap();
-    let p2 = script::eval(&g1, "Promise")
-      .ok()
-      .and_then(|v| v.into_function())
-      .unwrap();
-    assert!(p1.instance_of(&g2, &p2));
+    c2.exec_with(|g2| {
+      let p1 = p1.get(g2).and_then(|v| v.into_object()).unwrap();
+      let p2 = p2.get(g2).and_then(|v| v.into_function()).unwrap();
+      assert!(p1.instance_of(g2, &p2));
+    })
+    .unwrap();
   }
 }
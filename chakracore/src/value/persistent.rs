@@ -0,0 +1,197 @@
+//! Handles that keep a value alive beyond its `ContextGuard`.
+use crate::runtime::Instance;
+use crate::{util, Context, ContextGuard};
+use chakracore_sys::*;
+use std::{fmt, marker::PhantomData, mem::ManuallyDrop, ptr, sync::Arc};
+
+/// A value type, with a lifetime bound to a `ContextGuard`.
+///
+/// This is implemented by all value types, and is unsafe to implement since
+/// the type must be a reference counted `JsValueRef`.
+pub unsafe trait Handle {
+  /// The same type, with a `'static` lifetime (e.g `Object<'static>`).
+  ///
+  /// This is used to name the type of a `Persistent` handle.
+  type Static: 'static;
+
+  /// Returns the underlying raw pointer.
+  fn as_raw(&self) -> JsValueRef;
+}
+
+/// A value type that can be bound to the lifetime of a `ContextGuard`.
+pub unsafe trait Rebind<'ctx> {
+  /// The same type, with a `'ctx` lifetime (e.g `Object<'ctx>`).
+  type Bound: 'ctx;
+
+  /// Creates an instance from a raw pointer, incrementing its reference
+  /// count.
+  unsafe fn rebind(value: JsValueRef) -> Self::Bound;
+}
+
+/// A handle that keeps a value alive until it's dropped.
+///
+/// In contrast to a value, a persistent handle is not bound to a
+/// `ContextGuard`, so it can be stored (e.g in a struct or a closure) and
+/// later retrieved using another guard. The type parameter is the value's
+/// type with a `'static` lifetime:
+///
+/// ```rust
+/// # use chakracore as js;
+/// use js::value::{Object, Persistent};
+/// # let runtime = js::Runtime::new().unwrap();
+/// # let context = js::Context::new(&runtime).unwrap();
+///
+/// let persistent: Persistent<Object<'static>> = context
+///   .exec_with(|guard| {
+///     let object = js::script::eval(guard, "({ foo: 10 })").unwrap();
+///     Persistent::new(guard, &object.into_object().unwrap())
+///   })
+///   .unwrap();
+///
+/// context
+///   .exec_with(|guard| {
+///     let object = persistent.get(guard).unwrap();
+///     let foo = object.get(guard, js::Property::new(guard, "foo"));
+///     assert_eq!(foo.to_integer(guard), 10);
+///   })
+///   .unwrap();
+/// ```
+///
+/// The handle keeps its context and runtime alive, so it may outlive both.
+/// If the context cannot be activated when the handle is dropped (e.g since
+/// the runtime is active in another thread), the value and context are leaked.
+pub struct Persistent<T> {
+  value: JsValueRef,
+  context: ManuallyDrop<Context>,
+  // Dropped after the context, so the runtime is disposed last
+  runtime: Option<Arc<Instance>>,
+  phantom: PhantomData<T>,
+}
+
+impl<T: 'static> Persistent<T> {
+  /// Creates a persistent handle to a value in the guard's context.
+  pub fn new<V>(guard: &ContextGuard, value: &V) -> Self
+  where
+    V: Handle<Static = T>,
+  {
+    let value = value.as_raw();
+    let context = guard.context();
+    jsassert!(unsafe { JsAddRef(value, ptr::null_mut()) });
+    Persistent {
+      value,
+      runtime: context.runtime_instance(),
+      context: ManuallyDrop::new(context),
+      phantom: PhantomData,
+    }
+  }
+
+  /// Returns the value, bound to the guard's lifetime.
+  ///
+  /// `None` is returned if the guard belongs to another context than the one
+  /// the handle was created with.
+  pub fn get<'ctx>(&self, guard: &ContextGuard<'ctx>) -> Option<<T as Rebind<'ctx>>::Bound>
+  where
+    T: Rebind<'ctx>,
+  {
+    if guard.context() == *self.context {
+      Some(unsafe { T::rebind(self.value) })
+    } else {
+      None
+    }
+  }
+
+  /// Returns the context the handle was created with.
+  pub fn context(&self) -> Context {
+    Context::clone(&self.context)
+  }
+}
+
+impl<T> Clone for Persistent<T> {
+  fn clone(&self) -> Self {
+    jsassert!(unsafe { JsAddRef(self.value, ptr::null_mut()) });
+    Persistent {
+      value: self.value,
+      context: self.context.clone(),
+      runtime: self.runtime.clone(),
+      phantom: PhantomData,
+    }
+  }
+}
+
+impl<T> fmt::Debug for Persistent<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Persistent({:?})", self.value)
+  }
+}
+
+impl<T> Drop for Persistent<T> {
+  /// Decrements the reference counter, in the handle's context.
+  fn drop(&mut self) {
+    // Without its runtime, the value cannot be released safely
+    let value = self.value;
+    let released = self.runtime.is_some()
+      && Context::exec_with_context(&self.context, |_| util::release_reference(value)).is_ok();
+
+    if released {
+      unsafe { ManuallyDrop::drop(&mut self.context) };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::value::{Object, Persistent};
+  use crate::{script, test, Context, Property, Runtime};
+  use std::thread;
+
+  #[test]
+  fn outlive_guard() {
+    let (runtime, context) = test::setup_env();
+    let persistent: Persistent<Object<'static>> = context
+      .exec_with(|guard| {
+        let object = script::eval(guard, "({ foo: 'bar' })").unwrap();
+        Persistent::new(guard, &object.into_object().unwrap())
+      })
+      .unwrap();
+
+    // Ensure the object is not collected once it's unreachable from script
+    runtime.collect().unwrap();
+
+    let other = Context::new(&runtime).unwrap();
+    assert!(other
+      .exec_with(|guard| persistent.get(guard).is_none())
+      .unwrap());
+
+    context
+      .exec_with(|guard| {
+        let object = persistent.clone().get(guard).unwrap();
+        let foo = object.get(guard, Property::new(guard, "foo"));
+        assert_eq!(foo.to_string(guard), "bar");
+      })
+      .unwrap();
+  }
+
+  #[test]
+  fn outlive_runtime() {
+    let persistent: Persistent<Object<'static>> = {
+      let runtime = Runtime::new().unwrap();
+      let context = Context::new(&runtime).unwrap();
+      context
+        .exec_with(|guard| Persistent::new(guard, &Object::new(guard)))
+        .unwrap()
+    };
+
+    // The runtime is disposed once the handle is dropped
+    drop(persistent);
+  }
+
+  #[test]
+  fn drop_while_runtime_active() {
+    test::run_with_context(|guard| {
+      let persistent = Persistent::new(guard, &Object::new(guard));
+
+      // The context cannot be activated in another thread, so it's leaked
+      thread::spawn(move || drop(persistent)).join().unwrap();
+    });
+  }
+}
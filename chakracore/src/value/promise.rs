@@ -1,21 +1,23 @@
+use crate::intrinsics::{self, Intrinsic};
+use crate::scope::Root;
 use crate::value::{Function, Object, Value};
-use crate::{util::jsfunc, Context, ContextGuard, Result};
+use crate::{ContextGuard, Result};
 use chakracore_sys::*;
 
 /// A JavaScript promise executor.
-pub struct Executor {
-  resolve: Function,
-  reject: Function,
+pub struct Executor<'ctx> {
+  resolve: Function<'ctx>,
+  reject: Function<'ctx>,
 }
 
-impl Executor {
+impl<'ctx> Executor<'ctx> {
   /// Consumes the `Executor` and fulfills the associated promise.
-  pub fn resolve(self, guard: &ContextGuard, arguments: &[&Value]) -> Result<()> {
+  pub fn resolve(self, guard: &ContextGuard<'ctx>, arguments: &[&Value<'ctx>]) -> Result<()> {
     self.resolve.call(guard, arguments).map(|_| ())
   }
 
   /// Consumes the `Executor` and rejects the associated promise.
-  pub fn reject(self, guard: &ContextGuard, arguments: &[&Value]) -> Result<()> {
+  pub fn reject(self, guard: &ContextGuard<'ctx>, arguments: &[&Value<'ctx>]) -> Result<()> {
     self.reject.call(guard, arguments).map(|_| ())
   }
 }
@@ -24,11 +26,11 @@ impl Executor {
 ///
 /// To support promises within a context, see
 /// [Context](../context/struct.Context.html).
-pub struct Promise(JsValueRef);
+pub struct Promise<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Promise {
+impl<'ctx> Promise<'ctx> {
   /// Creates a new promise with an associated executor.
-  pub fn new(_guard: &ContextGuard) -> (Self, Executor) {
+  pub fn new(_guard: &ContextGuard<'ctx>) -> (Self, Executor<'ctx>) {
     let mut reference = JsValueRef::new();
     let mut resolve = JsValueRef::new();
     let mut reject = JsValueRef::new();
@@ -45,21 +47,8 @@ impl Promise {
   }
 
   /// Returns true if the value is a `Promise`.
-  pub fn is_same<V: AsRef<Value>>(value: V) -> bool {
-    // See: https://github.com/Microsoft/ChakraCore/issues/135
-    // There is no straight foward way to do this with the current API.
-    value
-      .as_ref()
-      .clone()
-      .into_object()
-      .map_or(false, |object| {
-        Context::exec_with_value(&object, |guard| {
-          let promise = jsfunc(guard, "Promise").expect("retrieving Promise constructor");
-          object.instance_of(guard, &promise)
-        })
-        .expect("changing active context for Promise comparison")
-        .expect("missing associated context for Promise comparison")
-      })
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
+    intrinsics::instance_of(value.as_ref(), Intrinsic::Promise)
   }
 }
 
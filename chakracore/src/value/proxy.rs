@@ -0,0 +1,445 @@
+//! A JavaScript proxy and its handler.
+use crate::intrinsics::{self, Intrinsic};
+use crate::scope::Root;
+use crate::value::function::{CallbackInfo, CallbackResult};
+use crate::value::{self, Array, External, Function, Object, Value};
+use crate::{util, ContextGuard, Property};
+use chakracore_sys::*;
+
+/// The result of a proxy trap, where an error is thrown as an exception.
+pub type TrapResult<'ctx, T> = ::std::result::Result<T, Value<'ctx>>;
+
+/// A handler intercepting operations on a `Proxy`.
+///
+/// Each method corresponds to a trap of the JavaScript handler object. The
+/// default implementations forward the operation to the target, using the
+/// built-in `Reflect` functions, so only the intercepted operations need to be
+/// implemented.
+///
+/// Keys are either strings or symbols.
+pub trait ProxyHandler: Send + 'static {
+  /// Intercepts retrieving a property's value.
+  fn get<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+    key: &Value<'ctx>,
+    receiver: &Value<'ctx>,
+  ) -> CallbackResult<'ctx> {
+    reflect(guard, Intrinsic::ReflectGet, &[target, key, receiver])
+  }
+
+  /// Intercepts setting a property's value. Returns whether the assignment
+  /// succeeded.
+  fn set<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+    key: &Value<'ctx>,
+    value: &Value<'ctx>,
+    receiver: &Value<'ctx>,
+  ) -> TrapResult<'ctx, bool> {
+    reflect(
+      guard,
+      Intrinsic::ReflectSet,
+      &[target, key, value, receiver],
+    )
+    .map(|result| result.to_bool(guard))
+  }
+
+  /// Intercepts the `in` operator.
+  fn has<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+    key: &Value<'ctx>,
+  ) -> TrapResult<'ctx, bool> {
+    reflect(guard, Intrinsic::ReflectHas, &[target, key]).map(|result| result.to_bool(guard))
+  }
+
+  /// Intercepts the `delete` operator. Returns whether the property was
+  /// deleted.
+  fn delete_property<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+    key: &Value<'ctx>,
+  ) -> TrapResult<'ctx, bool> {
+    reflect(guard, Intrinsic::ReflectDeleteProperty, &[target, key])
+      .map(|result| result.to_bool(guard))
+  }
+
+  /// Intercepts enumeration of the object's own keys (e.g `Object.keys()`).
+  fn own_keys<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+  ) -> TrapResult<'ctx, Vec<Value<'ctx>>> {
+    reflect(guard, Intrinsic::ReflectOwnKeys, &[target]).map(|keys| {
+      keys
+        .into_array()
+        .expect("Reflect.ownKeys to return an array")
+        .to_vec(guard)
+    })
+  }
+
+  /// Intercepts a function call. This is only invoked if the target is a
+  /// function.
+  fn apply<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+    this: &Value<'ctx>,
+    arguments: &[Value<'ctx>],
+  ) -> CallbackResult<'ctx> {
+    let arguments = Array::from_iter(guard, arguments).into();
+    reflect(guard, Intrinsic::ReflectApply, &[target, this, &arguments])
+  }
+
+  /// Intercepts the `new` operator. This is only invoked if the target is a
+  /// constructor.
+  fn construct<'ctx>(
+    &self,
+    guard: &ContextGuard<'ctx>,
+    target: &Object<'ctx>,
+    arguments: &[Value<'ctx>],
+    new_target: &Value<'ctx>,
+  ) -> CallbackResult<'ctx> {
+    let arguments = Array::from_iter(guard, arguments).into();
+    reflect(
+      guard,
+      Intrinsic::ReflectConstruct,
+      &[target, &arguments, new_target],
+    )
+  }
+}
+
+/// A JavaScript proxy object.
+///
+/// ```rust
+/// # use chakracore as js;
+/// # let runtime = js::Runtime::new().unwrap();
+/// # let context = js::Context::new(&runtime).unwrap();
+/// # context.exec_with(|guard| {
+/// struct Uppercase;
+///
+/// impl js::value::ProxyHandler for Uppercase {
+///   fn get<'ctx>(
+///     &self,
+///     guard: &js::ContextGuard<'ctx>,
+///     _target: &js::value::Object<'ctx>,
+///     key: &js::value::Value<'ctx>,
+///     _receiver: &js::value::Value<'ctx>,
+///   ) -> js::value::function::CallbackResult<'ctx> {
+///     let key = key.to_string(guard).to_uppercase();
+///     Ok(js::value::String::new(guard, &key).into())
+///   }
+/// }
+///
+/// let target = js::value::Object::new(guard);
+/// let proxy = js::value::Proxy::new(guard, &target, Uppercase);
+/// guard.global().set(guard, js::Property::new(guard, "proxy"), proxy);
+///
+/// let result = js::script::eval(guard, "proxy.foo").unwrap();
+/// assert_eq!(result.to_string(guard), "FOO");
+/// # }).unwrap();
+/// ```
+pub struct Proxy<'ctx>(JsValueRef, Root<'ctx>);
+
+impl<'ctx> Proxy<'ctx> {
+  /// Creates a new proxy for a target, intercepting operations with a
+  /// handler.
+  ///
+  /// The handler is owned by the proxy, and dropped once the proxy is
+  /// collected.
+  pub fn new<O, H>(guard: &ContextGuard<'ctx>, target: O, handler: H) -> Self
+  where
+    O: AsRef<Object<'ctx>>,
+    H: ProxyHandler,
+  {
+    let handler = External::new(guard, Box::new(handler));
+
+    trap::<H, _>(guard, &handler, "get", |handler, guard, target, args| {
+      handler.get(guard, target, &args[1], &args[2])
+    });
+    trap::<H, _>(guard, &handler, "set", |handler, guard, target, args| {
+      let result = handler.set(guard, target, &args[1], &args[2], &args[3])?;
+      Ok(value::Boolean::new(guard, result).into())
+    });
+    trap::<H, _>(guard, &handler, "has", |handler, guard, target, args| {
+      let result = handler.has(guard, target, &args[1])?;
+      Ok(value::Boolean::new(guard, result).into())
+    });
+    trap::<H, _>(
+      guard,
+      &handler,
+      "deleteProperty",
+      |handler, guard, target, args| {
+        let result = handler.delete_property(guard, target, &args[1])?;
+        Ok(value::Boolean::new(guard, result).into())
+      },
+    );
+    trap::<H, _>(guard, &handler, "ownKeys", |handler, guard, target, _| {
+      let keys = handler.own_keys(guard, target)?;
+      Ok(Array::from_iter(guard, keys).into())
+    });
+    trap::<H, _>(guard, &handler, "apply", |handler, guard, target, args| {
+      let arguments = arguments(guard, &args[2])?;
+      handler.apply(guard, target, &args[1], &arguments)
+    });
+    trap::<H, _>(
+      guard,
+      &handler,
+      "construct",
+      |handler, guard, target, args| {
+        let arguments = arguments(guard, &args[1])?;
+        handler.construct(guard, target, &arguments, &args[2])
+      },
+    );
+
+    let proxy = intrinsics::construct(guard, Intrinsic::Proxy, &[target.as_ref(), &handler])
+      .expect("creating proxy");
+    unsafe { Self::from_raw(proxy.as_raw()) }
+  }
+
+  /// Returns the proxy's target, or `None` if the proxy has been revoked.
+  pub fn target(&self, _guard: &ContextGuard<'ctx>) -> Option<Object<'ctx>> {
+    self.properties().map(|(target, _)| target)
+  }
+
+  /// Returns the proxy's handler object, or `None` if the proxy has been
+  /// revoked.
+  ///
+  /// For proxies created with `Proxy::new`, the handler is an `External`
+  /// object wrapping the `ProxyHandler`.
+  pub fn handler(&self, _guard: &ContextGuard<'ctx>) -> Option<Object<'ctx>> {
+    self.properties().map(|(_, handler)| handler)
+  }
+
+  /// Returns true if the value is a `Proxy`.
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
+    let mut is_proxy = false;
+    let mut target = JsValueRef::new();
+    let mut handler = JsValueRef::new();
+    let value = value.as_ref();
+    value.is_object()
+      && unsafe {
+        JsGetProxyProperties(value.as_raw(), &mut is_proxy, &mut target, &mut handler)
+          == JsErrorCode::NoError
+      }
+      && is_proxy
+  }
+
+  /// Returns the proxy's target and handler, unless it has been revoked.
+  fn properties(&self) -> Option<(Object<'ctx>, Object<'ctx>)> {
+    let mut is_proxy = false;
+    let mut target = JsValueRef::new();
+    let mut handler = JsValueRef::new();
+    unsafe {
+      jsassert!(JsGetProxyProperties(
+        self.as_raw(),
+        &mut is_proxy,
+        &mut target,
+        &mut handler
+      ));
+
+      if target == JsValueRef::new() || handler == JsValueRef::new() {
+        None
+      } else {
+        Some((Object::from_raw(target), Object::from_raw(handler)))
+      }
+    }
+  }
+}
+
+/// Installs a trap on a handler object, which dispatches to the
+/// `ProxyHandler` stored as the object's external data.
+///
+/// Missing arguments are passed as `undefined`.
+fn trap<'a, H, F>(guard: &ContextGuard<'a>, handler: &External<'a>, name: &str, callback: F)
+where
+  H: ProxyHandler,
+  F: for<'ctx> Fn(&H, &ContextGuard<'ctx>, &Object<'ctx>, &[Value<'ctx>]) -> CallbackResult<'ctx>
+    + Send
+    + 'static,
+{
+  let function = Function::with_name(
+    guard,
+    name,
+    Box::new(move |guard, info: CallbackInfo<'_>| {
+      let external = info.this.into_external();
+      let handler = external
+        .as_ref()
+        .and_then(|external| external.downcast_ref::<H>())
+        .ok_or_else(|| value::Error::type_error(guard, "Illegal invocation"))?;
+
+      let mut args = info.arguments;
+      args.resize_with(4, || value::undefined(guard));
+      let target = args[0]
+        .clone()
+        .into_object()
+        .ok_or_else(|| value::Error::type_error(guard, "Invalid proxy target"))?;
+      callback(handler, guard, &target, &args)
+    }),
+  );
+  handler.set(guard, Property::new(guard, name), function);
+}
+
+/// Converts an array-like arguments list to a vector.
+fn arguments<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  list: &Value<'ctx>,
+) -> TrapResult<'ctx, Vec<Value<'ctx>>> {
+  list
+    .clone()
+    .into_array()
+    .map(|list| list.to_vec(guard))
+    .ok_or_else(|| value::Error::type_error(guard, "Invalid arguments list").into())
+}
+
+/// Calls a built-in `Reflect` function, rethrowing any exception.
+fn reflect<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  intrinsic: Intrinsic,
+  arguments: &[&Value<'ctx>],
+) -> CallbackResult<'ctx> {
+  let undefined = value::undefined(guard);
+  util::rethrow(
+    guard,
+    || intrinsics::call(guard, intrinsic, &undefined, arguments),
+    |error| value::Error::new(guard, &error.to_string()).into(),
+  )
+}
+
+reference!(Proxy);
+inherit!(Proxy, Object);
+subtype!(Proxy, Value);
+
+#[cfg(test)]
+mod tests {
+  use super::{ProxyHandler, TrapResult};
+  use crate::value::function::CallbackResult;
+  use crate::value::{self, Object, Value};
+  use crate::{script, test, ContextGuard, Property};
+  use std::collections::HashMap;
+  use std::sync::{Arc, Mutex};
+
+  /// A lazily loaded row, with columns computed on access.
+  struct Row {
+    columns: Vec<&'static str>,
+    loads: Arc<Mutex<HashMap<String, usize>>>,
+  }
+
+  impl ProxyHandler for Row {
+    fn get<'ctx>(
+      &self,
+      guard: &ContextGuard<'ctx>,
+      _: &Object<'ctx>,
+      key: &Value<'ctx>,
+      _: &Value<'ctx>,
+    ) -> CallbackResult<'ctx> {
+      let name = key.to_string(guard);
+      if !key.is_string() || !self.columns.contains(&name.as_str()) {
+        return Ok(value::undefined(guard));
+      }
+
+      *self.loads.lock().unwrap().entry(name.clone()).or_insert(0) += 1;
+      Ok(value::String::new(guard, &format!("<{}>", name)).into())
+    }
+
+    fn has<'ctx>(
+      &self,
+      guard: &ContextGuard<'ctx>,
+      _target: &Object<'ctx>,
+      key: &Value<'ctx>,
+    ) -> TrapResult<'ctx, bool> {
+      Ok(self.columns.contains(&key.to_string(guard).as_str()))
+    }
+
+    fn set<'ctx>(
+      &self,
+      guard: &ContextGuard<'ctx>,
+      _: &Object<'ctx>,
+      _: &Value<'ctx>,
+      _: &Value<'ctx>,
+      _: &Value<'ctx>,
+    ) -> TrapResult<'ctx, bool> {
+      Err(value::Error::type_error(guard, "Row is read-only").into())
+    }
+  }
+
+  #[test]
+  fn interception() {
+    test::run_with_context(|guard| {
+      let loads = Arc::new(Mutex::new(HashMap::new()));
+      let target = value::Object::new(guard);
+      let row = Row {
+        columns: vec!["id", "name"],
+        loads: loads.clone(),
+      };
+
+      let proxy = value::Proxy::new(guard, &target, row);
+      guard
+        .global()
+        .set(guard, Property::new(guard, "row"), &proxy);
+
+      let result = script::eval(
+        guard,
+        "[row.name, row.name, 'id' in row, 'other' in row, row.other].join()",
+      )
+      .unwrap();
+      assert_eq!(result.to_string(guard), "<name>,<name>,true,false,");
+      assert_eq!(loads.lock().unwrap()["name"], 2);
+      assert!(!loads.lock().unwrap().contains_key("id"));
+
+      let result = script::eval(guard, "try { row.id = 1; } catch (e) { e.message }").unwrap();
+      assert_eq!(result.to_string(guard), "Row is read-only");
+
+      let value: Value = proxy.clone().into();
+      assert!(value.is_proxy());
+      assert!(!target.is_proxy());
+      assert!(proxy.target(guard).unwrap().strict_equals(guard, &target));
+      let handler: Value = proxy.handler(guard).unwrap().into();
+      assert!(handler.into_external().unwrap().is::<Row>());
+    });
+  }
+
+  #[test]
+  fn forwarding() {
+    struct Forward;
+    impl ProxyHandler for Forward {}
+
+    test::run_with_context(|guard| {
+      let target = script::eval(
+        guard,
+        "function Point(x) { this.x = x; } Point.prototype.sum = function (y) { return this.x + y; }; Point",
+      )
+      .unwrap()
+      .into_function()
+      .unwrap();
+
+      let proxy = value::Proxy::new(guard, &target, Forward);
+      guard
+        .global()
+        .set(guard, Property::new(guard, "Proxied"), proxy);
+
+      let result = script::eval(
+        guard,
+        "var point = new Proxied(1);
+         [point.sum(2), point instanceof Point, Proxied.name, Proxied.call(null, 0) === undefined,
+          Object.keys(Proxied.prototype), delete Proxied.prototype.sum, 'sum' in point].join()",
+      )
+      .unwrap();
+      assert_eq!(result.to_string(guard), "3,true,Point,true,sum,true,false");
+
+      let result = script::eval(
+        guard,
+        "var thrown = { reason: 'custom' };
+         Object.defineProperty(Point.prototype, 'fails', { get() { throw thrown; } });
+         try { point.fails } catch (e) { e === thrown }",
+      )
+      .unwrap();
+      assert!(result.to_bool(guard));
+    });
+  }
+}
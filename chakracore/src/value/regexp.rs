@@ -0,0 +1,91 @@
+use crate::intrinsics::{self, Intrinsic};
+use crate::scope::Root;
+use crate::value::{self, Array, Object, Value};
+use crate::{ContextGuard, Result};
+use chakracore_sys::*;
+
+/// A JavaScript regular expression.
+pub struct RegExp<'ctx>(JsValueRef, Root<'ctx>);
+
+impl<'ctx> RegExp<'ctx> {
+  /// Creates a new regular expression, with flags (e.g `gi`).
+  ///
+  /// A `SyntaxError` exception is returned if the pattern or the flags are
+  /// invalid.
+  pub fn new(guard: &ContextGuard<'ctx>, pattern: &str, flags: &str) -> Result<Self> {
+    let pattern = value::String::new(guard, pattern).into();
+    let flags = value::String::new(guard, flags).into();
+    let regexp = intrinsics::construct(guard, Intrinsic::RegExp, &[&pattern, &flags])?;
+    Ok(unsafe { Self::from_raw(regexp.as_raw()) })
+  }
+
+  /// Executes a search for a match in a string.
+  ///
+  /// Returns the match and its captured groups, or `None` if there is no
+  /// match. This is equivalent to `RegExp.prototype.exec()`, so global and
+  /// sticky expressions update their `lastIndex`.
+  pub fn exec(&self, guard: &ContextGuard<'ctx>, input: &str) -> Result<Option<Array<'ctx>>> {
+    let input = value::String::new(guard, input).into();
+    intrinsics::call(guard, Intrinsic::RegExpExec, self, &[&input])
+      .map(|result| result.into_array())
+  }
+
+  /// Returns true if the value is a `RegExp`, including a `RegExp` of another
+  /// context or one whose prototype has been changed.
+  pub fn is_same<'a, V: AsRef<Value<'a>>>(value: V) -> bool {
+    intrinsics::is_instance(value.as_ref(), Intrinsic::RegExp, Intrinsic::RegExpSource)
+  }
+}
+
+reference!(RegExp);
+inherit!(RegExp, Object);
+subtype!(RegExp, Value);
+
+#[cfg(test)]
+mod tests {
+  use crate::{script, test, value};
+
+  #[test]
+  fn exec() {
+    test::run_with_context(|guard| {
+      let regexp = value::RegExp::new(guard, r"(\d+)-(\d+)", "g").unwrap();
+      let result = regexp.exec(guard, "from 10-20").unwrap().unwrap();
+      assert_eq!(result.get_index(guard, 0).to_string(guard), "10-20");
+      assert_eq!(result.get_index(guard, 2).to_string(guard), "20");
+      assert!(regexp.exec(guard, "from 10-20").unwrap().is_none());
+
+      assert!(value::RegExp::new(guard, "(", "").is_err());
+      assert!(value::RegExp::new(guard, "a", "x").is_err());
+
+      let regexp = script::eval(guard, "/foo/i").unwrap();
+      assert!(regexp.is_regexp());
+      let regexp = regexp.into_regexp().unwrap();
+      assert!(regexp.exec(guard, "FOO").unwrap().is_some());
+    });
+  }
+
+  #[test]
+  fn detection() {
+    test::run_with_context(|guard| {
+      let regexp = script::eval(guard, "/foo/").unwrap();
+      let prototype = script::eval(guard, "RegExp.prototype").unwrap();
+      let inherited = script::eval(guard, "Object.create(RegExp.prototype)").unwrap();
+      let revoked = script::eval(
+        guard,
+        "var proxy = Proxy.revocable(/foo/, {}); proxy.revoke(); proxy.proxy",
+      )
+      .unwrap();
+
+      script::eval(
+        guard,
+        "Object.defineProperty(RegExp, Symbol.hasInstance, { value() { throw 1; } })",
+      )
+      .unwrap();
+      assert!(regexp.is_regexp());
+      assert!(!prototype.is_regexp());
+      assert!(!inherited.is_regexp());
+      assert!(!revoked.is_regexp());
+      assert!(!value::Object::new(guard).is_regexp());
+    });
+  }
+}
@@ -1,13 +1,14 @@
+use crate::scope::Root;
 use crate::value::Value;
 use crate::{context::ContextGuard, util};
 use chakracore_sys::*;
 
 /// A JavaScript string.
-pub struct String(JsValueRef);
+pub struct String<'ctx>(JsValueRef, Root<'ctx>);
 
-impl String {
+impl<'ctx> String<'ctx> {
   /// Creates a string value from a native string.
-  pub fn new(_guard: &ContextGuard, string: &str) -> Self {
+  pub fn new(_guard: &ContextGuard<'ctx>, string: &str) -> Self {
     let mut value = JsValueRef::new();
     unsafe {
       jsassert!(JsCreateString(
@@ -0,0 +1,623 @@
+use crate::intrinsics::{self, Intrinsic};
+use crate::value::{self, Value};
+use crate::{scope, util, ContextGuard, Error, Property, Result};
+use chakracore_sys::*;
+use std::collections::HashMap;
+use std::mem;
+
+/// A primitive, or a reference to one of the transfer's objects.
+#[derive(Clone, Debug, PartialEq)]
+enum Data {
+  Undefined,
+  Null,
+  Boolean(bool),
+  Number(f64),
+  String(String),
+  Reference(usize),
+}
+
+/// A serialized object.
+#[derive(Clone, Debug, PartialEq)]
+enum Entry {
+  Array(Vec<Data>),
+  Object(Vec<(String, Data)>),
+  ArrayBuffer(Vec<u8>),
+  /// A typed array, with its offset and length in bytes.
+  TypedArray {
+    kind: JsTypedArrayType,
+    buffer: usize,
+    offset: u32,
+    length: u32,
+  },
+  DataView {
+    buffer: usize,
+    offset: u32,
+    length: u32,
+  },
+  Map(Vec<(Data, Data)>),
+  Set(Vec<Data>),
+  Date(f64),
+  RegExp {
+    source: String,
+    flags: String,
+  },
+  Error {
+    name: String,
+    message: String,
+  },
+}
+
+/// Data detached from any runtime, used for moving values between runtimes.
+///
+/// Values cannot be shared between runtimes (see `Value::marshal_to`), but a
+/// transfer can be created from a value in one runtime, sent to another
+/// thread if need be, and turned into an equivalent value in another.
+///
+/// The data is serialized using the structured clone algorithm (see
+/// `structured_clone`), except that array buffers are transferred; their
+/// contents are moved and the source buffers are detached.
+///
+/// ```rust
+/// # use chakracore as js;
+/// # let runtime = js::Runtime::new().unwrap();
+/// # let context = js::Context::new(&runtime).unwrap();
+/// let transfer = context
+///   .exec_with(|guard| {
+///     let value = js::script::eval(guard, "({ foo: [1, 'bar'] })").unwrap();
+///     js::value::Transfer::new(guard, &value)
+///   })
+///   .unwrap()
+///   .unwrap();
+///
+/// let runtime = js::Runtime::new().unwrap();
+/// let context = js::Context::new(&runtime).unwrap();
+/// context
+///   .exec_with(|guard| {
+///     let value = transfer.into_value(guard);
+///     assert_eq!(value.to_json(guard).unwrap(), r#"{"foo":[1,"bar"]}"#);
+///   })
+///   .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Transfer {
+  root: Data,
+  objects: Vec<Entry>,
+}
+
+impl Transfer {
+  /// Serializes a value, detaching any array buffers it contains.
+  ///
+  /// Values that cannot be cloned result in `Error::DataClone`, in which case
+  /// no buffer is detached.
+  pub fn new<'ctx>(guard: &ContextGuard<'ctx>, value: &Value<'ctx>) -> Result<Transfer> {
+    Self::serialize(guard, value, true)
+  }
+
+  /// Creates the value in the guard's context.
+  pub fn into_value<'ctx>(self, guard: &ContextGuard<'ctx>) -> Value<'ctx> {
+    // The created objects are stored on the heap
+    scope::unscoped(|| {
+      let mut entries = self.objects;
+
+      // Objects are created before their contents, since they may be cyclic.
+      // Views are created once their buffers exist.
+      let mut objects = entries
+        .iter_mut()
+        .map(|entry| create(guard, entry))
+        .collect::<Vec<_>>();
+
+      for index in 0..entries.len() {
+        if objects[index].is_none() {
+          let view = create_view(&entries[index], &objects);
+          objects[index] = Some(view);
+        }
+      }
+
+      let objects = objects
+        .into_iter()
+        .map(|object| object.expect("creating object"))
+        .collect::<Vec<_>>();
+
+      let resolve = |data: &Data| -> Value<'ctx> {
+        match data {
+          Data::Undefined => value::undefined(guard),
+          Data::Null => value::null(guard),
+          Data::Boolean(boolean) => value::Boolean::new(guard, *boolean).into(),
+          Data::Number(number) => value::Number::from_double(guard, *number).into(),
+          Data::String(string) => value::String::new(guard, string).into(),
+          Data::Reference(index) => objects[*index].clone().into(),
+        }
+      };
+
+      for (entry, object) in entries.iter().zip(&objects) {
+        match entry {
+          Entry::Array(elements) => {
+            for (index, element) in elements.iter().enumerate() {
+              object.set_index(guard, index as u32, resolve(element));
+            }
+          },
+          Entry::Object(properties) => {
+            for (key, property) in properties {
+              object.set(guard, Property::new(guard, key), resolve(property));
+            }
+          },
+          Entry::Map(entries) => {
+            let map = Value::from(object.clone())
+              .into_map()
+              .expect("creating map");
+            for (key, value) in entries {
+              map
+                .set(guard, resolve(key), resolve(value))
+                .expect("adding map entry");
+            }
+          },
+          Entry::Set(values) => {
+            let set = Value::from(object.clone())
+              .into_set()
+              .expect("creating set");
+            for value in values {
+              set.add(guard, resolve(value)).expect("adding set value");
+            }
+          },
+          _ => (),
+        }
+      }
+
+      resolve(&self.root)
+    })
+  }
+
+  /// Serializes a value, optionally detaching any array buffers.
+  fn serialize<'ctx>(
+    guard: &ContextGuard<'ctx>,
+    value: &Value<'ctx>,
+    transfer: bool,
+  ) -> Result<Transfer> {
+    // The serialized values are stored on the heap
+    scope::unscoped(|| {
+      let mut serializer = Serializer {
+        guard,
+        memory: HashMap::new(),
+        values: Vec::new(),
+        buffers: Vec::new(),
+        objects: Vec::new(),
+      };
+
+      let root = serializer.serialize(value)?;
+      if transfer {
+        for buffer in serializer.buffers {
+          jsassert!(unsafe { JsDetachArrayBuffer(buffer.as_raw()) });
+        }
+      }
+
+      Ok(Transfer {
+        root,
+        objects: serializer.objects,
+      })
+    })
+  }
+}
+
+/// Creates a deep copy of a value, using the structured clone algorithm.
+///
+/// In contrast to a JSON round trip, references between objects (including
+/// cycles) are preserved, and besides primitives, arrays and plain objects
+/// (of which only own enumerable properties are copied) the following types
+/// are supported: `Map`, `Set`, `Date`, `RegExp`, `ArrayBuffer`, typed
+/// arrays, `DataView` and errors.
+///
+/// Functions, symbols, externals, proxies and promises cannot be cloned and
+/// result in `Error::DataClone`.
+pub fn structured_clone<'ctx>(
+  guard: &ContextGuard<'ctx>,
+  value: &Value<'ctx>,
+) -> Result<Value<'ctx>> {
+  Transfer::serialize(guard, value, false).map(|transfer| transfer.into_value(guard))
+}
+
+/// Defines `structuredClone` on the global object.
+///
+/// Values that cannot be cloned throw an error named `DataCloneError`.
+pub fn define_structured_clone(guard: &ContextGuard) {
+  let function = value::Function::with_name(
+    guard,
+    "structuredClone",
+    Box::new(|guard, info| {
+      let value = info
+        .arguments
+        .first()
+        .cloned()
+        .unwrap_or_else(|| value::undefined(guard));
+
+      let clone = || structured_clone(guard, &value);
+      util::rethrow(guard, clone, |error| match error {
+        Error::DataClone(message) => {
+          let exception = value::Error::new(guard, &message);
+          let name = value::String::new(guard, "DataCloneError");
+          exception.set(guard, Property::new(guard, "name"), name);
+          exception.into()
+        },
+        error => value::Error::new(guard, &error.to_string()).into(),
+      })
+    }),
+  );
+
+  let property = Property::new(guard, "structuredClone");
+  guard.global().set(guard, property, function);
+}
+
+/// Creates an empty object for an entry, or `None` for views.
+fn create<'ctx>(guard: &ContextGuard<'ctx>, entry: &mut Entry) -> Option<value::Object<'ctx>> {
+  Some(match entry {
+    Entry::Array(elements) => value::Array::new(guard, elements.len() as u32).into(),
+    Entry::Object(_) => value::Object::new(guard),
+    Entry::ArrayBuffer(data) => value::ArrayBuffer::with_data(guard, mem::take(data)).into(),
+    Entry::TypedArray { .. } | Entry::DataView { .. } => return None,
+    Entry::Map(_) => value::Map::new(guard).into(),
+    Entry::Set(_) => value::Set::new(guard).into(),
+    Entry::Date(millis) => value::Date::from_millis(guard, *millis).into(),
+    Entry::RegExp { source, flags } => value::RegExp::new(guard, source, flags)
+      .expect("creating regular expression")
+      .into(),
+    Entry::Error { name, message } => match name.as_str() {
+      "EvalError" => {
+        let message = value::String::new(guard, message).into();
+        return intrinsics::construct(guard, Intrinsic::EvalError, &[&message])
+          .expect("creating error")
+          .into_object();
+      },
+      "RangeError" => value::Error::range_error(guard, message).into(),
+      "ReferenceError" => value::Error::reference_error(guard, message).into(),
+      "SyntaxError" => value::Error::syntax_error(guard, message).into(),
+      "TypeError" => value::Error::type_error(guard, message).into(),
+      "URIError" => value::Error::uri_error(guard, message).into(),
+      _ => value::Error::new(guard, message).into(),
+    },
+  })
+}
+
+/// Creates a view of a buffer that has already been created.
+fn create_view<'ctx>(
+  entry: &Entry,
+  objects: &[Option<value::Object<'ctx>>],
+) -> value::Object<'ctx> {
+  let buffer = |index: usize| {
+    objects[index]
+      .as_ref()
+      .expect("creating view of buffer")
+      .as_raw()
+  };
+
+  let mut view = JsValueRef::new();
+  match *entry {
+    Entry::TypedArray {
+      kind,
+      buffer: index,
+      offset,
+      length,
+    } => {
+      let length = length / element_size(kind);
+      jsassert!(unsafe { JsCreateTypedArray(kind, buffer(index), offset, length, &mut view) });
+    },
+    Entry::DataView {
+      buffer: index,
+      offset,
+      length,
+    } => {
+      jsassert!(unsafe { JsCreateDataView(buffer(index), offset, length, &mut view) });
+    },
+    _ => unreachable!(),
+  }
+  unsafe { value::Object::from_raw(view) }
+}
+
+/// Returns the size of a typed array's elements, in bytes.
+fn element_size(kind: JsTypedArrayType) -> u32 {
+  match kind {
+    JsTypedArrayType::Int8 | JsTypedArrayType::Uint8 | JsTypedArrayType::Uint8Clamped => 1,
+    JsTypedArrayType::Int16 | JsTypedArrayType::Uint16 => 2,
+    JsTypedArrayType::Int32 | JsTypedArrayType::Uint32 | JsTypedArrayType::Float32 => 4,
+    JsTypedArrayType::Float64 => 8,
+  }
+}
+
+/// Serializes values into the entries of a transfer.
+struct Serializer<'a, 'ctx> {
+  guard: &'a ContextGuard<'ctx>,
+  /// The index of each visited object.
+  memory: HashMap<JsValueRef, usize>,
+  /// Keeps the visited objects alive, so their references remain unique.
+  values: Vec<Value<'ctx>>,
+  buffers: Vec<value::ArrayBuffer<'ctx>>,
+  objects: Vec<Entry>,
+}
+
+impl<'a, 'ctx> Serializer<'a, 'ctx> {
+  /// Serializes a value, and any objects it references.
+  fn serialize(&mut self, value: &Value<'ctx>) -> Result<Data> {
+    let guard = self.guard;
+    Ok(match value.get_type() {
+      JsValueType::Undefined => Data::Undefined,
+      JsValueType::Null => Data::Null,
+      JsValueType::Boolean => Data::Boolean(value.to_bool(guard)),
+      JsValueType::Number => Data::Number(value.to_double(guard)),
+      JsValueType::String => Data::String(value.to_string(guard)),
+      JsValueType::Function => return Err(uncloneable("function")),
+      JsValueType::Symbol => return Err(uncloneable("symbol")),
+      _ => return self.serialize_object(value),
+    })
+  }
+
+  /// Serializes an object, unless it has already been visited.
+  fn serialize_object(&mut self, value: &Value<'ctx>) -> Result<Data> {
+    if let Some(index) = self.memory.get(&value.as_raw()) {
+      return Ok(Data::Reference(*index));
+    }
+
+    let index = self.objects.len();
+    self.memory.insert(value.as_raw(), index);
+    self.values.push(value.clone());
+
+    // Reserve the object's index, in case it references itself
+    self.objects.push(Entry::Array(Vec::new()));
+
+    let guard = self.guard;
+    let entry = match value.get_type() {
+      JsValueType::Array => {
+        let array = value.clone().into_array().expect("converting array");
+        let elements = array
+          .iter(guard)
+          .map(|element| self.serialize(&element))
+          .collect::<Result<_>>()?;
+        Entry::Array(elements)
+      },
+      JsValueType::ArrayBuffer => {
+        let buffer = value
+          .clone()
+          .into_array_buffer()
+          .expect("converting array buffer");
+        let data = buffer.as_slice().to_vec();
+        self.buffers.push(buffer);
+        Entry::ArrayBuffer(data)
+      },
+      JsValueType::TypedArray => {
+        let mut kind = JsTypedArrayType::Int8;
+        let mut buffer = JsValueRef::new();
+        let (mut offset, mut length) = (0, 0);
+        jsassert!(unsafe {
+          JsGetTypedArrayInfo(
+            value.as_raw(),
+            &mut kind,
+            &mut buffer,
+            &mut offset,
+            &mut length,
+          )
+        });
+
+        Entry::TypedArray {
+          kind,
+          buffer: self.serialize_buffer(buffer)?,
+          offset,
+          length,
+        }
+      },
+      JsValueType::DataView => {
+        let mut buffer = JsValueRef::new();
+        let (mut offset, mut length) = (0, 0);
+        jsassert!(unsafe {
+          JsGetDataViewInfo(value.as_raw(), &mut buffer, &mut offset, &mut length)
+        });
+
+        Entry::DataView {
+          buffer: self.serialize_buffer(buffer)?,
+          offset,
+          length,
+        }
+      },
+      JsValueType::Error => {
+        let error = value.clone().into_object().expect("converting error");
+        let name = error.get(guard, Property::new(guard, "name"));
+        let message = Property::new(guard, "message");
+        Entry::Error {
+          name: name.to_string(guard),
+          message: if error.has_own_property(guard, &message) {
+            error.get(guard, &message).to_string(guard)
+          } else {
+            String::new()
+          },
+        }
+      },
+      _ if value.is_external() => return Err(uncloneable("external")),
+      _ if value.is_proxy() => return Err(uncloneable("proxy")),
+      _ if value.is_promise() => return Err(uncloneable("promise")),
+      _ => {
+        if let Some(map) = value.clone().into_map() {
+          let entries = map
+            .iter(guard)?
+            .map(|entry| {
+              let (key, value) = entry?;
+              Ok((self.serialize(&key)?, self.serialize(&value)?))
+            })
+            .collect::<Result<_>>()?;
+          Entry::Map(entries)
+        } else if let Some(set) = value.clone().into_set() {
+          let values = set
+            .iter(guard)?
+            .map(|value| self.serialize(&value?))
+            .collect::<Result<_>>()?;
+          Entry::Set(values)
+        } else if let Some(date) = value.clone().into_date() {
+          Entry::Date(date.millis(guard)?)
+        } else if value.is_regexp() {
+          let regexp = intrinsics::call(guard, Intrinsic::RegExpToString, value, &[])?;
+          let regexp = regexp.to_string(guard);
+
+          // The source is always escaped, so the last slash precedes the flags
+          let separator = regexp.rfind('/').expect("regular expression separator");
+          Entry::RegExp {
+            source: regexp[1..separator].to_string(),
+            flags: regexp[separator + 1..].to_string(),
+          }
+        } else {
+          let object = value.clone().into_object().expect("converting object");
+          let properties = object
+            .entries(guard)
+            .map(|(key, property)| Ok((key.to_string(guard), self.serialize(&property)?)))
+            .collect::<Result<_>>()?;
+          Entry::Object(properties)
+        }
+      },
+    };
+
+    self.objects[index] = entry;
+    Ok(Data::Reference(index))
+  }
+
+  /// Serializes the buffer of a view, and returns its index.
+  fn serialize_buffer(&mut self, buffer: JsValueRef) -> Result<usize> {
+    let buffer = unsafe { Value::from_raw(buffer) };
+    match self.serialize_object(&buffer)? {
+      Data::Reference(index) => Ok(index),
+      _ => unreachable!(),
+    }
+  }
+}
+
+/// Returns an error for a value that cannot be cloned.
+fn uncloneable(kind: &str) -> Error {
+  Error::DataClone(format!("{} could not be cloned", kind))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::value::{self, Transfer};
+  use crate::{script, test, Error, Property};
+  use matches::assert_matches;
+
+  #[test]
+  fn between_runtimes() {
+    let (_source_runtime, source) = test::setup_env();
+    let (transfer, length) = source
+      .exec_with(|guard| {
+        let value = script::eval(
+          guard,
+          "var buffer = new ArrayBuffer(4);
+           new Uint8Array(buffer)[1] = 7;
+           var data = { list: [1, 'two', null, undefined, true], buffer: buffer };
+           data.self = data;
+           data",
+        )
+        .unwrap();
+
+        let transfer = Transfer::new(guard, &value).unwrap();
+        let length = script::eval(guard, "buffer.byteLength").unwrap();
+        (transfer, length.to_integer(guard))
+      })
+      .unwrap();
+
+    // The buffer has been detached from the source
+    assert_eq!(length, 0);
+
+    let (_target_runtime, target) = test::setup_env();
+    target
+      .exec_with(|guard| {
+        let value = transfer.into_value(guard);
+        guard
+          .global()
+          .set(guard, Property::new(guard, "data"), value);
+
+        let result = script::eval(
+          guard,
+          "[data.self === data, JSON.stringify(data.list), new Uint8Array(data.buffer)[1]].join()",
+        )
+        .unwrap();
+        assert_eq!(
+          result.to_string(guard),
+          r#"true,[1,"two",null,null,true],7"#
+        );
+      })
+      .unwrap();
+  }
+
+  #[test]
+  fn structured_clone() {
+    test::run_with_context(|guard| {
+      value::define_structured_clone(guard);
+      let result = script::eval(
+        guard,
+        r"var buffer = new ArrayBuffer(8);
+          var source = {
+            map: new Map([[1, 'one'], ['key', { nested: true }]]),
+            set: new Set(['a', 'b']),
+            date: new Date(1000),
+            regexp: /a\/b/gi,
+            error: new RangeError('out of range'),
+            bytes: new Uint16Array(buffer, 2, 2),
+            view: new DataView(buffer, 4),
+          };
+          source.bytes[0] = 513;
+          source.cycle = source;
+          source.set.add(source.map);
+
+          var copy = structuredClone(source);
+          [
+            copy !== source && copy.cycle === copy,
+            copy.map.get(1) + copy.map.get('key').nested,
+            copy.set.has('b') && copy.set.has(copy.map) && !copy.set.has(source.map),
+            copy.date.getTime(),
+            copy.regexp.source + copy.regexp.flags,
+            copy.error instanceof RangeError && copy.error.message,
+            copy.bytes.length + copy.bytes[0],
+            copy.bytes.buffer === copy.view.buffer && copy.view.byteOffset,
+            buffer.byteLength,
+          ].join()",
+      )
+      .unwrap();
+
+      assert_eq!(
+        result.to_string(guard),
+        r"true,onetrue,true,1000,a\/bgi,out of range,515,4,8"
+      );
+
+      let error = script::eval(
+        guard,
+        "try { structuredClone(() => 1) } catch (e) { e.name }",
+      );
+      assert_eq!(error.unwrap().to_string(guard), "DataCloneError");
+
+      let error = script::eval(
+        guard,
+        "var thrown = { reason: 'custom' };
+         var regexp = /a/;
+         Object.defineProperty(regexp, 'source', { get() { throw thrown; } });
+         try { structuredClone(regexp) } catch (e) { e === thrown }",
+      );
+      assert!(error.unwrap().to_bool(guard));
+    });
+  }
+
+  #[test]
+  fn uncloneable() {
+    test::run_with_context(|guard| {
+      let error = |code| {
+        let value = script::eval(guard, code).unwrap();
+        value::structured_clone(guard, &value).unwrap_err()
+      };
+
+      assert_matches!(error("({ foo: () => 1 })"), Error::DataClone(_));
+      assert_matches!(error("[Symbol()]"), Error::DataClone(_));
+      assert_matches!(error("new Proxy({}, {})"), Error::DataClone(_));
+      assert_matches!(error("Promise.resolve()"), Error::DataClone(_));
+
+      let external = value::External::new(guard, Box::new(10));
+      assert_matches!(Transfer::new(guard, &external), Err(Error::DataClone(_)));
+
+      // No buffer is detached if the transfer fails
+      let value =
+        script::eval(guard, "var buffer = new ArrayBuffer(4); [buffer, () => 1]").unwrap();
+      assert!(Transfer::new(guard, &value).is_err());
+      let length = script::eval(guard, "buffer.byteLength").unwrap();
+      assert_eq!(length.to_integer(guard), 4);
+    });
+  }
+}
@@ -1,4 +1,5 @@
-use crate::{util, value, Context, ContextGuard, Result};
+use crate::scope::Root;
+use crate::{json, util, util::jstry, value, Context, ContextGuard, Error, Result};
 use chakracore_sys::*;
 use std::{fmt, mem};
 
@@ -15,7 +16,7 @@ macro_rules! downcast {
     downcast!($predicate, $predicate_doc, $result);
 
     #[doc=$conversion_doc]
-    pub fn $conversion(self) -> Option<super::$result> {
+    pub fn $conversion(self) -> Option<super::$result<'ctx>> {
       if self.$predicate() {
         Some(unsafe { mem::transmute(self) })
       } else {
@@ -28,7 +29,7 @@ macro_rules! downcast {
 macro_rules! nativecast {
   ($name:ident, $name_doc:expr, $result:ident, $into:ident, $represent:ident, $native:ident) => {
     #[doc=$name_doc]
-    pub fn $name(&self, _guard: &ContextGuard) -> $result {
+    pub fn $name(&self, _guard: &ContextGuard<'ctx>) -> $result {
       match self.clone().$into() {
         None => self.$represent(_guard),
         Some(value) => value,
@@ -40,7 +41,7 @@ macro_rules! nativecast {
 macro_rules! representation {
   ($name:ident, $name_doc:expr, $result:ident, $function:ident) => {
     #[doc=$name_doc]
-    pub fn $name(&self, _guard: &ContextGuard) -> super::$result {
+    pub fn $name(&self, _guard: &ContextGuard<'ctx>) -> super::$result<'ctx> {
       let mut value = JsValueRef::new();
       unsafe {
         jsassert!($function(self.as_raw(), &mut value));
@@ -52,15 +53,18 @@ macro_rules! representation {
 
 /// A JavaScript value, base class for all types.
 ///
-/// All values are tied to a specific context and should not be reused in
-/// between.
+/// All values are tied to a specific context, and they are branded with the
+/// lifetime of the `ContextGuard` they were created with (`'ctx`). They can
+/// therefore not be used with the guard of another context, nor escape the
+/// closure the guard was passed to. Use a `Persistent` handle to keep a value
+/// beyond the guard's scope, or `marshal_to` to use it within another
+/// context.
 ///
 /// The underlying object is represented as a `JsValueRef`, a reference to a
 /// ChakraCore value.
 ///
-/// This type implements the `Debug` trait, but it should be used carefully. It
-/// assumes there is an active context (the same context other the value was
-/// created with).
+/// This type implements the `Debug` trait, but it should only be used for
+/// debugging. It activates the value's context if required.
 ///
 /// Do not get intimidated by all the conversion methods. They are very easy to
 /// grok — there are three different types:
@@ -82,9 +86,9 @@ macro_rules! representation {
 /// `into_*() -> [*_representation()] -> value()`. A call to `*_representation`
 /// is only performed if required (i.e a string is not redundantly converted to
 /// a string).
-pub struct Value(JsValueRef);
+pub struct Value<'ctx>(JsValueRef, Root<'ctx>);
 
-impl Value {
+impl<'ctx> Value<'ctx> {
   // Transforms a value to another custom type
   downcast!(
     is_undefined,
@@ -155,6 +159,41 @@ impl Value {
     "Represent the value as a `Promise`. Does not affect the underlying value.",
     Promise
   );
+  downcast!(
+    is_proxy,
+    "Returns true if this value is a `Proxy`.",
+    into_proxy,
+    "Represent the value as a `Proxy`. Does not affect the underlying value.",
+    Proxy
+  );
+  downcast!(
+    is_map,
+    "Returns true if this value is a `Map`.",
+    into_map,
+    "Represent the value as a `Map`. Does not affect the underlying value.",
+    Map
+  );
+  downcast!(
+    is_set,
+    "Returns true if this value is a `Set`.",
+    into_set,
+    "Represent the value as a `Set`. Does not affect the underlying value.",
+    Set
+  );
+  downcast!(
+    is_date,
+    "Returns true if this value is a `Date`.",
+    into_date,
+    "Represent the value as a `Date`. Does not affect the underlying value.",
+    Date
+  );
+  downcast!(
+    is_regexp,
+    "Returns true if this value is a `RegExp`.",
+    into_regexp,
+    "Represent the value as a `RegExp`. Does not affect the underlying value.",
+    RegExp
+  );
 
   // Converts a value to a native type
   nativecast!(
@@ -192,18 +231,16 @@ impl Value {
 
   /// Converts the value to a native string, containing the value's JSON
   /// representation.
-  pub fn to_json(&self, guard: &ContextGuard) -> Result<String> {
-    // TODO: Use native functionality when implemented
-    let stringify =
-      util::jsfunc(guard, "JSON.stringify").expect("retrieving JSON.stringify function");
-    stringify.call(guard, &[self]).map(|v| v.to_string(guard))
+  ///
+  /// Values without a JSON representation are returned as `undefined`. See
+  /// the `json` module for more options.
+  pub fn to_json(&self, guard: &ContextGuard<'ctx>) -> Result<String> {
+    json::stringify(guard, self).map(|json| json.unwrap_or_else(|| "undefined".to_string()))
   }
 
   /// Parses JSON and returns it represented as a JavaScript value.
-  pub fn from_json(guard: &ContextGuard, json: &str) -> Result<Value> {
-    let parse = util::jsfunc(guard, "JSON.parse").expect("retrieving JSON.parse function");
-    let json = value::String::new(guard, json);
-    parse.call(guard, &[&json])
+  pub fn from_json(guard: &ContextGuard<'ctx>, json: &str) -> Result<Value<'ctx>> {
+    json::parse(guard, json)
   }
 
   // Casts a value to the JavaScript expression of another type
@@ -241,43 +278,105 @@ impl Value {
     value_type
   }
 
+  /// Returns a handle to the value, for use within another context.
+  ///
+  /// Values can be shared between contexts of the same runtime. An error is
+  /// returned if the context belongs to another runtime; use a `Transfer` to
+  /// move data between runtimes instead.
+  pub fn marshal_to(&self, context: &Context) -> Result<value::Persistent<Value<'static>>> {
+    match Context::from_value(self) {
+      Some(source) if !source.is_same_runtime(context) => Err(Error::RuntimeMismatch),
+      Some(_) => {
+        Context::exec_with_context(context, |guard| value::Persistent::new(guard, self))
+      },
+      // Values without an associated context (e.g integers) cannot be proven
+      // to belong to the runtime, so they are copied instead
+      None => Context::exec_with_context(context, |guard| {
+        let copy = self.copy_primitive(guard)?;
+        Ok(value::Persistent::new(guard, &copy))
+      })?,
+    }
+  }
+
+  /// Copies a primitive value into the guard's context.
+  fn copy_primitive<'a>(&self, guard: &ContextGuard<'a>) -> Result<Value<'a>> {
+    let value = self.clone();
+    if value.is_undefined() {
+      Ok(value::undefined(guard))
+    } else if value.is_null() {
+      Ok(value::null(guard))
+    } else if let Some(number) = value.clone().into_number() {
+      Ok(value::Number::from_double(guard, number.value_double()).into())
+    } else if let Some(boolean) = value.clone().into_boolean() {
+      Ok(value::Boolean::new(guard, boolean.value()).into())
+    } else if value.is_string() {
+      let string = util::to_string_impl(value.as_raw(), JsCopyString)?;
+      Ok(value::String::new(guard, &string).into())
+    } else {
+      Err(Error::RuntimeMismatch)
+    }
+  }
+
   /// Compare two values for equality (`==`).
-  pub fn equals<V: AsRef<Value>>(&self, _guard: &ContextGuard, other: V) -> bool {
+  pub fn equals<V: AsRef<Value<'ctx>>>(&self, _guard: &ContextGuard<'ctx>, other: V) -> bool {
     let mut result = false;
     jsassert!(unsafe { JsEquals(self.as_raw(), other.as_ref().as_raw(), &mut result) });
     result
   }
 
   /// Compare two values for strict equality (`===`).
-  pub fn strict_equals<V: AsRef<Value>>(&self, _guard: &ContextGuard, other: V) -> bool {
+  pub fn strict_equals<V: AsRef<Value<'ctx>>>(&self, _guard: &ContextGuard<'ctx>, other: V) -> bool {
     let mut result = false;
     jsassert!(unsafe { JsStrictEquals(self.as_raw(), other.as_ref().as_raw(), &mut result) });
     result
   }
 }
 
-impl PartialEq for Value {
-  /// Use carefully (prefer `strict_equals`), this relies on an implicitly
-  /// active context.
-  fn eq(&self, other: &Value) -> bool {
-    Context::exec_with_current(|guard| self.strict_equals(guard, other))
-      .expect("comparison to have an active context")
+impl<'ctx> PartialEq for Value<'ctx> {
+  /// Use carefully (prefer `strict_equals`), this compares the values in the
+  /// value's own context, or the active one. If neither is available, the
+  /// references themselves are compared.
+  fn eq(&self, other: &Value<'ctx>) -> bool {
+    with_context(self, || {
+      let mut result = false;
+      jstry(unsafe { JsStrictEquals(self.as_raw(), other.as_raw(), &mut result) })?;
+      Ok(result)
+    })
+    .unwrap_or_else(|| self.as_raw() == other.as_raw())
   }
 }
 
-impl fmt::Debug for Value {
-  /// Only use for debugging, it relies on an implicitly active context.
+impl<'ctx> fmt::Debug for Value<'ctx> {
+  /// Only use for debugging, the value is converted to a string in its own
+  /// context, or the active one. If this fails, only its type is shown.
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    Context::exec_with_current(|guard| {
-      let output = self.to_string(&guard);
+    let value_type = self.get_type();
+    let output = with_context(self, || {
+      let mut string = JsValueRef::new();
+      jstry(unsafe { JsConvertValueToString(self.as_raw(), &mut string) })?;
+      util::to_string_impl(string, JsCopyString)
+    });
 
-      let value_type = self.get_type();
-      match value_type {
-        JsValueType::String => write!(f, "Value({:?}: '{}')", value_type, output),
-        _ => write!(f, "Value({:?}: {})", value_type, output),
-      }
-    })
-    .expect("value debug output without an active context")
+    match output {
+      Some(output) if value_type == JsValueType::String => {
+        write!(f, "Value({:?}: '{}')", value_type, output)
+      },
+      Some(output) => write!(f, "Value({:?}: {})", value_type, output),
+      None => write!(f, "Value({:?})", value_type),
+    }
+  }
+}
+
+/// Executes a fallible closure in a value's associated context, or the active
+/// context if the value has none.
+fn with_context<T, F>(value: &Value, callback: F) -> Option<T>
+where
+  F: Fn() -> Result<T>,
+{
+  match Context::exec_with_value(value, |_| callback()) {
+    Ok(Some(result)) => result.ok(),
+    Ok(None) => callback().ok(),
+    Err(_) => None,
   }
 }
 
@@ -285,7 +384,8 @@ reference!(Value);
 
 #[cfg(test)]
 mod tests {
-  use crate::{test, value, Property};
+  use crate::{script, test, value, Context, Error, Property};
+  use matches::assert_matches;
 
   #[test]
   fn json_conversion() {
@@ -304,4 +404,34 @@ mod tests {
       assert_eq!(object.get(guard, &property).to_integer(guard), 1337);
     });
   }
+
+  #[test]
+  fn marshal() {
+    let (runtime, context) = test::setup_env();
+    let other = Context::new(&runtime).unwrap();
+    let (_foreign_runtime, foreign) = test::setup_env();
+
+    let marshalled = context
+      .exec_with(|guard| {
+        let value = script::eval(guard, "({ foo: 'bar' })").unwrap();
+        assert_matches!(value.marshal_to(&foreign), Err(Error::RuntimeMismatch));
+        value.marshal_to(&other).unwrap()
+      })
+      .unwrap();
+
+    // Primitives without a context are copied into the foreign runtime
+    let number = context
+      .exec_with(|guard| value::Number::from_double(guard, 1.5).marshal_to(&foreign).unwrap())
+      .unwrap();
+    foreign
+      .exec_with(|guard| assert_eq!(number.get(guard).unwrap().to_double(guard), 1.5))
+      .unwrap();
+
+    other
+      .exec_with(|guard| {
+        let value = marshalled.get(guard).unwrap();
+        assert_eq!(value.to_json(guard).unwrap(), r#"{"foo":"bar"}"#);
+      })
+      .unwrap();
+  }
 }
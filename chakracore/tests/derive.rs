@@ -0,0 +1,95 @@
+#![cfg(feature = "derive")]
+use chakracore::{binding, js_class, js_function, script, Context, Runtime};
+
+#[js_class(name = "Vector")]
+struct Point {
+  x: f64,
+  y: f64,
+}
+
+#[js_class]
+impl Point {
+  #[js(constructor)]
+  fn new(x: f64, y: f64) -> Result<Self, String> {
+    if x.is_finite() && y.is_finite() {
+      Ok(Point { x, y })
+    } else {
+      Err("coordinates must be finite".to_string())
+    }
+  }
+
+  fn len(&self) -> f64 {
+    (self.x * self.x + self.y * self.y).sqrt()
+  }
+
+  fn scale(&mut self, factor: f64) {
+    self.x *= factor;
+    self.y *= factor;
+  }
+
+  #[js(getter)]
+  fn x(&self) -> f64 {
+    self.x
+  }
+
+  #[js(setter)]
+  fn set_x(&mut self, x: f64) {
+    self.x = x;
+  }
+
+  #[js(name = "origin")]
+  fn zero() -> i32 {
+    0
+  }
+
+  #[js(skip)]
+  #[allow(dead_code)]
+  fn internal(&self) {}
+}
+
+#[js_function]
+fn add(a: i32, b: Option<i32>) -> i32 {
+  a + b.unwrap_or(0)
+}
+
+#[test]
+fn class_binding() {
+  let runtime = Runtime::new().unwrap();
+  let context = Context::new(&runtime).unwrap();
+  context
+    .exec_with(|guard| {
+      binding::register_class::<Point>(guard);
+
+      let result = script::eval(
+        guard,
+        "
+        var point = new Vector(3, 4);
+        point.scale(2);
+        point.x = point.x / 2;
+        [point.len(), point.x, Vector.origin()].join()",
+      )
+      .unwrap();
+      assert_eq!(result.to_string(guard), "8.54400374531753,3,0");
+
+      let error = script::eval(guard, "new Vector(Infinity, 0)").unwrap_err();
+      assert!(error.to_string().contains("coordinates must be finite"));
+    })
+    .unwrap();
+}
+
+#[test]
+fn function_binding() {
+  let runtime = Runtime::new().unwrap();
+  let context = Context::new(&runtime).unwrap();
+  context
+    .exec_with(|guard| {
+      binding::register_function::<js_add>(guard);
+
+      let result = script::eval(guard, "add(5, 10) + add(5)").unwrap();
+      assert_eq!(result.to_integer(guard), 20);
+
+      let error = script::eval(guard, "add('5')").unwrap_err();
+      assert!(error.to_string().contains("TypeError"));
+    })
+    .unwrap();
+}